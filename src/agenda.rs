@@ -0,0 +1,106 @@
+//! List of the classes on every day of a date range, used by the `agenda` command.
+
+use crate::calendar::schedule::Schedule;
+use crate::week_view::{class_lines, class_state, format_class_line};
+use chrono::{Datelike, NaiveDate, NaiveDateTime};
+use eyre::eyre;
+use qolor::color::BasicColor::Green;
+use qolor::shorthands::Formattable;
+
+/// Renders the classes between `from` and `to` (inclusive), grouped by date.
+///
+/// Dates before the first or after the last day of the schedule are marked as outside the semester,
+/// and other dates missing from the schedule (e.g. weekends) as days without classes.
+pub fn render_agenda(
+    schedule: &Schedule,
+    from: NaiveDate,
+    to: NaiveDate,
+    now: NaiveDateTime,
+) -> eyre::Result<String> {
+    if from > to {
+        return Err(eyre!("The range ends ({}) before it starts ({})", to, from));
+    }
+
+    let first_day = schedule.weeks.iter().flatten().flatten().min();
+    let last_day = schedule.weeks.iter().flatten().flatten().max();
+
+    let mut output = vec![format!(" - {} – {} - ", from, to).bg(Green).to_ansi()];
+
+    for date in from.iter_days().take_while(|date| *date <= to) {
+        let is_today = date == now.date();
+        let heading = format!("{} {}", date.weekday(), date);
+
+        let Ok((week_no, weekday)) = schedule.get_day(date) else {
+            let outside = first_day.is_none_or(|first| date < *first) || last_day.is_none_or(|last| date > *last);
+            let note = if outside { "outside the semester" } else { "not a day of the schedule" };
+
+            output.push(format!("{} ({})", heading, note).dim().to_ansi());
+            continue;
+        };
+
+        let mut heading = format!("{} | Week {}", heading, week_no);
+        if weekday != date.weekday() {
+            heading = format!("{}, {} schedule", heading, weekday);
+        }
+        output.push(if is_today { heading.bg(Green).to_ansi() } else { heading.bold().to_ansi() });
+
+        let mut classes: Vec<_> = schedule.get_classes_on(week_no, weekday).collect();
+        classes.sort_by_key(|class| class.time.start);
+
+        if classes.is_empty() {
+            output.push("  No classes".dim().to_ansi());
+        }
+        for class in classes {
+            let mut lines = class_lines(schedule, class)?;
+            // Time goes first, so that classes line up
+            lines.swap(0, 1);
+            let state = class_state(class, date, now);
+            output.push(format_class_line(format!("  {}", lines.join("  ")), class, state, is_today));
+        }
+    }
+
+    Ok(output.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn render_range() {
+        let schedule: Schedule = serde_json::from_value(json!({
+            "weeks": [["2025-10-20", "2025-10-21", "2025-10-22", "2025-10-23", "2025-10-24"],
+                      ["2025-10-27", "2025-10-28", "2025-10-29", "2025-10-31", "2025-10-30"]],
+            "subjects": { "aisd": { "name": "Algorytmy", "short": "AiSD" } },
+            "teachers": {},
+            "schedule": [
+                { "subject": "aisd", "type": "lab", "day": "monday", "time": "10:15-12:00", "location": "online" },
+                { "subject": "aisd", "type": "lecture", "day": "monday", "time": "8:15-10:00" }
+            ]
+        }))
+        .unwrap();
+        let date = |month, day| NaiveDate::from_ymd_opt(2025, month, day).unwrap();
+        let now = date(10, 1).and_hms_opt(12, 0, 0).unwrap();
+
+        let agenda = render_agenda(&schedule, date(10, 19), date(10, 21), now).unwrap();
+        assert_eq!(
+            agenda.lines().collect::<Vec<_>>(),
+            vec![
+                " - 2025-10-19 – 2025-10-21 - ",
+                "Sun 2025-10-19 (outside the semester)",
+                "Mon 2025-10-20 | Week 1",
+                "  08:15-10:00  📚 AiSD",
+                "  10:15-12:00  🧪 AiSD  Online",
+                "Tue 2025-10-21 | Week 1",
+                "  No classes",
+            ]
+        );
+
+        let agenda = render_agenda(&schedule, date(10, 25), date(10, 30), now).unwrap();
+        assert!(agenda.contains("Sat 2025-10-25 (not a day of the schedule)"));
+        assert!(agenda.contains("Thu 2025-10-30 | Week 2, Fri schedule"));
+
+        assert!(render_agenda(&schedule, date(10, 21), date(10, 20), now).is_err());
+    }
+}
@@ -0,0 +1,548 @@
+//! Synchronization of generated events with a CalDAV calendar collection.
+//!
+//! Every event is stored as a separate `<UID>.ics` resource.
+//! A state file remembers the content hash and the server `ETag` of every uploaded event,
+//! so that only events which changed locally or were modified on the server are uploaded again.
+//! Only resources uploaded by previous runs, as recorded in the state file, are deleted
+//! once their events are no longer generated, and only if they weren't modified on the server since.
+//! Any other resources in the collection are left alone.
+
+use crate::calendar::revisions::event_content_hash;
+use crate::http::{percent_decode, percent_encode};
+use crate::ical::vcalendar::VCalendar;
+use crate::ical::vevent::VEvent;
+use crate::ical::SerializeToICal;
+use base64::Engine;
+use eyre::eyre;
+use log::{debug, info, warn};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::path::Path;
+
+const PROPFIND_ETAGS: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:"><d:prop><d:getetag/></d:prop></d:propfind>
+"#;
+
+/// Response of a CalDAV server.
+struct DavResponse {
+    status: u16,
+    etag: Option<String>,
+    body: String,
+}
+
+/// Minimal client of a single CalDAV calendar collection.
+pub struct CalDavClient {
+    agent: ureq::Agent,
+    /// URL of the collection, ending with `/`.
+    collection_url: String,
+    /// Value of the `Authorization` header, if any.
+    authorization: Option<String>,
+}
+
+impl CalDavClient {
+    pub fn new(collection_url: &str, credentials: Option<(&str, &str)>) -> Self {
+        let mut collection_url = collection_url.to_string();
+        if !collection_url.ends_with('/') {
+            collection_url.push('/');
+        }
+
+        let authorization = credentials.map(|(user, password)| {
+            let encoded = base64::engine::general_purpose::STANDARD.encode(format!("{}:{}", user, password));
+            format!("Basic {}", encoded)
+        });
+
+        CalDavClient {
+            agent: ureq::AgentBuilder::new().build(),
+            collection_url,
+            authorization,
+        }
+    }
+
+    /// Sends a request, treating error status codes as regular responses.
+    fn send(&self, request: ureq::Request, body: Option<&[u8]>) -> eyre::Result<DavResponse> {
+        let request = match &self.authorization {
+            Some(authorization) => request.set("Authorization", authorization),
+            None => request,
+        };
+
+        let result = match body {
+            Some(body) => request.send_bytes(body),
+            None => request.call(),
+        };
+
+        let response = match result {
+            Ok(response) => response,
+            Err(ureq::Error::Status(_, response)) => response,
+            Err(e) => return Err(e.into()),
+        };
+
+        Ok(DavResponse {
+            status: response.status(),
+            etag: response.header("ETag").map(str::to_string),
+            body: response.into_string()?,
+        })
+    }
+
+    fn resource_url(&self, name: &str) -> String {
+        format!("{}{}", self.collection_url, percent_encode(name))
+    }
+
+    /// Lists the resources of the collection, as names mapped to their `ETag`s.
+    pub fn list(&self) -> eyre::Result<BTreeMap<String, Option<String>>> {
+        let request = self
+            .agent
+            .request("PROPFIND", &self.collection_url)
+            .set("Depth", "1")
+            .set("Content-Type", "application/xml; charset=utf-8");
+
+        let response = self.send(request, Some(PROPFIND_ETAGS.as_bytes()))?;
+        if response.status != 207 {
+            return Err(eyre!("PROPFIND {} failed with status {}", self.collection_url, response.status));
+        }
+
+        Ok(parse_multistatus(&response.body)
+            .into_iter()
+            .filter(|(href, _)| !href.ends_with('/'))
+            .filter_map(|(href, etag)| {
+                let name = href.rsplit('/').next()?;
+                Some((percent_decode(name), etag))
+            })
+            .collect())
+    }
+
+    /// Uploads a resource, returning its new `ETag` if the server sent it.
+    ///
+    /// With `etag`, the resource is only replaced if it wasn't modified since.
+    /// Without it, the resource is only created if it doesn't exist yet.
+    pub fn put(&self, name: &str, body: &[u8], etag: Option<&str>) -> eyre::Result<Option<String>> {
+        let request = self
+            .agent
+            .put(&self.resource_url(name))
+            .set("Content-Type", "text/calendar; charset=utf-8");
+        let request = match etag {
+            Some(etag) => request.set("If-Match", etag),
+            None => request.set("If-None-Match", "*"),
+        };
+
+        let response = self.send(request, Some(body))?;
+        match response.status {
+            200..=299 => Ok(response.etag),
+            412 => Err(eyre!("{} was modified by someone else", name)),
+            status => Err(eyre!("PUT {} failed with status {}", name, status)),
+        }
+    }
+
+    /// Deletes a resource, if it exists.
+    ///
+    /// With `etag`, the resource is only deleted if it wasn't modified since.
+    pub fn delete(&self, name: &str, etag: Option<&str>) -> eyre::Result<()> {
+        let request = self.agent.delete(&self.resource_url(name));
+        let request = match etag {
+            Some(etag) => request.set("If-Match", etag),
+            None => request,
+        };
+
+        let response = self.send(request, None)?;
+        match response.status {
+            200..=299 | 404 => Ok(()),
+            412 => Err(eyre!("{} was modified by someone else", name)),
+            status => Err(eyre!("DELETE {} failed with status {}", name, status)),
+        }
+    }
+}
+
+/// Extracts the `href` and `getetag` of every `response` of a WebDAV `multistatus` document.
+///
+/// Namespace prefixes are ignored.
+fn parse_multistatus(xml: &str) -> Vec<(String, Option<String>)> {
+    fn unescape(text: &str) -> String {
+        text.replace("&lt;", "<")
+            .replace("&gt;", ">")
+            .replace("&quot;", "\"")
+            .replace("&apos;", "'")
+            .replace("&amp;", "&")
+    }
+
+    let mut responses = Vec::new();
+    let mut href = None;
+    let mut etag = None;
+    let mut rest = xml;
+
+    while let Some(start) = rest.find('<') {
+        let Some(end) = rest[start..].find('>') else {
+            break;
+        };
+        let tag = &rest[start + 1..start + end];
+        let after = &rest[start + end + 1..];
+        let text = &after[..after.find('<').unwrap_or(after.len())];
+
+        let closing = tag.starts_with('/');
+        let name = tag
+            .trim_start_matches('/')
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .trim_end_matches('/');
+        let local_name = name.rsplit(':').next().unwrap_or(name);
+
+        match (local_name, closing) {
+            ("href", false) => href = Some(unescape(text.trim())),
+            ("getetag", false) if !tag.ends_with('/') => etag = Some(unescape(text.trim())),
+            ("response", true) => {
+                if let Some(href) = href.take() {
+                    responses.push((href, etag.take()));
+                }
+                etag = None;
+            }
+            _ => {}
+        }
+
+        rest = after;
+    }
+
+    responses
+}
+
+/// What is known about an uploaded event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+struct SyncedEvent {
+    /// Hash of the content of the resource's events, see [`event_content_hash`], separated with commas.
+    hash: String,
+    /// `ETag` of the resource after it was uploaded, if the server sent it.
+    etag: Option<String>,
+}
+
+/// State of the synchronization, saved between runs.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SyncState {
+    events: BTreeMap<String, SyncedEvent>,
+}
+
+impl SyncState {
+    /// Loads the state file, or returns an empty state if it doesn't exist.
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        if !path.exists() {
+            return Ok(SyncState::default());
+        }
+
+        Ok(serde_json::from_reader(File::open(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> eyre::Result<()> {
+        serde_json::to_writer_pretty(File::create(path)?, self)?;
+        Ok(())
+    }
+}
+
+/// Numbers of resources affected by a synchronization.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct SyncSummary {
+    pub created: usize,
+    pub updated: usize,
+    pub unchanged: usize,
+    pub deleted: usize,
+    pub failed: usize,
+}
+
+/// Wraps the events of the calendar with the same UID (a single event, or a recurring event with the overrides
+/// of its occurrences) in a calendar of their own, as stored in a CalDAV resource.
+fn event_resource(calendar: &VCalendar, events: &[&VEvent]) -> eyre::Result<Vec<u8>> {
+    let mut resource = VCalendar {
+        prod_id: calendar.prod_id.clone(),
+        version: calendar.version.clone(),
+        timezones: Vec::new(),
+        events: events.iter().map(|event| (*event).clone()).collect(),
+        extra_properties: Vec::new(),
+    };
+    resource.add_referenced_timezones();
+
+    let mut buffer = Vec::new();
+    resource.serialize_to_ical(&mut buffer)?;
+    Ok(buffer)
+}
+
+/// Makes the collection contain exactly the events of the calendar,
+/// among the resources uploaded by this and previous synchronizations with the same state.
+///
+/// Failures of single resources are logged and counted, and don't stop the synchronization.
+/// Resources which can't be deleted because they were modified on the server are forgotten, and kept.
+pub fn sync_calendar(
+    client: &CalDavClient,
+    calendar: &VCalendar,
+    state: &mut SyncState,
+) -> eyre::Result<SyncSummary> {
+    let remote = client.list()?;
+    let mut summary = SyncSummary::default();
+
+    let mut uids: Vec<&str> = Vec::new();
+    for event in &calendar.events {
+        if !uids.contains(&event.uid.as_str()) {
+            uids.push(&event.uid);
+        }
+    }
+
+    for uid in uids {
+        let events: Vec<&VEvent> = calendar.events.iter().filter(|event| event.uid == uid).collect();
+        let event = events[0];
+        let name = format!("{}.ics", event.uid);
+        let hash = events
+            .iter()
+            .map(|event| event_content_hash(event))
+            .collect::<eyre::Result<Vec<_>>>()?
+            .join(",");
+        let remote_etag = remote.get(&name);
+
+        let up_to_date = match (remote_etag, state.events.get(&event.uid)) {
+            (Some(Some(etag)), Some(synced)) => synced.hash == hash && synced.etag.as_ref() == Some(etag),
+            _ => false,
+        };
+
+        if up_to_date {
+            summary.unchanged += 1;
+            continue;
+        }
+
+        // Resources without an ETag can't be updated conditionally, so they're overwritten
+        let precondition = match remote_etag {
+            Some(Some(etag)) => Some(etag.as_str()),
+            Some(None) => Some("*"),
+            None => None,
+        };
+
+        match client.put(&name, &event_resource(calendar, &events)?, precondition) {
+            Ok(etag) => {
+                debug!("Uploaded {}", name);
+                if remote_etag.is_some() {
+                    summary.updated += 1;
+                } else {
+                    summary.created += 1;
+                }
+                state.events.insert(event.uid.clone(), SyncedEvent { hash, etag });
+            }
+            Err(e) => {
+                warn!("Failed to upload {}: {}", event.summary, e);
+                summary.failed += 1;
+            }
+        }
+    }
+
+    let removed: Vec<(String, SyncedEvent)> = state
+        .events
+        .iter()
+        .filter(|(uid, _)| !calendar.events.iter().any(|event| event.uid == **uid))
+        .map(|(uid, synced)| (uid.clone(), synced.clone()))
+        .collect();
+
+    for (uid, synced) in removed {
+        let name = format!("{}.ics", uid);
+
+        if remote.contains_key(&name) {
+            match client.delete(&name, synced.etag.as_deref()) {
+                Ok(()) => {
+                    debug!("Deleted {}", name);
+                    summary.deleted += 1;
+                }
+                Err(e) => {
+                    warn!("Failed to delete {}: {}", name, e);
+                    summary.failed += 1;
+                    // Deletions failing for other reasons are retried on the next synchronization
+                    if synced.etag.is_none() || remote[&name] == synced.etag {
+                        continue;
+                    }
+                }
+            }
+        }
+
+        state.events.remove(&uid);
+    }
+
+    info!(
+        "Created {}, updated {}, deleted {}, unchanged {}, failed {}",
+        summary.created, summary.updated, summary.deleted, summary.unchanged, summary.failed
+    );
+
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::schedule::{ICalOptions, Schedule};
+    use chrono_tz::Tz;
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+    use tiny_http::{Header, Response, Server};
+
+    /// Resources of the stand-in server, as names mapped to their body and `ETag`.
+    type Resources = Arc<Mutex<HashMap<String, (String, String)>>>;
+
+    /// Starts a stand-in CalDAV server of a single collection at `/calendar/`.
+    ///
+    /// Returns the collection URL, its resources and the log of modifying requests.
+    fn start_server() -> (String, Resources, Arc<Mutex<Vec<String>>>) {
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let url = format!("http://{}/calendar/", server.server_addr().to_ip().unwrap());
+        let resources: Resources = Default::default();
+        let log: Arc<Mutex<Vec<String>>> = Default::default();
+
+        let (thread_resources, thread_log) = (resources.clone(), log.clone());
+        std::thread::spawn(move || {
+            let mut next_etag = 0;
+
+            for mut request in server.incoming_requests() {
+                let method = request.method().to_string();
+                let name = percent_decode(request.url().trim_start_matches("/calendar/"));
+                let header = |name: &'static str| {
+                    request
+                        .headers()
+                        .iter()
+                        .find(|h| h.field.equiv(name))
+                        .map(|h| h.value.to_string())
+                };
+                let (if_match, if_none_match) = (header("If-Match"), header("If-None-Match"));
+                let mut body = String::new();
+                request.as_reader().read_to_string(&mut body).unwrap();
+
+                let mut resources = thread_resources.lock().unwrap();
+                let existing = resources.get(&name).map(|(_, etag)| etag.clone());
+
+                let response = match method.as_str() {
+                    "PROPFIND" => {
+                        let mut xml = String::from(
+                            "<?xml version=\"1.0\"?><D:multistatus xmlns:D=\"DAV:\">\
+                             <D:response><D:href>/calendar/</D:href></D:response>",
+                        );
+                        for (name, (_, etag)) in resources.iter() {
+                            xml.push_str(&format!(
+                                "<D:response><D:href>/calendar/{}</D:href><D:propstat><D:prop>\
+                                 <D:getetag>{}</D:getetag></D:prop></D:propstat></D:response>",
+                                percent_encode(name),
+                                etag.replace('"', "&quot;")
+                            ));
+                        }
+                        xml.push_str("</D:multistatus>");
+                        Response::from_string(xml).with_status_code(207)
+                    }
+                    "PUT" if if_none_match.is_some() && existing.is_some() => {
+                        Response::from_string("").with_status_code(412)
+                    }
+                    "PUT" if if_match.is_some() && if_match.as_deref() != existing.as_deref() => {
+                        Response::from_string("").with_status_code(412)
+                    }
+                    "PUT" => {
+                        next_etag += 1;
+                        let etag = format!("\"{}\"", next_etag);
+                        resources.insert(name.clone(), (body, etag.clone()));
+                        thread_log.lock().unwrap().push(format!("PUT {}", name));
+                        Response::from_string("")
+                            .with_status_code(if existing.is_some() { 204 } else { 201 })
+                            .with_header(Header::from_bytes("ETag", etag).unwrap())
+                    }
+                    "DELETE" if if_match.is_some() && if_match.as_deref() != existing.as_deref() => {
+                        Response::from_string("").with_status_code(412)
+                    }
+                    "DELETE" => {
+                        resources.remove(&name);
+                        thread_log.lock().unwrap().push(format!("DELETE {}", name));
+                        Response::from_string("").with_status_code(204)
+                    }
+                    _ => Response::from_string("").with_status_code(405),
+                };
+
+                request.respond(response).unwrap();
+            }
+        });
+
+        (url, resources, log)
+    }
+
+    fn schedule(classes: serde_json::Value) -> Schedule {
+        serde_json::from_value(json!({
+            "calendar": { "id": "sync", "uidDomain": "example.com" },
+            "weeks": [["2025-10-20", "2025-10-21", "2025-10-22", "2025-10-23", "2025-10-24"]],
+            "subjects": { "aisd": { "name": "AiSD" } },
+            "teachers": {},
+            "schedule": classes
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn multistatus_parsing() {
+        let xml = r#"<multistatus xmlns="DAV:"><response><href>/cal/a%40b.ics</href>
+            <propstat><prop><getetag>"1&amp;2"</getetag></prop></propstat></response>
+            <response><href>/cal/c.ics</href><propstat><prop><getetag/></prop></propstat></response>
+            </multistatus>"#;
+
+        assert_eq!(
+            parse_multistatus(xml),
+            vec![
+                ("/cal/a%40b.ics".to_string(), Some("\"1&2\"".to_string())),
+                ("/cal/c.ics".to_string(), None),
+            ]
+        );
+    }
+
+    #[test]
+    fn sync_with_server() {
+        let (url, resources, log) = start_server();
+        let client = CalDavClient::new(&url, Some(("user", "password")));
+        let tz = Tz::Europe__Warsaw;
+        let mut state = SyncState::default();
+
+        // Resources not uploaded by the synchronization are kept, even with the same UID domain
+        for foreign in ["foreign.ics", "other-schedule@example.com.ics"] {
+            resources
+                .lock()
+                .unwrap()
+                .insert(foreign.to_string(), (String::new(), "\"x\"".to_string()));
+        }
+
+        let original = schedule(json!([
+            { "subject": "aisd", "type": "lecture", "day": "monday", "time": "8:15-10:00" },
+            { "subject": "aisd", "type": "lab", "day": "tuesday", "time": "10:15-12:00" }
+        ]));
+        let calendar = original.to_ical(&tz, &ICalOptions::default()).unwrap();
+
+        let summary = sync_calendar(&client, &calendar, &mut state).unwrap();
+        assert_eq!(summary.created, 7);
+        assert_eq!(resources.lock().unwrap().len(), 9);
+
+        // Regenerating changes DTSTAMP only, so nothing is uploaded again
+        let calendar = original.to_ical(&tz, &ICalOptions::default()).unwrap();
+        log.lock().unwrap().clear();
+
+        let summary = sync_calendar(&client, &calendar, &mut state).unwrap();
+        assert_eq!(summary.unchanged, 7);
+        assert!(log.lock().unwrap().is_empty());
+
+        let changed = schedule(json!([
+            { "subject": "aisd", "type": "lecture", "day": "monday", "time": "8:30-10:00" }
+        ]));
+        let calendar = changed.to_ical(&tz, &ICalOptions::default()).unwrap();
+
+        let summary = sync_calendar(&client, &calendar, &mut state).unwrap();
+        assert_eq!(
+            summary,
+            SyncSummary { created: 0, updated: 1, unchanged: 5, deleted: 1, failed: 0 }
+        );
+        assert!(resources.lock().unwrap().contains_key("foreign.ics"));
+        assert!(resources.lock().unwrap().contains_key("other-schedule@example.com.ics"));
+        assert_eq!(state.events.len(), 6);
+
+        let lecture = &calendar.events.last().unwrap().uid;
+        assert!(resources.lock().unwrap()[&format!("{}.ics", lecture)].0.contains("T083000"));
+
+        // Resources modified on the server since they were uploaded aren't deleted
+        let modified = format!("{}.ics", lecture);
+        resources.lock().unwrap().get_mut(&modified).unwrap().1 = "\"edited\"".to_string();
+        let calendar = schedule(json!([])).to_ical(&tz, &ICalOptions::default()).unwrap();
+
+        let summary = sync_calendar(&client, &calendar, &mut state).unwrap();
+        assert_eq!(summary.failed, 1);
+        assert!(resources.lock().unwrap().contains_key(&modified));
+        assert!(!state.events.contains_key(lecture));
+    }
+}
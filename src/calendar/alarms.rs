@@ -0,0 +1,104 @@
+use crate::calendar::class::Class;
+use crate::calendar::class_type::ClassType;
+use serde::{de, Deserialize, Deserializer};
+use std::collections::HashMap;
+
+/// Reminder before a class: either a number of minutes before it starts, or no reminder at all.
+///
+/// In the schedule file, it's defined as a number of minutes, or `false` to disable the reminder.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Reminder {
+    Disabled,
+    MinutesBefore(u32),
+}
+
+impl<'de> Deserialize<'de> for Reminder {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum RawReminder {
+            Minutes(u32),
+            Enabled(bool),
+        }
+
+        match RawReminder::deserialize(deserializer)? {
+            RawReminder::Minutes(minutes) => Ok(Reminder::MinutesBefore(minutes)),
+            RawReminder::Enabled(false) => Ok(Reminder::Disabled),
+            RawReminder::Enabled(true) => Err(de::Error::invalid_value(
+                de::Unexpected::Bool(true),
+                &"a number of minutes or false",
+            )),
+        }
+    }
+}
+
+/// Reminder settings, defined in the `alarms` property of the schedule.
+///
+/// The most specific setting wins: the class's own `alarm`, then the subject's, then the class type's,
+/// then the default.
+#[derive(Debug, Default, Deserialize)]
+pub struct AlarmSettings {
+    pub default: Option<Reminder>,
+    #[serde(default)]
+    pub types: HashMap<ClassType, Reminder>,
+    #[serde(default)]
+    pub subjects: HashMap<String, Reminder>,
+}
+
+impl AlarmSettings {
+    /// Returns the number of minutes before the class its reminder should trigger, if any.
+    pub fn minutes_before(&self, class: &Class) -> Option<u32> {
+        let reminder = class
+            .alarm
+            .or_else(|| self.subjects.get(&class.subject).copied())
+            .or_else(|| self.types.get(&class.class_type).copied())
+            .or(self.default)?;
+
+        match reminder {
+            Reminder::Disabled => None,
+            Reminder::MinutesBefore(minutes) => Some(minutes),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn most_specific_reminder_wins() {
+        let settings: AlarmSettings = serde_json::from_value(json!({
+            "default": 15,
+            "types": { "lab": 30, "pe": false },
+            "subjects": { "aisd": 5 }
+        }))
+        .unwrap();
+
+        let class = |subject: &str, class_type: &str, alarm: Option<serde_json::Value>| {
+            let mut json = json!({
+                "subject": subject,
+                "type": class_type,
+                "day": "monday",
+                "time": "8:15-10:00",
+            });
+            if let Some(alarm) = alarm {
+                json["alarm"] = alarm;
+            }
+            serde_json::from_value::<Class>(json).unwrap()
+        };
+
+        assert_eq!(settings.minutes_before(&class("ang", "lecture", None)), Some(15));
+        assert_eq!(settings.minutes_before(&class("ang", "lab", None)), Some(30));
+        assert_eq!(settings.minutes_before(&class("ang", "pe", None)), None);
+        assert_eq!(settings.minutes_before(&class("aisd", "lab", None)), Some(5));
+        assert_eq!(settings.minutes_before(&class("aisd", "lab", Some(json!(false)))), None);
+        assert_eq!(settings.minutes_before(&class("ang", "pe", Some(json!(10)))), Some(10));
+
+        assert!(serde_json::from_value::<Reminder>(json!(true)).is_err());
+        assert_eq!(AlarmSettings::default().minutes_before(&class("ang", "lab", None)), None);
+    }
+}
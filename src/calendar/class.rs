@@ -1,13 +1,15 @@
 use std::num::NonZero;
 use super::class_type::ClassType;
+use crate::calendar::alarms::Reminder;
 use crate::calendar::periods::NaiveTimePeriod;
 use crate::calendar::Weeks;
 use chrono::Weekday;
 use serde::Deserialize;
+use std::fmt::{Display, Formatter};
 
 #[derive(Deserialize)]
 #[serde(remote = "Weekday")]
-enum WeekdayDef {
+pub enum WeekdayDef {
     #[serde(rename = "monday")]
     Mon = 0,
     #[serde(rename = "tuesday")]
@@ -24,7 +26,7 @@ enum WeekdayDef {
     Sun = 6,
 }
 
-#[derive(Debug, Deserialize, PartialEq, Eq)]
+#[derive(Debug, Clone, Deserialize, PartialEq, Eq)]
 #[serde(untagged)]
 pub enum Location {
     Offline { building: String, room: String },
@@ -32,6 +34,15 @@ pub enum Location {
     Online,
 }
 
+impl Display for Location {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Location::Offline { building, room } => write!(f, "{}/{}", room, building),
+            Location::Online => write!(f, "Online"),
+        }
+    }
+}
+
 fn deserialize_online<'de, D>(deserializer: D) -> Result<(), D::Error>
 where
     D: serde::Deserializer<'de>,
@@ -46,6 +57,8 @@ where
 
 #[derive(Debug, Deserialize)]
 pub struct Class {
+    /// Explicit identifier of the class, used to derive stable event UIDs.
+    pub id: Option<String>,
     pub subject: String,
     #[serde(rename = "type")]
     pub class_type: ClassType,
@@ -55,6 +68,8 @@ pub struct Class {
     pub location: Option<Location>,
     pub teachers: Option<Vec<String>>,
     pub weeks: Option<Weeks>,
+    /// Reminder overriding the one from the schedule's alarm settings.
+    pub alarm: Option<Reminder>,
 }
 
 impl Class {
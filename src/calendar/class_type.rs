@@ -1,7 +1,7 @@
 use qolor::color::BasicColor;
 use serde::Deserialize;
 
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ClassType {
     Lecture,
@@ -14,6 +14,54 @@ pub enum ClassType {
 }
 
 impl ClassType {
+    pub const ALL: [ClassType; 7] = [
+        ClassType::Lecture,
+        ClassType::Lab,
+        ClassType::Exercise,
+        ClassType::Seminar,
+        ClassType::PE,
+        ClassType::Languages,
+        ClassType::Project,
+    ];
+
+    /// Finds the class type with the given identifier, as returned by [`ClassType::to_id`].
+    pub fn from_id(id: &str) -> Option<ClassType> {
+        Self::ALL.into_iter().find(|t| t.to_id() == id)
+    }
+
+    /// Finds the class type with the given name, as returned by [`ClassType::to_name`].
+    pub fn from_name(name: &str) -> Option<ClassType> {
+        Self::ALL.into_iter().find(|t| t.to_name() == name)
+    }
+
+    /// Finds the class type whose emoji, as returned by [`ClassType::to_emoji`], starts the text.
+    ///
+    /// Returns the class type and the rest of the text, without leading whitespace.
+    pub fn strip_emoji(text: &str) -> Option<(ClassType, &str)> {
+        Self::ALL.into_iter().find_map(|t| {
+            let emoji = t.to_emoji();
+            // Emoji variation selectors are often dropped by calendar apps
+            let bare = emoji.trim_end_matches('\u{fe0f}');
+
+            text.strip_prefix(emoji)
+                .or_else(|| text.strip_prefix(bare))
+                .map(|rest| (t, rest.trim_start_matches('\u{fe0f}').trim_start()))
+        })
+    }
+
+    /// Returns the identifier of the class type, as used in the schedule file.
+    pub fn to_id(self) -> &'static str {
+        match self {
+            ClassType::Lecture => "lecture",
+            ClassType::Lab => "lab",
+            ClassType::Exercise => "exercise",
+            ClassType::Seminar => "seminar",
+            ClassType::PE => "pe",
+            ClassType::Languages => "languages",
+            ClassType::Project => "project",
+        }
+    }
+
     pub fn to_name(self) -> &'static str {
         match self {
             ClassType::Lecture => "Wykład",
@@ -38,6 +86,21 @@ impl ClassType {
         }
     }
 
+    /// Returns the default CSS3 color name of the class type's events, used by the `COLOR` property.
+    ///
+    /// Matches the Google Calendar event colors assigned by `colorer.gs`.
+    pub fn to_css_color(self) -> &'static str {
+        match self {
+            ClassType::Lecture => "mediumpurple",
+            ClassType::Lab => "seagreen",
+            ClassType::Exercise => "tomato",
+            ClassType::Seminar => "gold",
+            ClassType::PE => "lightcoral",
+            ClassType::Languages => "darkturquoise",
+            ClassType::Project => "gray",
+        }
+    }
+
     pub fn to_color(self) -> BasicColor {
         match self {
             ClassType::Lecture => BasicColor::Magenta,
@@ -55,6 +118,19 @@ impl ClassType {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_strip_emoji() {
+        assert_eq!(
+            ClassType::strip_emoji("🧪 AiSD"),
+            Some((ClassType::Lab, "AiSD"))
+        );
+        assert_eq!(
+            ClassType::strip_emoji("🛠 Projekt zespołowy"),
+            Some((ClassType::Project, "Projekt zespołowy"))
+        );
+        assert_eq!(ClassType::strip_emoji("AiSD"), None);
+    }
+
     #[test]
     fn test_deserialize() {
         let json = "\"lecture\"";
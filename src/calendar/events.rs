@@ -0,0 +1,431 @@
+//! Events other than classes, defined in the `events` property of the schedule:
+//! one-off events such as exams and colloquia, deadlines, and teachers' office hours.
+
+use crate::calendar::class::{Location, WeekdayDef};
+use crate::calendar::periods::NaiveTimePeriod;
+use crate::calendar::schedule::{deserialize_optional_date, Schedule};
+use crate::calendar::Weeks;
+use crate::ical::valarm::VAlarm;
+use crate::ical::vevent::{EventTime, VEvent};
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeDelta, Weekday};
+use chrono_tz::Tz;
+use eyre::eyre;
+use qolor::color::BasicColor;
+use serde::{de, Deserialize, Deserializer};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum EventKind {
+    Exam,
+    Colloquium,
+    Deadline,
+    OfficeHours,
+    Other,
+}
+
+impl EventKind {
+    /// Returns the identifier of the event kind, as used in the schedule file.
+    pub fn to_id(self) -> &'static str {
+        match self {
+            EventKind::Exam => "exam",
+            EventKind::Colloquium => "colloquium",
+            EventKind::Deadline => "deadline",
+            EventKind::OfficeHours => "officeHours",
+            EventKind::Other => "other",
+        }
+    }
+
+    pub fn to_name(self) -> &'static str {
+        match self {
+            EventKind::Exam => "Egzamin",
+            EventKind::Colloquium => "Kolokwium",
+            EventKind::Deadline => "Termin",
+            EventKind::OfficeHours => "Konsultacje",
+            EventKind::Other => "Wydarzenie",
+        }
+    }
+
+    pub fn to_emoji(self) -> &'static str {
+        match self {
+            EventKind::Exam => "🎓",
+            EventKind::Colloquium => "✍️",
+            EventKind::Deadline => "⏰",
+            EventKind::OfficeHours => "🙋",
+            EventKind::Other => "📌",
+        }
+    }
+
+    /// Returns the CSS3 color name of the event kind's events, used by the `COLOR` property.
+    pub fn to_css_color(self) -> &'static str {
+        match self {
+            EventKind::Exam => "crimson",
+            EventKind::Colloquium => "darkorange",
+            EventKind::Deadline => "firebrick",
+            EventKind::OfficeHours => "steelblue",
+            EventKind::Other => "slategray",
+        }
+    }
+
+    pub fn to_color(self) -> BasicColor {
+        match self {
+            EventKind::Exam => BasicColor::BrightRed,
+            EventKind::Colloquium => BasicColor::BrightYellow,
+            EventKind::Deadline => BasicColor::Red,
+            EventKind::OfficeHours => BasicColor::Blue,
+            EventKind::Other => BasicColor::White,
+        }
+    }
+}
+
+fn deserialize_optional_weekday<'de, D>(deserializer: D) -> Result<Option<Weekday>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    WeekdayDef::deserialize(deserializer).map(Some)
+}
+
+fn deserialize_optional_time<'de, D>(deserializer: D) -> Result<Option<NaiveTime>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let s = String::deserialize(deserializer)?;
+    NaiveTime::parse_from_str(s.trim(), "%H:%M")
+        .map(Some)
+        .map_err(|_| de::Error::invalid_value(de::Unexpected::Str(&s), &"expected HH:MM"))
+}
+
+/// An event other than a class.
+///
+/// Exams, colloquia and other events take place once, on their `date`, either at their `time`
+/// or for the whole day. Deadlines are points in time, `due` at the end of their `date` by default.
+/// Office hours of a `teacher` repeat on their `day` of every week (or of the `weeks`), like classes.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Event {
+    /// Explicit identifier of the event, used to derive stable event UIDs.
+    ///
+    /// Events without one keep their UIDs when edited, but those of the same kind, subject and teacher
+    /// are told apart by their order, so adding, removing or reordering such events changes their UIDs.
+    pub id: Option<String>,
+    #[serde(rename = "type")]
+    pub kind: EventKind,
+    /// Title of the event, by default the name of its subject, or of its teacher.
+    pub title: Option<String>,
+    /// Subject ID.
+    pub subject: Option<String>,
+    /// Teacher ID, required for office hours.
+    pub teacher: Option<String>,
+    #[serde(default, deserialize_with = "deserialize_optional_date")]
+    pub date: Option<NaiveDate>,
+    #[serde(default, deserialize_with = "deserialize_optional_weekday")]
+    pub day: Option<Weekday>,
+    pub weeks: Option<Weeks>,
+    pub time: Option<NaiveTimePeriod>,
+    #[serde(default, deserialize_with = "deserialize_optional_time")]
+    pub due: Option<NaiveTime>,
+    pub location: Option<Location>,
+    pub description: Option<String>,
+    /// Minutes before the event (or before a deadline is due) its reminders trigger.
+    #[serde(default)]
+    pub reminders: Vec<u32>,
+}
+
+/// When an event takes place on one of its dates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventSpan {
+    AllDay,
+    Period(NaiveTimePeriod),
+    Due(NaiveTime),
+}
+
+impl Event {
+    pub fn span(&self) -> EventSpan {
+        match (self.kind, self.time) {
+            (EventKind::Deadline, _) => EventSpan::Due(self.due.unwrap_or(NaiveTime::from_hms_opt(23, 59, 0).unwrap())),
+            (_, Some(time)) => EventSpan::Period(time),
+            (_, None) => EventSpan::AllDay,
+        }
+    }
+
+    /// Describes when the event takes place on a day, e.g. `all day` or `due 23:59`.
+    pub fn span_label(&self) -> String {
+        match self.span() {
+            EventSpan::AllDay => "all day".to_string(),
+            EventSpan::Period(time) => time.to_string(),
+            EventSpan::Due(time) => format!("due {}", time.format("%H:%M")),
+        }
+    }
+
+    /// Returns the start (or due time) of the event on a day, or `None` for all-day events.
+    pub fn start(&self) -> Option<NaiveTime> {
+        match self.span() {
+            EventSpan::AllDay => None,
+            EventSpan::Period(time) => Some(time.start),
+            EventSpan::Due(time) => Some(time),
+        }
+    }
+
+    fn describe(&self) -> String {
+        match (&self.id, &self.title) {
+            (Some(id), _) => format!("{} {}", self.kind.to_id(), id),
+            (None, Some(title)) => format!("{} {}", self.kind.to_id(), title),
+            (None, None) => self.kind.to_id().to_string(),
+        }
+    }
+}
+
+impl Schedule {
+    /// Ensures the events have the properties required by their kind, and refer to existing subjects and teachers.
+    pub fn check_events(&self) -> eyre::Result<()> {
+        for event in &self.events {
+            let name = event.describe();
+
+            if let Some(subject) = &event.subject {
+                if !self.subjects.contains_key(subject) {
+                    return Err(eyre!("{} has an unknown subject: {}", name, subject));
+                }
+            }
+            if let Some(teacher) = &event.teacher {
+                if !self.teachers.contains_key(teacher) {
+                    return Err(eyre!("{} has an unknown teacher: {}", name, teacher));
+                }
+            }
+
+            match event.kind {
+                EventKind::OfficeHours => {
+                    if event.teacher.is_none() || event.day.is_none() || event.time.is_none() {
+                        return Err(eyre!("{} needs a teacher, a day and a time", name));
+                    }
+                    if event.date.is_some() || event.due.is_some() {
+                        return Err(eyre!("{} repeats every week, it can't have a date or be due", name));
+                    }
+                }
+                EventKind::Deadline => {
+                    if event.date.is_none() {
+                        return Err(eyre!("{} needs a date", name));
+                    }
+                    if event.time.is_some() || event.day.is_some() || event.weeks.is_some() {
+                        return Err(eyre!("{} is a point in time, set when it's due instead", name));
+                    }
+                }
+                _ => {
+                    if event.date.is_none() {
+                        return Err(eyre!("{} needs a date", name));
+                    }
+                    if event.day.is_some() || event.weeks.is_some() || event.due.is_some() {
+                        return Err(eyre!("{} takes place once, it can't have a day, weeks or be due", name));
+                    }
+                }
+            }
+
+            if event.title.is_none() && event.subject.is_none() && event.kind != EventKind::OfficeHours {
+                return Err(eyre!("{} needs a title or a subject", name));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the title of the event, falling back to the name of its subject or teacher.
+    pub fn event_title(&self, event: &Event) -> String {
+        let subject = event.subject.as_ref().and_then(|subject| self.subjects.get(subject));
+        let teacher = event.teacher.as_ref().and_then(|teacher| self.teachers.get(teacher));
+
+        event
+            .title
+            .clone()
+            .or_else(|| subject.map(|subject| subject.get_short_or_name().to_string()))
+            .or_else(|| teacher.map(|teacher| teacher.name.clone()))
+            .unwrap_or_else(|| event.kind.to_name().to_string())
+    }
+
+    /// Returns the dates the event takes place on, in chronological order.
+    ///
+    /// Office hours take place on the days of the `weeks` table scheduled on their weekday.
+    pub fn event_dates(&self, event: &Event) -> Vec<NaiveDate> {
+        match (event.date, event.day) {
+            (Some(date), _) => vec![date],
+            (None, Some(day)) => self
+                .days()
+                .filter(|(week, weekday, _)| {
+                    *weekday == day && event.weeks.as_ref().is_none_or(|weeks| weeks.happens_in_week(*week))
+                })
+                .map(|(_, _, date)| date)
+                .collect(),
+            (None, None) => Vec::new(),
+        }
+    }
+
+    /// Returns the events taking place on the date, ordered by their start, with all-day events first.
+    pub fn events_on(&self, date: NaiveDate) -> Vec<&Event> {
+        let mut events: Vec<&Event> = self
+            .events
+            .iter()
+            .filter(|event| self.event_dates(event).contains(&date))
+            .collect();
+        events.sort_by_key(|event| event.start());
+
+        events
+    }
+
+    /// Generates an event for the event with the given UID and time, without recurrence.
+    fn event_to_vevent(&self, event: &Event, uid: String, now: DateTime<Tz>, time: EventTime) -> VEvent {
+        let summary = format!("{} {}", event.kind.to_emoji(), self.event_title(event));
+        let subject = event.subject.as_ref().and_then(|subject| self.subjects.get(subject));
+        let teacher = event.teacher.as_ref().and_then(|teacher| self.teachers.get(teacher));
+
+        let mut description = match subject {
+            Some(subject) => vec![format!("{}: {}", event.kind.to_name(), subject.name)],
+            None => vec![event.kind.to_name().to_string()],
+        };
+        description.extend(teacher.map(|teacher| teacher.name.clone()));
+        description.extend(event.description.clone());
+
+        let mut categories = vec![event.kind.to_name().to_string()];
+        categories.extend(subject.map(|subject| subject.name.clone()));
+
+        VEvent {
+            // Deadlines don't take any time
+            transparent: event.kind == EventKind::Deadline,
+            alarms: event
+                .reminders
+                .iter()
+                .map(|minutes| VAlarm::display_before(TimeDelta::minutes(*minutes as i64), summary.clone()))
+                .collect(),
+            categories,
+            color: Some(event.kind.to_css_color().to_string()),
+            description: Some(description.join("\n")),
+            location: event.location.as_ref().map(Location::to_string),
+            url: subject.and_then(|subject| subject.subject_url.clone()),
+            ..VEvent::new(uid, now, time, summary)
+        }
+    }
+
+    fn event_time(event: &Event, date: NaiveDate, tz: &Tz) -> eyre::Result<EventTime> {
+        Ok(match event.span() {
+            EventSpan::AllDay => EventTime::full_day(date),
+            EventSpan::Period(time) => {
+                let (start, end) = Self::period_start_end(&time, date, tz)?;
+                EventTime::Timed { start, end }
+            }
+            EventSpan::Due(time) => {
+                let (due, _) = Self::period_start_end(&NaiveTimePeriod { start: time, end: time }, date, tz)?;
+                EventTime::Timed { start: due, end: due }
+            }
+        })
+    }
+
+    /// Generates the iCalendar events of the schedule's events.
+    ///
+    /// Office hours are generated as a single recurring event if `recurring` is set,
+    /// or as separate events for every occurrence otherwise.
+    pub fn events_to_ical(&self, tz: &Tz, recurring: bool, now: DateTime<Tz>) -> eyre::Result<Vec<VEvent>> {
+        let mut vevents = Vec::new();
+
+        for event in &self.events {
+            let dates = self.event_dates(event);
+
+            if recurring && event.kind == EventKind::OfficeHours {
+                let time = event.time.ok_or_else(|| eyre!("{} has no time", event.describe()))?;
+                let day = event.day.ok_or_else(|| eyre!("{} has no day", event.describe()))?;
+                if let Some(series) = Self::weekly_series(&dates, day, event.weeks.as_ref(), &time, tz)? {
+                    let vevent = self.event_to_vevent(event, self.event_series_uid(event), now, series.time.clone());
+                    vevents.push(series.apply(vevent));
+                }
+                continue;
+            }
+
+            for date in dates {
+                let time = Self::event_time(event, date, tz)?;
+                vevents.push(self.event_to_vevent(event, self.event_uid(event, date), now, time));
+            }
+        }
+
+        Ok(vevents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::schedule::ICalOptions;
+    use crate::ical::vevent::EventStatus;
+    use crate::ical::SerializeToICal;
+    use serde_json::json;
+
+    fn schedule(events: serde_json::Value) -> Schedule {
+        serde_json::from_value(json!({
+            "weeks": [
+                ["2025-11-03", "2025-11-04", "2025-11-05", "2025-11-06", "2025-11-07"],
+                ["2025-11-10", "2025-11-11", "2025-11-12", "2025-11-13", "2025-11-14"],
+                ["2025-11-17", "2025-11-18", "2025-11-19", "2025-11-20", "2025-11-21"]
+            ],
+            "subjects": { "aisd": { "name": "Algorytmy", "short": "AiSD" } },
+            "teachers": { "kowalski": { "name": "dr Kowalski" } },
+            "schedule": [],
+            "events": events
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn events_on_dates() {
+        let schedule = schedule(json!([
+            { "type": "exam", "subject": "aisd", "date": "2025-11-12", "time": "9:00-11:00",
+              "location": { "building": "C3", "room": "204" } },
+            { "type": "deadline", "title": "Projekt AiSD", "date": "2025-11-12", "reminders": [1440, 60] },
+            { "type": "other", "title": "Dzień sportu", "date": "2025-11-12" },
+            { "type": "officeHours", "teacher": "kowalski", "day": "wednesday", "time": "12:00-13:00",
+              "weeks": { "parity": "odd" } }
+        ]));
+        schedule.check_events().unwrap();
+        let date = |day| NaiveDate::from_ymd_opt(2025, 11, day).unwrap();
+
+        let titles: Vec<_> = schedule.events_on(date(19)).into_iter().map(|e| schedule.event_title(e)).collect();
+        assert_eq!(titles, vec!["dr Kowalski"]);
+        let titles: Vec<_> = schedule.events_on(date(12)).into_iter().map(|e| schedule.event_title(e)).collect();
+        assert_eq!(titles, vec!["Dzień sportu", "AiSD", "Projekt AiSD"]);
+        assert_eq!(schedule.events[1].span_label(), "due 23:59");
+        assert_eq!(schedule.events[2].span_label(), "all day");
+
+        let tz = Tz::Europe__Warsaw;
+        let calendar = schedule.to_ical(&tz, &ICalOptions::default()).unwrap();
+        let events: Vec<_> = calendar.events.iter().filter(|e| e.color.is_some()).collect();
+        assert_eq!(events.len(), 5);
+        assert_eq!(events[0].summary, "🎓 AiSD");
+        assert_eq!(events[0].categories, vec!["Egzamin", "Algorytmy"]);
+        assert_eq!(events[0].location.as_deref(), Some("204/C3"));
+        assert_eq!(events[1].alarms.len(), 2);
+        assert!(events[1].transparent);
+        let mut buffer = Vec::new();
+        events[1].serialize_to_ical(&mut buffer).unwrap();
+        let deadline = String::from_utf8(buffer).unwrap();
+        assert!(deadline.contains("DTSTART;TZID=Europe/Warsaw:20251112T235900\r\n"));
+        assert!(!deadline.contains("DTEND"));
+        assert_eq!(events[2].time, EventTime::full_day(date(12)));
+        assert_eq!(events[3].categories, vec!["Konsultacje"]);
+        assert_ne!(events[3].uid, events[4].uid);
+        assert!(events.iter().all(|e| e.status != Some(EventStatus::Cancelled)));
+
+        let options = ICalOptions { recurring: true, ..ICalOptions::default() };
+        let calendar = schedule.to_ical(&tz, &options).unwrap();
+        let office_hours = calendar.events.iter().find(|e| e.summary == "🙋 dr Kowalski").unwrap();
+        assert!(office_hours.recurrence.is_some());
+    }
+
+    #[test]
+    fn invalid_events() {
+        let invalid = [
+            json!({ "type": "exam", "subject": "aisd" }),
+            json!({ "type": "exam", "subject": "pe", "date": "2025-11-12" }),
+            json!({ "type": "exam", "date": "2025-11-12" }),
+            json!({ "type": "deadline", "title": "Projekt", "date": "2025-11-12", "time": "9:00-11:00" }),
+            json!({ "type": "officeHours", "teacher": "kowalski", "day": "wednesday" }),
+            json!({ "type": "officeHours", "teacher": "nowak", "day": "wednesday", "time": "12:00-13:00" }),
+        ];
+
+        for event in invalid {
+            assert!(schedule(json!([event])).check_events().is_err());
+        }
+    }
+}
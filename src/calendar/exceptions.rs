@@ -0,0 +1,264 @@
+//! One-off changes of single occurrences of classes, defined in the `exceptions` property of the schedule:
+//! cancellations, moves to another date or time, and changes of location or teachers.
+//!
+//! Exceptions are resolved by [`Schedule::resolve_exceptions`] into [`ChangedOccurrence`]s
+//! when the schedule is deserialized.
+//! The changed occurrence is then skipped in favor of its replacement, a copy of the class
+//! with the changes applied, taking place on the new date.
+
+use crate::calendar::class::{Class, Location};
+use crate::calendar::periods::NaiveTimePeriod;
+use crate::calendar::schedule::{deserialize_optional_date, Schedule};
+use crate::calendar::Weeks;
+use chrono::{NaiveDate, Weekday};
+use eyre::eyre;
+use serde::Deserialize;
+use std::num::NonZero;
+
+/// A change of a single occurrence of a class.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ClassException {
+    /// ID of the class, as in its `id` property.
+    pub class: String,
+    /// Date of the changed occurrence.
+    #[serde(default, deserialize_with = "deserialize_optional_date")]
+    pub date: Option<NaiveDate>,
+    /// Week of the changed occurrence, instead of its date.
+    pub week: Option<NonZero<usize>>,
+    #[serde(default)]
+    pub cancel: bool,
+    /// Date the occurrence is moved to.
+    #[serde(default, deserialize_with = "deserialize_optional_date")]
+    pub new_date: Option<NaiveDate>,
+    pub time: Option<NaiveTimePeriod>,
+    pub location: Option<Location>,
+    pub teachers: Option<Vec<String>>,
+}
+
+impl ClassException {
+    fn describe(&self) -> String {
+        match (self.date, self.week) {
+            (Some(date), _) => format!("{} on {}", self.class, date),
+            (None, Some(week)) => format!("{} in week {}", self.class, week),
+            (None, None) => self.class.clone(),
+        }
+    }
+}
+
+/// The class taking place instead of a changed occurrence.
+#[derive(Debug)]
+pub struct Replacement {
+    pub week: NonZero<usize>,
+    pub weekday: Weekday,
+    pub date: NaiveDate,
+    /// Copy of the class with the changes applied, happening only on the new date.
+    pub class: Class,
+}
+
+/// An occurrence of a class changed by an exception.
+#[derive(Debug)]
+pub struct ChangedOccurrence {
+    /// Index of the class in the schedule.
+    pub class: usize,
+    pub week: NonZero<usize>,
+    pub weekday: Weekday,
+    pub date: NaiveDate,
+    /// The class taking place instead, or `None` if the occurrence is cancelled.
+    pub replacement: Option<Replacement>,
+}
+
+impl ChangedOccurrence {
+    /// Whether the occurrence takes place on another date.
+    pub fn is_moved(&self) -> bool {
+        self.replacement.as_ref().is_some_and(|replacement| replacement.date != self.date)
+    }
+}
+
+impl Schedule {
+    /// Resolves the `exceptions` of the schedule against its `weeks` table and classes.
+    pub fn resolve_exceptions(&mut self) -> eyre::Result<()> {
+        let mut changes: Vec<ChangedOccurrence> = Vec::with_capacity(self.exceptions.len());
+
+        for exception in &self.exceptions {
+            let index = self
+                .schedule
+                .iter()
+                .position(|class| class.id.as_ref() == Some(&exception.class))
+                .ok_or_else(|| eyre!("exception for an unknown class id: {}", exception.class))?;
+            let class = &self.schedule[index];
+
+            let (week, weekday, date) = match (exception.date, exception.week) {
+                (Some(date), None) => {
+                    let (week, weekday) = self.get_day(date)?;
+                    (week, weekday, date)
+                }
+                (None, Some(week)) => {
+                    let date = self
+                        .week_days(week)?
+                        .into_iter()
+                        .find(|(weekday, _)| *weekday == class.day)
+                        .and_then(|(_, date)| date)
+                        .ok_or_else(|| eyre!("{} doesn't occur in week {}", class.day, week))?;
+                    (week, class.day, date)
+                }
+                _ => return Err(eyre!("exception for {} needs either a date or a week", exception.class)),
+            };
+
+            if !class.happens_on(week, weekday) {
+                return Err(eyre!("{} doesn't take place", exception.describe()));
+            }
+            if changes.iter().any(|change| change.class == index && change.week == week) {
+                return Err(eyre!("{} has more than one exception", exception.describe()));
+            }
+
+            let changes_anything = exception.new_date.is_some()
+                || exception.time.is_some()
+                || exception.location.is_some()
+                || exception.teachers.is_some();
+
+            let replacement = match (exception.cancel, changes_anything) {
+                (true, true) => return Err(eyre!("{} is both cancelled and changed", exception.describe())),
+                (true, false) => None,
+                (false, false) => return Err(eyre!("exception for {} changes nothing", exception.describe())),
+                (false, true) => {
+                    let new_date = exception.new_date.unwrap_or(date);
+                    let (new_week, new_weekday) = self
+                        .get_day(new_date)
+                        .map_err(|_| eyre!("{} is moved to {}, which isn't a day of the schedule", exception.describe(), new_date))?;
+
+                    Some(Replacement {
+                        week: new_week,
+                        weekday: new_weekday,
+                        date: new_date,
+                        class: Class {
+                            id: class.id.clone(),
+                            subject: class.subject.clone(),
+                            class_type: class.class_type,
+                            day: new_weekday,
+                            time: exception.time.unwrap_or(class.time),
+                            location: exception.location.clone().or_else(|| class.location.clone()),
+                            teachers: exception.teachers.clone().or_else(|| class.teachers.clone()),
+                            weeks: Some(Weeks {
+                                only: Some(vec![new_week]),
+                                ..Weeks::default()
+                            }),
+                            alarm: class.alarm,
+                        },
+                    })
+                }
+            };
+
+            changes.push(ChangedOccurrence {
+                class: index,
+                week,
+                weekday,
+                date,
+                replacement,
+            });
+        }
+
+        self.changes = changes;
+        Ok(())
+    }
+
+    /// Returns the change of the occurrence of the class in the week, if any.
+    pub fn change_of(&self, class: &Class, week: NonZero<usize>) -> Option<&ChangedOccurrence> {
+        self.changes
+            .iter()
+            .find(|change| change.week == week && std::ptr::eq(&self.schedule[change.class], class))
+    }
+
+    /// Returns the change the class is the replacement of, if it is one.
+    pub fn change_replaced_by(&self, class: &Class) -> Option<&ChangedOccurrence> {
+        self.changes.iter().find(|change| {
+            change
+                .replacement
+                .as_ref()
+                .is_some_and(|replacement| std::ptr::eq(&replacement.class, class))
+        })
+    }
+
+    /// Returns the changed occurrences originally taking place on the day.
+    pub fn changes_on(&self, week: NonZero<usize>, weekday: Weekday) -> impl Iterator<Item = &ChangedOccurrence> {
+        self.changes
+            .iter()
+            .filter(move |change| change.week == week && change.weekday == weekday)
+    }
+
+    /// Returns the class of a changed occurrence, as originally scheduled.
+    pub fn changed_class(&self, change: &ChangedOccurrence) -> &Class {
+        &self.schedule[change.class]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schedule(exceptions: serde_json::Value) -> eyre::Result<Schedule> {
+        let schedule: Schedule = serde_json::from_value(json!({
+            "weeks": [
+                ["2025-11-03", "2025-11-04", "2025-11-05", "2025-11-06", "2025-11-07"],
+                ["2025-11-10", "2025-11-11", "2025-11-12", "2025-11-13", "2025-11-14"]
+            ],
+            "subjects": { "aisd": { "name": "Algorytmy", "short": "AiSD" } },
+            "teachers": { "kowalski": { "name": "dr Kowalski" }, "nowak": { "name": "dr Nowak" } },
+            "schedule": [
+                { "id": "aisd-lab", "subject": "aisd", "type": "lab", "day": "wednesday", "time": "10:15-12:00",
+                  "location": { "building": "C3", "room": "204" }, "teachers": ["kowalski"] },
+                { "id": "aisd-lecture", "subject": "aisd", "type": "lecture", "day": "monday", "time": "8:15-10:00" }
+            ],
+            "exceptions": exceptions
+        }))?;
+        Ok(schedule)
+    }
+
+    #[test]
+    fn resolve_exceptions() {
+        let schedule = schedule(json!([
+            { "class": "aisd-lecture", "date": "2025-11-03", "cancel": true },
+            { "class": "aisd-lab", "week": 2, "newDate": "2025-11-13", "time": "14:00-15:45",
+              "location": { "building": "C3", "room": "105" } },
+            { "class": "aisd-lab", "week": 1, "teachers": ["nowak"] }
+        ]))
+        .unwrap();
+        let week = |week| NonZero::new(week).unwrap();
+        let date = |day| NaiveDate::from_ymd_opt(2025, 11, day).unwrap();
+
+        assert_eq!(schedule.get_classes_on(week(1), Weekday::Mon).count(), 0);
+        assert_eq!(schedule.get_classes_on(week(2), Weekday::Mon).count(), 1);
+        assert_eq!(schedule.get_classes_on(week(2), Weekday::Wed).count(), 0);
+
+        let moved: Vec<_> = schedule.get_classes_on(week(2), Weekday::Thu).collect();
+        assert_eq!(moved.len(), 1);
+        assert_eq!(moved[0].time, NaiveTimePeriod::from_hm_hm(14, 0, 15, 45));
+        assert_eq!(moved[0].teachers, Some(vec!["kowalski".to_string()]));
+        let change = schedule.change_replaced_by(moved[0]).unwrap();
+        assert!(change.is_moved());
+        assert_eq!(change.date, date(12));
+
+        let changed: Vec<_> = schedule.get_classes_on(week(1), Weekday::Wed).collect();
+        assert_eq!(changed[0].teachers, Some(vec!["nowak".to_string()]));
+        assert!(!schedule.change_replaced_by(changed[0]).unwrap().is_moved());
+
+        let dates: Vec<_> = schedule.occurrences().map(|o| o.date).collect();
+        assert_eq!(dates, vec![date(5), date(10), date(13)]);
+    }
+
+    #[test]
+    fn invalid_exceptions() {
+        assert!(schedule(json!([{ "class": "pe", "week": 1, "cancel": true }])).is_err());
+        assert!(schedule(json!([{ "class": "aisd-lab", "cancel": true }])).is_err());
+        assert!(schedule(json!([{ "class": "aisd-lab", "date": "2025-11-04", "cancel": true }])).is_err());
+        assert!(schedule(json!([{ "class": "aisd-lab", "week": 1 }])).is_err());
+        assert!(schedule(json!([{ "class": "aisd-lab", "week": 1, "cancel": true, "time": "8:00-9:00" }])).is_err());
+        assert!(schedule(json!([{ "class": "aisd-lab", "week": 1, "newDate": "2025-11-08" }])).is_err());
+        assert!(schedule(json!([
+            { "class": "aisd-lab", "week": 1, "cancel": true },
+            { "class": "aisd-lab", "date": "2025-11-05", "time": "8:00-9:00" }
+        ]))
+        .is_err());
+    }
+}
@@ -0,0 +1,117 @@
+use crate::calendar::class::Class;
+use crate::calendar::class_type::ClassType;
+use crate::calendar::schedule::Schedule;
+use eyre::eyre;
+
+/// Criteria selecting a subset of the classes of a schedule.
+///
+/// Each criterion is a set of accepted values, and an empty set accepts any class.
+/// A class must match all criteria to be selected.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+pub struct ClassFilter {
+    pub types: Vec<ClassType>,
+    /// Subject IDs.
+    pub subjects: Vec<String>,
+    /// Teacher IDs. A class matches if any of its teachers does.
+    pub teachers: Vec<String>,
+}
+
+impl ClassFilter {
+    pub fn matches(&self, class: &Class) -> bool {
+        let type_matches = self.types.is_empty() || self.types.contains(&class.class_type);
+        let subject_matches = self.subjects.is_empty() || self.subjects.contains(&class.subject);
+        let teacher_matches = self.teachers.is_empty()
+            || class
+                .teachers
+                .iter()
+                .flatten()
+                .any(|teacher| self.teachers.contains(teacher));
+
+        type_matches && subject_matches && teacher_matches
+    }
+
+    /// Adds the comma-separated values of a criterion, named `type`, `subject` or `teacher`.
+    pub fn add(&mut self, criterion: &str, values: &str) -> eyre::Result<()> {
+        let values = values.split(',').map(str::trim).filter(|v| !v.is_empty());
+
+        match criterion {
+            "type" => {
+                for value in values {
+                    let class_type =
+                        ClassType::from_id(value).ok_or_else(|| eyre!("unknown class type: {}", value))?;
+                    self.types.push(class_type);
+                }
+            }
+            "subject" => self.subjects.extend(values.map(str::to_string)),
+            "teacher" => self.teachers.extend(values.map(str::to_string)),
+            _ => return Err(eyre!("unknown filter: {}", criterion)),
+        }
+
+        Ok(())
+    }
+
+    /// Sorts the values of the criteria and removes duplicates, so that equivalent filters are equal.
+    pub fn normalize(&mut self) {
+        self.types.sort_by_key(|class_type| class_type.to_id());
+        self.types.dedup();
+        self.subjects.sort();
+        self.subjects.dedup();
+        self.teachers.sort();
+        self.teachers.dedup();
+    }
+
+    /// Ensures all subject and teacher IDs are defined in the schedule.
+    pub fn check(&self, schedule: &Schedule) -> eyre::Result<()> {
+        if let Some(subject) = self.subjects.iter().find(|s| !schedule.subjects.contains_key(*s)) {
+            return Err(eyre!("unknown subject: {}", subject));
+        }
+        if let Some(teacher) = self.teachers.iter().find(|t| !schedule.teachers.contains_key(*t)) {
+            return Err(eyre!("unknown teacher: {}", teacher));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn filter_classes() {
+        let class: Class = serde_json::from_value(json!({
+            "subject": "aisd", "type": "lab", "day": "monday", "time": "8:15-10:00",
+            "teachers": ["kowalski", "nowak"]
+        }))
+        .unwrap();
+
+        let mut filter = ClassFilter::default();
+        assert!(filter.matches(&class));
+
+        filter.add("type", "lecture,lab").unwrap();
+        filter.add("teacher", "nowak").unwrap();
+        assert!(filter.matches(&class));
+
+        filter.add("subject", "ang").unwrap();
+        assert!(!filter.matches(&class));
+
+        assert!(filter.add("type", "party").is_err());
+        assert!(filter.add("room", "204").is_err());
+    }
+
+    #[test]
+    fn normalize_filters() {
+        let mut a = ClassFilter::default();
+        a.add("type", "lab,lecture,lab").unwrap();
+        a.add("subject", "ang,aisd").unwrap();
+        let mut b = ClassFilter::default();
+        b.add("subject", "aisd,ang,aisd").unwrap();
+        b.add("type", "lecture,lab").unwrap();
+
+        assert_ne!(a, b);
+        a.normalize();
+        b.normalize();
+        assert_eq!(a, b);
+    }
+}
@@ -0,0 +1,239 @@
+//! Public holidays, skipped when generating the `weeks` table and flagged when classes fall on them.
+
+use crate::calendar::semester::Break;
+use crate::ical::vcalendar::VCalendar;
+use crate::ical::vevent::EventTime;
+use chrono::{DateTime, Datelike, NaiveDate, TimeDelta, Utc};
+use chrono_tz::Tz;
+use eyre::{eyre, WrapErr};
+use serde::Deserialize;
+use std::path::{Path, PathBuf};
+
+/// Returns the date of (Western) Easter Sunday in the given year.
+///
+/// Uses the anonymous Gregorian algorithm (Meeus/Jones/Butcher).
+pub fn easter(year: i32) -> NaiveDate {
+    let a = year % 19;
+    let b = year / 100;
+    let c = year % 100;
+    let d = b / 4;
+    let e = b % 4;
+    let f = (b + 8) / 25;
+    let g = (b - f + 1) / 3;
+    let h = (19 * a + b - d - g + 15) % 30;
+    let i = c / 4;
+    let k = c % 4;
+    let l = (32 + 2 * e + 2 * i - h - k) % 7;
+    let m = (a + 11 * h + 22 * l) / 451;
+    let month = (h + l - 7 * m + 114) / 31;
+    let day = (h + l - 7 * m + 114) % 31 + 1;
+
+    NaiveDate::from_ymd_opt(year, month as u32, day as u32).unwrap()
+}
+
+/// A built-in calendar of public holidays.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum HolidayCalendar {
+    /// Public holidays (days free from work) in Poland.
+    #[serde(rename = "pl")]
+    Poland,
+}
+
+impl HolidayCalendar {
+    /// Returns the holidays in the given year, sorted by date.
+    pub fn holidays(self, year: i32) -> Vec<(NaiveDate, &'static str)> {
+        let date = |month, day| NaiveDate::from_ymd_opt(year, month, day).unwrap();
+
+        match self {
+            HolidayCalendar::Poland => {
+                let easter = easter(year);
+                let mut holidays = vec![
+                    (date(1, 1), "Nowy Rok"),
+                    (date(1, 6), "Święto Trzech Króli"),
+                    (easter, "Wielkanoc"),
+                    (easter + TimeDelta::days(1), "Poniedziałek Wielkanocny"),
+                    (date(5, 1), "Święto Pracy"),
+                    (date(5, 3), "Święto Konstytucji 3 Maja"),
+                    (easter + TimeDelta::days(49), "Zielone Świątki"),
+                    (easter + TimeDelta::days(60), "Boże Ciało"),
+                    (date(8, 15), "Wniebowzięcie Najświętszej Maryi Panny"),
+                    (date(11, 1), "Wszystkich Świętych"),
+                    (date(11, 11), "Narodowe Święto Niepodległości"),
+                    (date(12, 25), "Boże Narodzenie"),
+                    (date(12, 26), "Drugi dzień Bożego Narodzenia"),
+                ];
+                // A day free from work since 2025
+                if year >= 2025 {
+                    holidays.push((date(12, 24), "Wigilia Bożego Narodzenia"));
+                }
+                holidays.sort();
+                holidays
+            }
+        }
+    }
+}
+
+/// Holidays of the schedule, from a built-in calendar and extra files.
+#[derive(Debug, Default, Deserialize)]
+pub struct HolidaySettings {
+    pub calendar: Option<HolidayCalendar>,
+    /// Paths of files with extra holidays, relative to the schedule file.
+    ///
+    /// `.ics` files have a holiday for every occurrence of their full-day events,
+    /// and `.json` files contain an array of dates or `from`-`to` ranges, like the breaks of the semester rules.
+    #[serde(default)]
+    pub files: Vec<PathBuf>,
+    /// Holidays loaded from the files by [`HolidaySettings::load_files`].
+    #[serde(skip)]
+    pub extra: Vec<Break>,
+}
+
+impl HolidaySettings {
+    /// Loads the extra holidays from the files, resolving their paths relative to `base`.
+    pub fn load_files(&mut self, base: &Path) -> eyre::Result<()> {
+        for file in &self.files {
+            let path = base.join(file);
+            let holidays = load_holidays(&path).wrap_err_with(|| format!("loading holidays from {}", path.display()))?;
+            self.extra.extend(holidays);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the name of the holiday on the date, if any.
+    pub fn holiday_on(&self, date: NaiveDate) -> Option<&str> {
+        if let Some(extra) = self.extra.iter().find(|holiday| holiday.contains(date)) {
+            return Some(extra.name.as_deref().unwrap_or("holiday"));
+        }
+
+        self.calendar?
+            .holidays(date.year())
+            .into_iter()
+            .find(|(holiday, _)| *holiday == date)
+            .map(|(_, name)| name)
+    }
+}
+
+/// Number of years after the current date recurring full-day events in `.ics` files are expanded for.
+const RECURRING_HOLIDAY_YEARS: i64 = 5;
+
+fn load_holidays(path: &Path) -> eyre::Result<Vec<Break>> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        Some("ics") => {
+            let limit = Utc::now().with_timezone(&Tz::UTC) + TimeDelta::days(366 * RECURRING_HOLIDAY_YEARS);
+            Ok(calendar_holidays(&VCalendar::parse_file(path)?, limit))
+        }
+        Some("json") => Ok(serde_json::from_str(&std::fs::read_to_string(path)?)?),
+        _ => Err(eyre!("unsupported file type, expected .ics or .json")),
+    }
+}
+
+/// Returns a holiday for every occurrence of the full-day events of the calendar, up to `limit`.
+fn calendar_holidays(calendar: &VCalendar, limit: DateTime<Tz>) -> Vec<Break> {
+    calendar
+        .events
+        .iter()
+        .filter_map(|event| match event.time {
+            EventTime::FullDay { start, end } => Some((event, end - start)),
+            EventTime::Timed { .. } => None,
+        })
+        .flat_map(|(event, length)| {
+            event.occurrences(limit).into_iter().map(move |occurrence| {
+                let from = occurrence.date_naive();
+                Break {
+                    from,
+                    to: from + length - TimeDelta::days(1),
+                    name: Some(event.summary.clone()),
+                }
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveTime;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn easter_dates() {
+        assert_eq!(easter(2024), date(2024, 3, 31));
+        assert_eq!(easter(2025), date(2025, 4, 20));
+        assert_eq!(easter(2026), date(2026, 4, 5));
+        assert_eq!(easter(2038), date(2038, 4, 25));
+    }
+
+    #[test]
+    fn polish_holidays() {
+        let settings = HolidaySettings {
+            calendar: Some(HolidayCalendar::Poland),
+            files: vec![],
+            extra: vec![Break {
+                from: date(2026, 5, 2),
+                to: date(2026, 5, 2),
+                name: None,
+            }],
+        };
+
+        assert_eq!(settings.holiday_on(date(2025, 11, 11)), Some("Narodowe Święto Niepodległości"));
+        assert_eq!(settings.holiday_on(date(2026, 6, 4)), Some("Boże Ciało"));
+        assert_eq!(settings.holiday_on(date(2025, 12, 24)), Some("Wigilia Bożego Narodzenia"));
+        assert_eq!(settings.holiday_on(date(2024, 12, 24)), None);
+        assert_eq!(settings.holiday_on(date(2026, 5, 2)), Some("holiday"));
+        assert_eq!(settings.holiday_on(date(2025, 11, 12)), None);
+    }
+
+    #[test]
+    fn ics_holidays() {
+        let calendar = VCalendar::parse(
+            "BEGIN:VCALENDAR\r\n\
+             VERSION:2.0\r\n\
+             PRODID:-//Test//EN\r\n\
+             BEGIN:VEVENT\r\n\
+             UID:rector@example.com\r\n\
+             DTSTAMP:20250101T000000Z\r\n\
+             DTSTART;VALUE=DATE:20251103\r\n\
+             DTEND;VALUE=DATE:20251106\r\n\
+             SUMMARY:Dni rektorskie\r\n\
+             END:VEVENT\r\n\
+             BEGIN:VEVENT\r\n\
+             UID:anniversary@example.com\r\n\
+             DTSTAMP:20250101T000000Z\r\n\
+             DTSTART;VALUE=DATE:20241120\r\n\
+             RRULE:FREQ=YEARLY\r\n\
+             SUMMARY:Święto uczelni\r\n\
+             END:VEVENT\r\n\
+             BEGIN:VEVENT\r\n\
+             UID:meeting@example.com\r\n\
+             DTSTAMP:20250101T000000Z\r\n\
+             DTSTART:20251110T100000Z\r\n\
+             DTEND:20251110T110000Z\r\n\
+             SUMMARY:Meeting\r\n\
+             END:VEVENT\r\n\
+             END:VCALENDAR\r\n",
+        )
+        .unwrap();
+        let limit = date(2026, 12, 31).and_time(NaiveTime::MIN).and_utc().with_timezone(&Tz::UTC);
+
+        let settings = HolidaySettings {
+            calendar: None,
+            files: vec![],
+            extra: calendar_holidays(&calendar, limit),
+        };
+
+        assert_eq!(settings.extra.len(), 4);
+        assert_eq!(settings.holiday_on(date(2025, 11, 2)), None);
+        assert_eq!(settings.holiday_on(date(2025, 11, 3)), Some("Dni rektorskie"));
+        assert_eq!(settings.holiday_on(date(2025, 11, 5)), Some("Dni rektorskie"));
+        assert_eq!(settings.holiday_on(date(2025, 11, 6)), None);
+        assert_eq!(settings.holiday_on(date(2024, 11, 20)), Some("Święto uczelni"));
+        assert_eq!(settings.holiday_on(date(2025, 11, 20)), Some("Święto uczelni"));
+        assert_eq!(settings.holiday_on(date(2026, 11, 20)), Some("Święto uczelni"));
+        assert_eq!(settings.holiday_on(date(2025, 11, 21)), None);
+        assert_eq!(settings.holiday_on(date(2025, 11, 10)), None);
+    }
+}
@@ -0,0 +1,562 @@
+//! Conversion of existing iCalendar data into a schedule definition.
+//!
+//! Timed events are expanded into occurrences, which are grouped into classes by their title,
+//! description, location, weekday and time.
+//! The `weeks` table is built from calendar weeks containing at least one occurrence,
+//! and the weeks of each class are described with `from`/`to`/`parity` if possible,
+//! or with `only` otherwise.
+
+use crate::calendar::class_type::ClassType;
+use crate::calendar::WeekParity;
+use crate::ical::vcalendar::VCalendar;
+use crate::ical::vevent::{EventTime, VEvent};
+use chrono::{Datelike, NaiveDate, NaiveTime, TimeDelta, Weekday};
+use chrono_tz::Tz;
+use log::{debug, warn};
+use serde_json::{json, Map, Value};
+use std::collections::{BTreeMap, BTreeSet};
+use std::num::NonZero;
+
+/// Properties shared by all occurrences of an imported class.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct ClassKey {
+    weekday: u32,
+    start: NaiveTime,
+    end: NaiveTime,
+    summary: String,
+    description: Option<String>,
+    location: Option<String>,
+}
+
+/// Converts text to a lowercase ASCII identifier, e.g. `Język angielski` to `jezyk-angielski`.
+fn slugify(text: &str) -> String {
+    let mut slug = String::with_capacity(text.len());
+
+    for c in text.chars().flat_map(char::to_lowercase) {
+        let c = match c {
+            'ą' => 'a',
+            'ć' => 'c',
+            'ę' => 'e',
+            'ł' => 'l',
+            'ń' => 'n',
+            'ó' => 'o',
+            'ś' => 's',
+            'ź' | 'ż' => 'z',
+            c if c.is_ascii_alphanumeric() => c,
+            _ => '-',
+        };
+
+        if c != '-' || !slug.is_empty() && !slug.ends_with('-') {
+            slug.push(c);
+        }
+    }
+
+    while slug.ends_with('-') {
+        slug.pop();
+    }
+
+    if slug.is_empty() {
+        slug.push_str("unnamed");
+    }
+
+    slug
+}
+
+/// Returns a key not yet present in the map, based on `base`.
+fn unique_key(map: &Map<String, Value>, base: &str, matches: impl Fn(&Value) -> bool) -> String {
+    let mut key = base.to_string();
+
+    for n in 2.. {
+        match map.get(&key) {
+            Some(existing) if !matches(existing) => key = format!("{}-{}", base, n),
+            _ => break,
+        }
+    }
+
+    key
+}
+
+/// Describes the set of weeks a class happens in, as a `weeks` object of a class.
+///
+/// Returns `None` if the class happens in every week.
+pub(crate) fn infer_weeks(weeks: &BTreeSet<usize>, total: usize) -> Option<Value> {
+    let (&first, &last) = (weeks.first()?, weeks.last()?);
+
+    let matches = |parity: WeekParity| {
+        (first..=last)
+            .filter(|w| parity.includes(NonZero::new(*w).unwrap()))
+            .eq(weeks.iter().copied())
+    };
+
+    let parity = [WeekParity::All, WeekParity::Odd, WeekParity::Even]
+        .into_iter()
+        .find(|parity| matches(*parity));
+
+    let Some(parity) = parity else {
+        return Some(json!({ "only": weeks }));
+    };
+
+    let in_parity = |w: usize| parity.includes(NonZero::new(w).unwrap());
+    let first_possible = (1..=total).find(|w| in_parity(*w));
+    let last_possible = (1..=total).rev().find(|w| in_parity(*w));
+
+    let mut object = Map::new();
+
+    if Some(first) != first_possible {
+        object.insert("from".into(), json!(first));
+    }
+    if Some(last) != last_possible {
+        object.insert("to".into(), json!(last));
+    }
+    match parity {
+        WeekParity::All => {}
+        WeekParity::Odd => {
+            object.insert("parity".into(), json!("odd"));
+        }
+        WeekParity::Even => {
+            object.insert("parity".into(), json!("even"));
+        }
+    }
+
+    (!object.is_empty()).then_some(Value::Object(object))
+}
+
+/// Splits the first line of a description generated by this program,
+/// `<class type name>: <subject name>`, into the class type and the subject name.
+fn parse_description_header(key: &ClassKey) -> Option<(ClassType, &str)> {
+    let (type_name, name) = key.description.as_ref()?.lines().next()?.split_once(": ")?;
+
+    Some((ClassType::from_name(type_name)?, name))
+}
+
+/// Returns the `<label>: <URL>` links listed after the first blank line of the description.
+fn parse_description_links(key: &ClassKey) -> Vec<(&str, &str)> {
+    key.description
+        .iter()
+        .flat_map(|d| d.lines().skip_while(|line| !line.trim().is_empty()))
+        .filter_map(|line| line.trim().split_once(": "))
+        .collect()
+}
+
+/// Splits an event title and description into the class type, subject name and short subject name.
+///
+/// Recognizes the format generated by this program: the class type emoji before the title,
+/// and `<class type name>: <subject name>` in the first line of the description.
+fn parse_subject(key: &ClassKey) -> (Option<ClassType>, String, Option<String>) {
+    let (mut class_type, title) = match ClassType::strip_emoji(&key.summary) {
+        Some((class_type, rest)) => (Some(class_type), rest.to_string()),
+        None => (None, key.summary.trim().to_string()),
+    };
+
+    let full_name = parse_description_header(key).map(|(described_type, name)| {
+        class_type.get_or_insert(described_type);
+        name.to_string()
+    });
+
+    match full_name {
+        Some(name) if name != title => (class_type, name, Some(title)),
+        _ => (class_type, title, None),
+    }
+}
+
+/// Returns the teachers of an event as `(name, email)`.
+///
+/// Uses the lines following the first one in the description, up to the first blank line,
+/// if the description was generated by this program or the event has no `ORGANIZER` nor `ATTENDEE`.
+/// Emails are taken from matching `ORGANIZER` and `ATTENDEE` properties,
+/// and the remaining ones are added as separate teachers.
+fn parse_teachers(event: &VEvent, key: &ClassKey) -> Vec<(String, Option<String>)> {
+    let users: Vec<(String, String)> = event
+        .organizer
+        .iter()
+        .chain(&event.attendees)
+        .map(|user| {
+            let name = user.name.clone().unwrap_or_else(|| user.email.clone());
+            (name, user.email.clone())
+        })
+        .collect();
+
+    let mut teachers: Vec<(String, Option<String>)> = Vec::new();
+
+    if users.is_empty() || parse_description_header(key).is_some() {
+        let lines = key
+            .description
+            .iter()
+            .flat_map(|d| d.lines().skip(1))
+            .map(str::trim)
+            .take_while(|line| !line.is_empty());
+
+        for line in lines {
+            let email = users
+                .iter()
+                .find(|(name, _)| name == line)
+                .map(|(_, email)| email.clone());
+            teachers.push((line.to_string(), email));
+        }
+    }
+
+    for (name, email) in users {
+        if !teachers.iter().any(|(other, _)| *other == name) {
+            teachers.push((name, Some(email)));
+        }
+    }
+
+    teachers
+}
+
+/// Parses an event location in the `room/building` format.
+fn parse_location(location: &str) -> Value {
+    if location.eq_ignore_ascii_case("online") {
+        return json!("online");
+    }
+
+    match location.rsplit_once('/') {
+        Some((room, building)) => json!({ "building": building.trim(), "room": room.trim() }),
+        None => json!({ "building": "", "room": location.trim() }),
+    }
+}
+
+/// Converts a calendar with weekly class events into a schedule definition,
+/// which can be saved as a schedule .json file.
+///
+/// All times are converted to the given time zone first.
+pub fn import_calendar(calendar: &VCalendar, tz: &Tz) -> eyre::Result<Value> {
+    let mut classes: BTreeMap<ClassKey, (&VEvent, BTreeSet<NaiveDate>)> = BTreeMap::new();
+
+    for event in &calendar.events {
+        let EventTime::Timed { start, end } = &event.time else {
+            debug!("Skipping full-day event {}", event.summary);
+            continue;
+        };
+
+        if event.recurrence.is_none() && event.extra_properties.iter().any(|p| p.name == "RRULE") {
+            warn!("Only the first occurrence of {} will be imported", event.summary);
+        }
+
+        let duration = *end - *start;
+        let limit = *start + TimeDelta::days(366);
+
+        for occurrence in event.occurrences(limit) {
+            let start = occurrence.with_timezone(tz).naive_local();
+            let end = start + duration;
+
+            if end.date() != start.date() {
+                warn!("Skipping {} on {}: it spans multiple days", event.summary, start.date());
+                continue;
+            }
+
+            let key = ClassKey {
+                weekday: start.weekday().num_days_from_monday(),
+                start: start.time(),
+                end: end.time(),
+                summary: event.summary.clone(),
+                description: event.description.clone(),
+                location: event.location.clone().filter(|l| !l.is_empty()),
+            };
+
+            classes
+                .entry(key)
+                .or_insert_with(|| (event, BTreeSet::new()))
+                .1
+                .insert(start.date());
+        }
+    }
+
+    let mondays: BTreeSet<NaiveDate> = classes
+        .values()
+        .flat_map(|(_, dates)| dates.iter())
+        .map(|date| date.week(Weekday::Mon).first_day())
+        .collect();
+    let week_numbers: BTreeMap<NaiveDate, usize> = mondays
+        .iter()
+        .enumerate()
+        .map(|(index, monday)| (*monday, index + 1))
+        .collect();
+
+    // Weeks span Monday to Friday, or to the last weekend day with classes
+    let day_count = classes
+        .keys()
+        .map(|key| key.weekday as usize + 1)
+        .max()
+        .unwrap_or_default()
+        .max(5);
+
+    let weeks: Vec<Value> = mondays
+        .iter()
+        .map(|monday| {
+            json!(monday
+                .iter_days()
+                .take(day_count)
+                .map(|day| day.format("%Y-%m-%d").to_string())
+                .collect::<Vec<_>>())
+        })
+        .collect();
+
+    let mut subjects = Map::new();
+    let mut teachers = Map::new();
+    let mut schedule = Vec::new();
+    let mut untyped = 0;
+
+    for (key, (event, dates)) in &classes {
+        let (class_type, name, short) = parse_subject(key);
+        let class_type = class_type.unwrap_or_else(|| {
+            untyped += 1;
+            ClassType::Lecture
+        });
+
+        let subject_id = unique_key(&subjects, &slugify(short.as_ref().unwrap_or(&name)), |s| {
+            s["name"] == name.as_str()
+        });
+        let links = parse_description_links(key);
+        let mut subject = json!({ "name": name });
+        if let Some(short) = short {
+            subject["short"] = json!(short);
+        }
+        if let Some(url) = &event.url {
+            subject["subjectUrl"] = json!(url);
+        }
+        if let Some((_, url)) = links.iter().find(|(label, _)| *label == "Notatki") {
+            subject["notesUrl"] = json!(url);
+        }
+        subjects.insert(subject_id.clone(), subject);
+
+        let mut teacher_ids = Vec::new();
+        for (name, email) in parse_teachers(event, key) {
+            let teacher_id = unique_key(&teachers, &slugify(&name), |t| t["name"] == name.as_str());
+            let mut teacher = json!({ "name": name });
+            if let Some(email) = email {
+                teacher["email"] = json!(email);
+            }
+            if let Some((_, url)) = links.iter().find(|(label, _)| *label == name) {
+                teacher["profileUrl"] = json!(url);
+            }
+            teachers.insert(teacher_id.clone(), teacher);
+            teacher_ids.push(teacher_id);
+        }
+
+        let weekday = Weekday::try_from(key.weekday as u8)?;
+        let mut class = json!({
+            "subject": subject_id,
+            "type": class_type.to_id(),
+            "day": weekday_to_id(weekday),
+            "time": format!("{}-{}", key.start.format("%H:%M"), key.end.format("%H:%M")),
+        });
+
+        if let Some(location) = &key.location {
+            class["location"] = parse_location(location);
+        }
+        if !teacher_ids.is_empty() {
+            class["teachers"] = json!(teacher_ids);
+        }
+
+        let class_weeks: BTreeSet<usize> = dates
+            .iter()
+            .map(|date| week_numbers[&date.week(Weekday::Mon).first_day()])
+            .collect();
+        if let Some(weeks) = infer_weeks(&class_weeks, mondays.len()) {
+            class["weeks"] = weeks;
+        }
+
+        schedule.push(class);
+    }
+
+    if untyped > 0 {
+        warn!("Could not detect the type of {} classes, assuming lectures", untyped);
+    }
+
+    let mut imported = Map::new();
+    imported.insert("$schema".to_string(), json!("./schedule.schema.json"));
+    if day_count > 5 {
+        let days: Vec<_> = (0..day_count)
+            .map(|day| weekday_to_id(Weekday::try_from(day as u8).unwrap()))
+            .collect();
+        imported.insert("days".to_string(), json!(days));
+    }
+    imported.insert("weeks".to_string(), json!(weeks));
+    imported.insert("subjects".to_string(), Value::Object(subjects));
+    imported.insert("teachers".to_string(), Value::Object(teachers));
+    imported.insert("schedule".to_string(), json!(schedule));
+
+    Ok(Value::Object(imported))
+}
+
+fn weekday_to_id(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "monday",
+        Weekday::Tue => "tuesday",
+        Weekday::Wed => "wednesday",
+        Weekday::Thu => "thursday",
+        Weekday::Fri => "friday",
+        Weekday::Sat => "saturday",
+        Weekday::Sun => "sunday",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::periods::NaiveTimePeriod;
+    use crate::calendar::schedule::{ICalOptions, Schedule};
+    use crate::calendar::Weeks;
+    use crate::ical::SerializeToICal;
+    use crate::times;
+
+    fn set(weeks: &[usize]) -> BTreeSet<usize> {
+        weeks.iter().copied().collect()
+    }
+
+    /// Returns whether both describe the same set of weeks.
+    fn same_weeks(weeks: Option<&Weeks>, value: Option<&Value>, total: usize) -> bool {
+        let other: Weeks = value
+            .map(|v| serde_json::from_value(v.clone()).unwrap())
+            .unwrap_or_default();
+
+        (1..=total).all(|w| {
+            let w = NonZero::new(w).unwrap();
+            weeks.is_none_or(|weeks| weeks.happens_in_week(w)) == other.happens_in_week(w)
+        })
+    }
+
+    #[test]
+    fn slugs() {
+        assert_eq!(slugify("Język angielski"), "jezyk-angielski");
+        assert_eq!(slugify("  dr inż. Jan Kowalski "), "dr-inz-jan-kowalski");
+        assert_eq!(slugify("🧪"), "unnamed");
+    }
+
+    #[test]
+    fn weeks_inference() {
+        assert_eq!(infer_weeks(&set(&[1, 2, 3, 4]), 4), None);
+        assert_eq!(infer_weeks(&set(&[2, 3]), 4), Some(json!({ "from": 2, "to": 3 })));
+        assert_eq!(infer_weeks(&set(&[1, 3, 5]), 6), Some(json!({ "parity": "odd" })));
+        assert_eq!(
+            infer_weeks(&set(&[4, 6]), 7),
+            Some(json!({ "from": 4, "parity": "even" }))
+        );
+        assert_eq!(infer_weeks(&set(&[1, 2, 5]), 6), Some(json!({ "only": [1, 2, 5] })));
+        assert_eq!(infer_weeks(&set(&[3]), 6), Some(json!({ "from": 3, "to": 3 })));
+    }
+
+    #[test]
+    fn import_floating_times() {
+        let input = "BEGIN:VCALENDAR\r\n\
+                     VERSION:2.0\r\n\
+                     PRODID:-//Test//EN\r\n\
+                     BEGIN:VEVENT\r\n\
+                     UID:aisd@example.com\r\n\
+                     DTSTAMP:20250101T000000Z\r\n\
+                     DTSTART:20251020T081500\r\n\
+                     DTEND:20251020T100000\r\n\
+                     RRULE:FREQ=WEEKLY;COUNT=3\r\n\
+                     SUMMARY:Wykład AiSD\r\n\
+                     END:VEVENT\r\n\
+                     END:VCALENDAR\r\n";
+        let tz = Tz::Europe__Warsaw;
+
+        let calendar = VCalendar::parse_in(input, tz).unwrap();
+        let EventTime::Timed { start, .. } = calendar.events[0].time else {
+            panic!("expected a timed event");
+        };
+        assert_eq!(start.timezone(), tz);
+
+        // The times stay the same after the change to winter time on 26 October
+        let schedule: Schedule = serde_json::from_value(import_calendar(&calendar, &tz).unwrap()).unwrap();
+        assert_eq!(schedule.weeks.len(), 3);
+        assert_eq!(schedule.schedule.len(), 1);
+        assert_eq!(schedule.schedule[0].time, times!(8:15--10:00));
+        assert_eq!(schedule.schedule[0].day, Weekday::Mon);
+    }
+
+    #[test]
+    fn import_generated_calendar() {
+        let original: Schedule = serde_json::from_value(json!({
+            "weeks": [
+                ["2025-10-20", "2025-10-21", "2025-10-22", "2025-10-23", "2025-10-24"],
+                ["2025-10-27", "2025-10-28", "2025-10-29", "2025-10-30", "2025-10-31"],
+                ["2025-11-03", "2025-11-04", "2025-11-05", "2025-11-06", "2025-11-07"],
+                ["2025-11-10", "2025-11-11", "2025-11-12", "2025-11-13", "2025-11-14"]
+            ],
+            "subjects": {
+                "aisd": { "name": "Algorytmy i struktury danych", "short": "AiSD",
+                          "subjectUrl": "https://example.com/aisd", "notesUrl": "https://example.com/aisd/notes" },
+                "ang": { "name": "Język angielski" }
+            },
+            "teachers": {
+                "kowalski": { "name": "dr Jan Kowalski", "email": "jan.kowalski@example.com",
+                              "profileUrl": "https://example.com/~kowalski" },
+                "nowak": { "name": "mgr Anna Nowak" }
+            },
+            "schedule": [
+                { "subject": "aisd", "type": "lecture", "day": "monday", "time": "8:15-10:00",
+                  "location": { "building": "C3", "room": "204" }, "teachers": ["nowak", "kowalski"] },
+                { "subject": "aisd", "type": "lab", "day": "wednesday", "time": "10:15-12:00",
+                  "weeks": { "parity": "odd" } },
+                { "subject": "ang", "type": "languages", "day": "friday", "time": "12:15-14:00",
+                  "location": "online", "weeks": { "only": [1, 2, 4] } }
+            ]
+        }))
+        .unwrap();
+
+        let tz = Tz::Europe__Warsaw;
+
+        for recurring in [false, true] {
+            let generated = original.to_ical(&tz, &ICalOptions { recurring, ..Default::default() }).unwrap();
+            let mut buffer = Vec::new();
+            (&generated as &dyn SerializeToICal).serialize_to_ical(&mut buffer).unwrap();
+            let calendar = VCalendar::parse(&String::from_utf8(buffer).unwrap()).unwrap();
+            let imported = import_calendar(&calendar, &tz).unwrap();
+            let schedule: Schedule = serde_json::from_value(imported.clone()).unwrap();
+
+            assert_eq!(schedule.weeks, original.weeks);
+            assert_eq!(schedule.schedule.len(), 3);
+
+            for class in &original.schedule {
+                let subject = &original.subjects[&class.subject];
+                let imported_class = schedule
+                    .schedule
+                    .iter()
+                    .find(|c| c.class_type == class.class_type && c.day == class.day)
+                    .unwrap();
+
+                assert_eq!(schedule.subjects[&imported_class.subject].name, subject.name);
+                assert_eq!(imported_class.time, class.time);
+                assert_eq!(imported_class.location, class.location);
+
+                let imported_weeks = imported["schedule"]
+                    .as_array()
+                    .unwrap()
+                    .iter()
+                    .find(|c| c["day"] == weekday_to_id(class.day))
+                    .unwrap()
+                    .get("weeks");
+                assert!(same_weeks(
+                    class.weeks.as_ref(),
+                    imported_weeks,
+                    original.weeks.len()
+                ));
+            }
+
+            let lecture_teachers = schedule
+                .schedule
+                .iter()
+                .find(|c| c.class_type == ClassType::Lecture)
+                .and_then(|c| c.teachers.clone())
+                .unwrap();
+            assert_eq!(schedule.teachers[&lecture_teachers[0]].name, "mgr Anna Nowak");
+            assert_eq!(schedule.teachers[&lecture_teachers[0]].email, None);
+
+            let kowalski = &schedule.teachers[&lecture_teachers[1]];
+            assert_eq!(kowalski.name, "dr Jan Kowalski");
+            assert_eq!(kowalski.email.as_deref(), Some("jan.kowalski@example.com"));
+            assert_eq!(kowalski.profile_url.as_deref(), Some("https://example.com/~kowalski"));
+
+            let aisd = schedule
+                .subjects
+                .values()
+                .find(|s| s.name == "Algorytmy i struktury danych")
+                .unwrap();
+            assert_eq!(aisd.subject_url.as_deref(), Some("https://example.com/aisd"));
+            assert_eq!(aisd.notes_url.as_deref(), Some("https://example.com/aisd/notes"));
+        }
+    }
+}
@@ -1,7 +1,18 @@
+pub mod alarms;
 pub mod class;
 pub mod class_type;
+pub mod events;
+pub mod exceptions;
+pub mod filter;
+pub mod holidays;
+pub mod import;
 pub mod periods;
+pub mod revisions;
 pub mod schedule;
+pub mod semester;
+pub mod settings;
+pub mod template;
+pub mod uid;
 
 use serde::Deserialize;
 use std::num::NonZero;
@@ -45,6 +56,35 @@ pub struct Weeks {
 }
 
 impl Weeks {
+    pub fn parity(&self) -> WeekParity {
+        self.parity
+    }
+
+    /// Describes the weeks in a few words, e.g. `odd weeks 3-9`, or returns `None` for all weeks.
+    pub fn label(&self) -> Option<String> {
+        let mut parts = Vec::new();
+
+        match self.parity {
+            WeekParity::All => {}
+            WeekParity::Odd => parts.push("odd".to_string()),
+            WeekParity::Even => parts.push("even".to_string()),
+        }
+        parts.push("weeks".to_string());
+
+        if let Some(only) = &self.only {
+            let only: Vec<_> = only.iter().map(|week| week.to_string()).collect();
+            parts.push(only.join(", "));
+        }
+        match (self.from, self.to) {
+            (Some(from), Some(to)) => parts.push(format!("{}-{}", from, to)),
+            (Some(from), None) => parts.push(format!("from {}", from)),
+            (None, Some(to)) => parts.push(format!("until {}", to)),
+            (None, None) => {}
+        }
+
+        Some(parts.join(" ")).filter(|_| parts.len() > 1)
+    }
+
     pub fn happens_in_week(&self, week: NonZero<usize>) -> bool {
         if self.only.is_some() && !self.only.as_ref().unwrap().contains(&week) {
             return false;
@@ -109,6 +149,17 @@ mod tests {
         );
     }
 
+    #[test]
+    fn weeks_label() {
+        let label = |json: serde_json::Value| serde_json::from_value::<Weeks>(json).unwrap().label();
+
+        assert_eq!(label(json!({})), None);
+        assert_eq!(label(json!({ "parity": "odd" })).as_deref(), Some("odd weeks"));
+        assert_eq!(label(json!({ "parity": "even", "from": 3, "to": 9 })).as_deref(), Some("even weeks 3-9"));
+        assert_eq!(label(json!({ "only": [1, 4] })).as_deref(), Some("weeks 1, 4"));
+        assert_eq!(label(json!({ "to": 7 })).as_deref(), Some("weeks until 7"));
+    }
+
     #[test]
     fn happens_in_week() {
         let weeks = Weeks {
@@ -0,0 +1,252 @@
+//! Tracking of changes between generations of a calendar.
+//!
+//! A state file kept next to the generated calendar records the content hash, sequence number
+//! and modification time of every event. When the calendar is generated again,
+//! only events whose content changed get a new `SEQUENCE` and `LAST-MODIFIED`,
+//! and events which disappeared are kept in the calendar with `STATUS:CANCELLED`
+//! for [`RevisionState::CANCELLED_DAYS`] days, so that clients importing the calendar remove them.
+//! Events are told apart by their UID and `RECURRENCE-ID`, as overrides of occurrences
+//! of a recurring event share its UID.
+
+use crate::calendar::uid::stable_hash;
+use crate::ical::parser::{parse_datetime, parse_single};
+use crate::ical::vcalendar::VCalendar;
+use crate::ical::vevent::{EventStatus, VEvent};
+use crate::ical::SerializeToICal;
+use crate::time::timeext::TimeExt;
+use chrono::{DateTime, TimeDelta, Utc};
+use chrono_tz::Tz;
+use log::info;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs::File;
+use std::path::Path;
+
+/// Returns the hash of an event's content.
+///
+/// `DTSTAMP`, `LAST-MODIFIED` and `SEQUENCE` are ignored, as they change without the content changing.
+pub fn event_content_hash(event: &VEvent) -> eyre::Result<String> {
+    let mut event = event.clone();
+    event.created = DateTime::<Utc>::UNIX_EPOCH.with_timezone(&Tz::UTC);
+    event.last_modified = None;
+    event.sequence = None;
+
+    let mut buffer = Vec::new();
+    event.serialize_to_ical(&mut buffer)?;
+
+    Ok(format!("{:016x}", stable_hash(&[&String::from_utf8_lossy(&buffer)])))
+}
+
+/// Last known revision of an event.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct EventRevision {
+    hash: String,
+    sequence: u32,
+    /// Time of the last change, as an iCalendar UTC date-time.
+    last_modified: String,
+    /// Whether the event was removed from the schedule.
+    #[serde(default)]
+    cancelled: bool,
+    /// The event as last generated, used to write it out again once it's cancelled.
+    event: String,
+}
+
+/// Revisions of all events ever generated into a calendar file, by [`VEvent::instance_key`].
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct RevisionState {
+    events: BTreeMap<String, EventRevision>,
+}
+
+impl RevisionState {
+    /// Number of days cancelled events are written out for, after which they're forgotten.
+    pub const CANCELLED_DAYS: i64 = 30;
+
+    /// Loads the state file, or returns an empty state if it doesn't exist.
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        if !path.exists() {
+            return Ok(RevisionState::default());
+        }
+
+        Ok(serde_json::from_reader(File::open(path)?)?)
+    }
+
+    pub fn save(&self, path: &Path) -> eyre::Result<()> {
+        serde_json::to_writer_pretty(File::create(path)?, self)?;
+        Ok(())
+    }
+
+    /// Sets `SEQUENCE`, `LAST-MODIFIED` and `DTSTAMP` of the calendar's events according to their revisions,
+    /// starting new revisions at `now` for events which changed, and adds cancelled events
+    /// for every event no longer in the calendar, until [`RevisionState::CANCELLED_DAYS`] days after it disappeared.
+    pub fn apply(&mut self, calendar: &mut VCalendar, now: DateTime<Tz>) -> eyre::Result<()> {
+        let now_stamp = now.to_utc_stamp();
+        let (mut changed, mut cancelled) = (0, 0);
+        let mut seen = HashSet::new();
+
+        for event in &mut calendar.events {
+            let hash = event_content_hash(event)?;
+            let key = event.instance_key();
+            // Events with duplicate UIDs (e.g. day markers of a date listed twice) share the first one's revision
+            let first = seen.insert(key.clone());
+
+            let revision = match self.events.get_mut(&key) {
+                Some(revision) if !first || revision.hash == hash && !revision.cancelled => revision,
+                Some(revision) => {
+                    revision.hash = hash;
+                    revision.sequence += 1;
+                    revision.last_modified = now_stamp.clone();
+                    revision.cancelled = false;
+                    changed += 1;
+                    revision
+                }
+                None => {
+                    changed += 1;
+                    self.events.entry(key).or_insert(EventRevision {
+                        hash,
+                        sequence: 0,
+                        last_modified: now_stamp.clone(),
+                        cancelled: false,
+                        event: String::new(),
+                    })
+                }
+            };
+
+            let last_modified = parse_datetime(&revision.last_modified, None)?;
+            event.created = last_modified;
+            event.last_modified = Some(last_modified);
+            event.sequence = Some(revision.sequence);
+
+            if first {
+                let mut buffer = Vec::new();
+                event.serialize_to_ical(&mut buffer)?;
+                revision.event = String::from_utf8(buffer)?;
+            }
+        }
+
+        let keys: HashSet<String> = calendar.events.iter().map(VEvent::instance_key).collect();
+        let mut removed = Vec::new();
+
+        for (key, revision) in &mut self.events {
+            if keys.contains(key) {
+                continue;
+            }
+
+            let mut event: VEvent = parse_single(&revision.event, "VEVENT")?;
+
+            // Occurrences are no longer overridden, cancelling the override would cancel the occurrence
+            if event.recurrence_id.is_some() {
+                removed.push(key.clone());
+                continue;
+            }
+
+            if !revision.cancelled {
+                revision.cancelled = true;
+                revision.sequence += 1;
+                revision.last_modified = now_stamp.clone();
+                cancelled += 1;
+            }
+
+            let last_modified = parse_datetime(&revision.last_modified, None)?;
+            if now - last_modified > TimeDelta::days(Self::CANCELLED_DAYS) {
+                removed.push(key.clone());
+                continue;
+            }
+
+            event.created = last_modified;
+            event.last_modified = Some(last_modified);
+            event.sequence = Some(revision.sequence);
+            event.status = Some(EventStatus::Cancelled);
+            event.alarms.clear();
+
+            calendar.events.push(event);
+        }
+
+        for key in removed {
+            self.events.remove(&key);
+        }
+
+        calendar.add_referenced_timezones();
+
+        info!("{} events changed, {} cancelled", changed, cancelled);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::schedule::{ICalOptions, Schedule};
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    fn generate(classes: serde_json::Value) -> VCalendar {
+        let schedule: Schedule = serde_json::from_value(json!({
+            "weeks": [["2025-10-20", "2025-10-21", "2025-10-22", "2025-10-23", "2025-10-24"]],
+            "subjects": { "aisd": { "name": "AiSD" } },
+            "teachers": {},
+            "schedule": classes
+        }))
+        .unwrap();
+
+        schedule
+            .to_ical(&Tz::Europe__Warsaw, &ICalOptions::default())
+            .unwrap()
+    }
+
+    fn find<'a>(calendar: &'a VCalendar, summary: &str) -> &'a VEvent {
+        calendar.events.iter().find(|e| e.summary == summary).unwrap()
+    }
+
+    #[test]
+    fn track_revisions() {
+        let mut state = RevisionState::default();
+        let first = Tz::UTC.with_ymd_and_hms(2025, 10, 1, 12, 0, 0).unwrap();
+        let second = Tz::UTC.with_ymd_and_hms(2025, 10, 2, 12, 0, 0).unwrap();
+        let third = Tz::UTC.with_ymd_and_hms(2025, 10, 3, 12, 0, 0).unwrap();
+
+        let lecture = json!({ "subject": "aisd", "type": "lecture", "day": "monday", "time": "8:15-10:00" });
+        let lab = json!({ "subject": "aisd", "type": "lab", "day": "tuesday", "time": "10:15-12:00" });
+
+        let mut calendar = generate(json!([lecture, lab]));
+        state.apply(&mut calendar, first).unwrap();
+        assert!(calendar.events.iter().all(|e| e.sequence == Some(0)));
+        assert!(calendar.events.iter().all(|e| e.last_modified == Some(first)));
+
+        let mut moved_lab = lab.clone();
+        moved_lab["time"] = json!("12:15-14:00");
+        let mut calendar = generate(json!([lecture, moved_lab]));
+        state.apply(&mut calendar, second).unwrap();
+
+        let lecture_event = find(&calendar, "📚 AiSD");
+        assert_eq!(lecture_event.sequence, Some(0));
+        assert_eq!(lecture_event.created, first);
+        let lab_event = find(&calendar, "🧪 AiSD");
+        assert_eq!(lab_event.sequence, Some(1));
+        assert_eq!(lab_event.last_modified, Some(second));
+
+        let mut calendar = generate(json!([moved_lab]));
+        state.apply(&mut calendar, third).unwrap();
+
+        let cancelled = find(&calendar, "📚 AiSD");
+        assert_eq!(cancelled.status, Some(EventStatus::Cancelled));
+        assert_eq!(cancelled.sequence, Some(1));
+        assert_eq!(cancelled.last_modified, Some(third));
+        assert_eq!(find(&calendar, "🧪 AiSD").sequence, Some(1));
+
+        // Cancelled events are written out again, but not modified
+        let mut calendar = generate(json!([moved_lab]));
+        state.apply(&mut calendar, third + TimeDelta::days(1)).unwrap();
+        assert_eq!(find(&calendar, "📚 AiSD").last_modified, Some(third));
+
+        // ...until they're forgotten
+        let mut calendar = generate(json!([moved_lab]));
+        state
+            .apply(&mut calendar, third + TimeDelta::days(RevisionState::CANCELLED_DAYS + 1))
+            .unwrap();
+        assert!(calendar.events.iter().all(|e| e.summary != "📚 AiSD"));
+        assert!(calendar.events.iter().all(|e| e.status.is_none()));
+        assert_eq!(state.events.len(), calendar.events.len());
+    }
+}
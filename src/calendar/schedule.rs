@@ -1,7 +1,20 @@
-use crate::calendar::class::Class;
+use crate::calendar::alarms::AlarmSettings;
+use crate::calendar::class::{Class, WeekdayDef};
+use crate::calendar::periods::NaiveTimePeriod;
+use crate::calendar::class_type::ClassType;
+use crate::calendar::events::Event;
+use crate::calendar::exceptions::{ChangedOccurrence, ClassException};
+use crate::calendar::filter::ClassFilter;
+use crate::calendar::holidays::HolidaySettings;
+use crate::calendar::semester::SemesterRules;
+use crate::calendar::settings::{CalendarSettings, DayMarkerSettings};
+use crate::calendar::template::TemplateSettings;
+use crate::calendar::{WeekParity, Weeks};
+use crate::ical::recurrence::RecurrenceRule;
+use crate::ical::valarm::VAlarm;
 use crate::ical::vcalendar::VCalendar;
-use crate::ical::vevent::{EventTime, VEvent};
-use chrono::{Datelike, Local};
+use crate::ical::vevent::{CalendarUser, EventStatus, EventTime, VEvent};
+use chrono::{DateTime, Datelike, Local, TimeDelta};
 use chrono::{NaiveDate, Weekday};
 use chrono_tz::Tz;
 use eyre::{eyre, OptionExt};
@@ -9,9 +22,11 @@ use log::warn;
 use serde::de::IntoDeserializer;
 use serde::{de, Deserialize, Deserializer};
 use std::collections::HashMap;
+use std::fs::File;
 use std::num::NonZero;
+use std::path::Path;
 
-fn deserialize_date<'de, D>(deserializer: D) -> Result<NaiveDate, D::Error>
+pub(crate) fn deserialize_date<'de, D>(deserializer: D) -> Result<NaiveDate, D::Error>
 where
     D: Deserializer<'de>,
 {
@@ -20,30 +35,49 @@ where
         .map_err(|_| de::Error::invalid_value(de::Unexpected::Str(&s), &"expected YYYY-MM-DD"))
 }
 
-fn deserialize_weeks<'de, D>(deserializer: D) -> Result<Vec<[NaiveDate; 5]>, D::Error>
+pub(crate) fn deserialize_optional_date<'de, D>(deserializer: D) -> Result<Option<NaiveDate>, D::Error>
 where
     D: Deserializer<'de>,
 {
-    let raw_weeks: Vec<Vec<String>> = Vec::deserialize(deserializer)?;
+    deserialize_date(deserializer).map(Some)
+}
+
+/// Dates of the days of a week of the schedule, in the order of [`Schedule::days`].
+///
+/// `None` marks days which don't occur in the week, e.g. a Saturday during a break.
+pub type Week = Vec<Option<NaiveDate>>;
+
+fn deserialize_weeks<'de, D>(deserializer: D) -> Result<Vec<Week>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let raw_weeks: Vec<Vec<Option<String>>> = Vec::deserialize(deserializer)?;
 
     raw_weeks
         .into_iter()
         .map(|week| {
-            if week.len() != 5 {
-                return Err(de::Error::invalid_length(
-                    week.len(),
-                    &"expected 5 dates per week",
-                ));
-            }
-            let mut arr = [NaiveDate::from_ymd_opt(1970, 1, 1).unwrap(); 5]; // temporary init
-            for (i, s) in week.into_iter().enumerate() {
-                arr[i] = deserialize_date(s.into_deserializer())?;
-            }
-            Ok(arr)
+            week.into_iter()
+                .map(|day| day.map(|s| deserialize_date(s.into_deserializer())).transpose())
+                .collect()
         })
         .collect()
 }
 
+fn default_days() -> Vec<Weekday> {
+    vec![Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri]
+}
+
+fn deserialize_days<'de, D>(deserializer: D) -> Result<Vec<Weekday>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    struct Day(#[serde(with = "WeekdayDef")] Weekday);
+
+    let days: Vec<Day> = Vec::deserialize(deserializer)?;
+    Ok(days.into_iter().map(|Day(day)| day).collect())
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct Subject {
@@ -63,168 +97,540 @@ impl Subject {
 #[serde(rename_all = "camelCase")]
 pub struct Teacher {
     pub name: String,
+    pub email: Option<String>,
     pub profile_url: Option<String>,
 }
 
+/// Options affecting how [`Schedule::to_ical`] generates events.
+#[derive(Debug, Default, Clone)]
+pub struct ICalOptions {
+    /// Whether to generate a single recurring event per class
+    /// instead of a separate event for every occurrence.
+    pub recurring: bool,
+    /// Classes to generate events for. Day markers and events other than classes are generated regardless.
+    pub filter: ClassFilter,
+}
+
+/// Timing of an event repeating weekly, see [`Schedule::weekly_series`].
+pub(crate) struct WeeklySeries {
+    /// Time of the first occurrence.
+    pub time: EventTime,
+    pub recurrence: RecurrenceRule,
+    pub exception_dates: Vec<DateTime<Tz>>,
+    pub recurrence_dates: Vec<DateTime<Tz>>,
+}
+
+impl WeeklySeries {
+    /// Makes the event repeat according to the series.
+    pub fn apply(self, event: VEvent) -> VEvent {
+        VEvent {
+            time: self.time,
+            recurrence: Some(self.recurrence),
+            exception_dates: self.exception_dates,
+            recurrence_dates: self.recurrence_dates,
+            ..event
+        }
+    }
+}
+
+/// A single occurrence of a class on a specific day of the schedule.
+#[derive(Debug, Clone, Copy)]
+pub struct Occurrence<'a> {
+    pub week: NonZero<usize>,
+    pub weekday: Weekday,
+    pub date: NaiveDate,
+    pub class: &'a Class,
+    /// The change the class is the replacement of, if the occurrence is changed by an exception.
+    pub change: Option<&'a ChangedOccurrence>,
+}
+
+/// A schedule, with its `weeks` table generated from the semester rules and its exceptions resolved
+/// once it's deserialized.
 #[derive(Debug, Deserialize)]
+#[serde(remote = "Self")]
 pub struct Schedule {
-    #[serde(deserialize_with = "deserialize_weeks")]
-    pub weeks: Vec<[NaiveDate; 5]>,
+    #[serde(default)]
+    pub calendar: CalendarSettings,
+    #[serde(default)]
+    pub alarms: AlarmSettings,
+    /// CSS3 color names of events by class type, overriding [`ClassType::to_css_color`].
+    #[serde(default)]
+    pub colors: HashMap<ClassType, String>,
+    #[serde(default, rename = "dayMarkers")]
+    pub day_markers: DayMarkerSettings,
+    #[serde(default)]
+    pub templates: TemplateSettings,
+    /// Weekdays of the columns of the `weeks` table, Monday to Friday by default.
+    #[serde(default = "default_days", deserialize_with = "deserialize_days")]
+    pub days: Vec<Weekday>,
+    /// Holidays, skipped by the semester rules and flagged if classes fall on them.
+    #[serde(default)]
+    pub holidays: HolidaySettings,
+    /// Rules from which the `weeks` table is generated when it isn't listed explicitly.
+    #[serde(default)]
+    pub semester: Option<SemesterRules>,
+    #[serde(default, deserialize_with = "deserialize_weeks")]
+    pub weeks: Vec<Week>,
     pub subjects: HashMap<String, Subject>,
     pub teachers: HashMap<String, Teacher>,
     pub schedule: Vec<Class>,
+    /// One-off changes of single occurrences of classes.
+    #[serde(default)]
+    pub exceptions: Vec<ClassException>,
+    /// The `exceptions`, resolved by [`Schedule::resolve_exceptions`].
+    #[serde(skip)]
+    pub changes: Vec<ChangedOccurrence>,
+    /// Events other than classes, e.g. exams, deadlines and office hours.
+    #[serde(default)]
+    pub events: Vec<Event>,
+}
+
+impl<'de> Deserialize<'de> for Schedule {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let value = serde_json::Value::deserialize(deserializer)?;
+        match (value.get("weeks"), value.get("semester")) {
+            (Some(_), Some(_)) => {
+                return Err(de::Error::custom(
+                    "the schedule lists both weeks and semester rules, remove one of them",
+                ))
+            }
+            (None, None) => return Err(de::Error::custom("the schedule has neither weeks nor semester rules")),
+            _ => {}
+        }
+
+        let mut schedule = Schedule::deserialize(value).map_err(de::Error::custom)?;
+        schedule.resolve().map_err(de::Error::custom)?;
+        Ok(schedule)
+    }
 }
 
 impl Schedule {
-    pub fn get_day(&self, date: NaiveDate) -> eyre::Result<(NonZero<usize>, Weekday)> {
-        for (week_index, week) in self.weeks.iter().enumerate() {
-            for (day_index, day) in week.iter().enumerate() {
-                if *day == date {
-                    let weekday = Weekday::try_from(day_index as u8)?;
-                    return Ok((
-                        NonZero::new(week_index + 1).ok_or_eyre("week number would be zero")?,
-                        weekday,
-                    ));
-                }
+    /// Loads a schedule file, loading its holiday files and checking its events.
+    ///
+    /// Days with classes falling on holidays are reported as warnings.
+    pub fn load(path: &Path) -> eyre::Result<Self> {
+        let mut schedule: Schedule = serde_json::from_reader(File::open(path)?)?;
+        schedule.holidays.load_files(path.parent().unwrap_or(Path::new(".")))?;
+        if schedule.semester.is_some() && !schedule.holidays.extra.is_empty() {
+            // The weeks were generated before the holidays from the files were known
+            schedule.resolve()?;
+        }
+        schedule.check_events()?;
+
+        for (week, weekday, date, holiday) in schedule.holiday_conflicts() {
+            warn!("Week {}, {} ({}) has classes, but it's a holiday: {}", week, weekday, date, holiday);
+        }
+
+        Ok(schedule)
+    }
+
+    /// Returns the days of the `weeks` table with classes which fall on holidays, with the names of the holidays.
+    pub fn holiday_conflicts(&self) -> Vec<(NonZero<usize>, Weekday, NaiveDate, &str)> {
+        self.days()
+            .filter_map(|(week, weekday, date)| {
+                let holiday = self.holidays.holiday_on(date)?;
+                self.get_classes_on(week, weekday).next()?;
+                Some((week, weekday, date, holiday))
+            })
+            .collect()
+    }
+
+    /// Generates the `weeks` table from the `semester` rules, ensures it matches the `days`,
+    /// and resolves the `exceptions`.
+    ///
+    /// Called once the schedule is deserialized, and again by [`Schedule::load`] if holidays are loaded from files.
+    pub fn resolve(&mut self) -> eyre::Result<()> {
+        self.resolve_weeks()?;
+        self.check_weeks()?;
+        self.resolve_exceptions()
+    }
+
+    /// Generates the `weeks` table from the `semester` rules, if there are any.
+    pub fn resolve_weeks(&mut self) -> eyre::Result<()> {
+        let Some(semester) = &self.semester else {
+            return Ok(());
+        };
+
+        self.weeks = semester.generate_weeks(&self.days, &self.holidays)?;
+        Ok(())
+    }
+
+    /// Ensures the weekdays in `days` are unique and every week has a date (or `null`) for each of them.
+    pub fn check_weeks(&self) -> eyre::Result<()> {
+        if self.days.is_empty() {
+            return Err(eyre!("the schedule has no days"));
+        }
+        for (index, day) in self.days.iter().enumerate() {
+            if self.days[..index].contains(day) {
+                return Err(eyre!("{} is listed twice in days", day));
+            }
+        }
+
+        for (index, week) in self.weeks.iter().enumerate() {
+            if week.len() != self.days.len() {
+                return Err(eyre!(
+                    "week {} has {} days, expected {} (one for each of the days)",
+                    index + 1,
+                    week.len(),
+                    self.days.len()
+                ));
             }
         }
 
-        Err(eyre!("date not found in schedule: {}", date))
+        Ok(())
+    }
+
+    pub fn get_day(&self, date: NaiveDate) -> eyre::Result<(NonZero<usize>, Weekday)> {
+        self.days()
+            .find(|(_, _, day)| *day == date)
+            .map(|(week_no, weekday, _)| (week_no, weekday))
+            .ok_or_else(|| eyre!("date not found in schedule: {}", date))
+    }
+
+    /// Returns the days of a week, as `(scheduled weekday, date)`, including the ones which don't occur.
+    pub fn week_days(&self, week: NonZero<usize>) -> eyre::Result<Vec<(Weekday, Option<NaiveDate>)>> {
+        let dates = self
+            .weeks
+            .get(week.get() - 1)
+            .ok_or_else(|| eyre!("Week number out of range: {}", week))?;
+
+        Ok(self.days.iter().copied().zip(dates.iter().copied()).collect())
     }
 
+    /// Returns the classes taking place on the day, taking the exceptions into account.
+    ///
+    /// Changed occurrences are skipped, and their replacements are returned on the days they're moved to.
     pub fn get_classes_on(
         &self,
         week_number: NonZero<usize>,
         weekday: Weekday,
     ) -> impl Iterator<Item = &Class> {
-        let classes = self
+        self.classes_on(week_number, weekday).map(|(class, _)| class)
+    }
+
+    /// Returns the classes taking place on the day, with the changes replaced by them.
+    fn classes_on(
+        &self,
+        week_number: NonZero<usize>,
+        weekday: Weekday,
+    ) -> impl Iterator<Item = (&Class, Option<&ChangedOccurrence>)> {
+        let regular = self
             .schedule
             .iter()
-            .filter(move |class| class.happens_on(week_number, weekday));
+            .filter(move |class| class.happens_on(week_number, weekday) && self.change_of(class, week_number).is_none())
+            .map(|class| (class, None));
+        let replacements = self.changes.iter().filter_map(move |change| {
+            let replacement = change.replacement.as_ref()?;
+            (replacement.week == week_number && replacement.weekday == weekday).then_some((&replacement.class, Some(change)))
+        });
 
-        classes
+        regular.chain(replacements)
     }
 
-    pub fn to_ical(&self, tz: &Tz) -> eyre::Result<VCalendar> {
-        let mut events = Vec::with_capacity(64);
+    /// Returns the number of the weekday's column of the `weeks` table, starting at 1,
+    /// as used by the `W<week>D<day>` date syntax.
+    pub fn day_number(&self, weekday: Weekday) -> Option<usize> {
+        self.days.iter().position(|day| *day == weekday).map(|index| index + 1)
+    }
+
+    /// Returns all days in the schedule, as `(week number, scheduled weekday, date)`.
+    pub fn days(&self) -> impl Iterator<Item = (NonZero<usize>, Weekday, NaiveDate)> + '_ {
+        self.weeks.iter().enumerate().flat_map(|(week_index, week)| {
+            let week_no = NonZero::new(week_index + 1).unwrap();
+
+            self.days
+                .iter()
+                .zip(week)
+                .filter_map(move |(weekday, day)| Some((week_no, *weekday, (*day)?)))
+        })
+    }
+
+    /// Returns all occurrences of all classes, in chronological order of the schedule days,
+    /// taking the exceptions into account.
+    pub fn occurrences(&self) -> impl Iterator<Item = Occurrence<'_>> {
+        self.days().flat_map(move |(week, weekday, date)| {
+            self.classes_on(week, weekday).map(move |(class, change)| Occurrence {
+                week,
+                weekday,
+                date,
+                class,
+                change,
+            })
+        })
+    }
+
+    /// Returns all regularly scheduled occurrences of a single class, ignoring the exceptions.
+    pub fn occurrences_of<'a>(&'a self, class: &'a Class) -> impl Iterator<Item = Occurrence<'a>> {
+        self.days()
+            .filter(move |(week, weekday, _)| class.happens_on(*week, *weekday))
+            .map(move |(week, weekday, date)| Occurrence {
+                week,
+                weekday,
+                date,
+                class,
+                change: None,
+            })
+    }
+
+    /// Generates an event for a class with the given UID and time,
+    /// with all properties describing the class, but without recurrence.
+    ///
+    /// The summary, description and location are filled from the schedule's [`TemplateSettings`].
+    /// Teachers with an email address are also added as the organizer (the first one) and attendees.
+    fn class_event(
+        &self,
+        class: &Class,
+        uid: String,
+        now: DateTime<Tz>,
+        time: EventTime,
+    ) -> eyre::Result<VEvent> {
+        let context = self.class_context(class)?;
+        let summary = context.render(&self.templates.summary);
+
+        let mut users = context.teachers.iter().filter_map(|teacher| {
+            Some(CalendarUser {
+                name: Some(teacher.name.clone()),
+                email: teacher.email.clone()?,
+            })
+        });
+
+        Ok(VEvent {
+            alarms: self.class_alarms(class, &summary),
+            categories: vec![class.class_type.to_name().to_string(), context.subject.name.clone()],
+            color: Some(self.class_color(class.class_type).to_string()),
+            description: Some(context.render(&self.templates.description)),
+            location: Some(context.render(&self.templates.location)),
+            url: context.subject.subject_url.clone(),
+            organizer: users.next(),
+            attendees: users.collect(),
+            ..VEvent::new(uid, now, time, summary)
+        })
+    }
+
+    /// Returns the alarms of the events generated for a class, according to the alarm settings.
+    fn class_alarms(&self, class: &Class, summary: &str) -> Vec<VAlarm> {
+        self.alarms
+            .minutes_before(class)
+            .map(|minutes| {
+                VAlarm::display_before(TimeDelta::minutes(minutes as i64), summary.to_string())
+            })
+            .into_iter()
+            .collect()
+    }
+
+    /// Returns the CSS3 color name of the events generated for a class.
+    pub fn class_color(&self, class_type: ClassType) -> &str {
+        self.colors
+            .get(&class_type)
+            .map(String::as_str)
+            .unwrap_or(class_type.to_css_color())
+    }
+
+    pub(crate) fn period_start_end(
+        period: &NaiveTimePeriod,
+        date: NaiveDate,
+        tz: &Tz,
+    ) -> eyre::Result<(DateTime<Tz>, DateTime<Tz>)> {
+        let start = date
+            .and_time(period.start)
+            .and_local_timezone(*tz)
+            .single()
+            .ok_or_eyre("ambiguous or non-existent start time")?;
+        let end = date
+            .and_time(period.end)
+            .and_local_timezone(*tz)
+            .single()
+            .ok_or_eyre("ambiguous or non-existent end time")?;
+
+        Ok((start, end))
+    }
+
+    fn class_start_end(class: &Class, date: NaiveDate, tz: &Tz) -> eyre::Result<(DateTime<Tz>, DateTime<Tz>)> {
+        Self::period_start_end(&class.time, date, tz)
+    }
+
+    /// Returns the timing of a single event repeating on the dates at the time of the period,
+    /// or `None` if there are no dates.
+    ///
+    /// The recurrence rule repeats weekly, or every other week for odd/even week parity,
+    /// from the first date falling on `weekday` to the last date.
+    /// Dates generated by the rule which aren't among the dates are excluded with `EXDATE`,
+    /// and dates not matching the rule (e.g. swapped days) are added with `RDATE`.
+    pub(crate) fn weekly_series(
+        dates: &[NaiveDate],
+        weekday: Weekday,
+        weeks: Option<&Weeks>,
+        period: &NaiveTimePeriod,
+        tz: &Tz,
+    ) -> eyre::Result<Option<WeeklySeries>> {
+        let Some(&last) = dates.last() else {
+            return Ok(None);
+        };
+        // Swapped days don't fall on the weekday, so they can't anchor the rule
+        let first = dates
+            .iter()
+            .copied()
+            .find(|date| date.weekday() == weekday)
+            .unwrap_or(dates[0]);
+
+        let interval = match weeks.map(Weeks::parity) {
+            Some(WeekParity::Odd | WeekParity::Even) => 2,
+            _ => 1,
+        };
+
+        let rule_dates: Vec<NaiveDate> = first
+            .iter_days()
+            .step_by(7 * interval as usize)
+            .take_while(|date| *date <= last)
+            .collect();
+
+        let to_start = |date: &NaiveDate| Self::period_start_end(period, *date, tz).map(|(start, _)| start);
+
+        let exception_dates = rule_dates
+            .iter()
+            .filter(|date| !dates.contains(date))
+            .map(to_start)
+            .collect::<eyre::Result<_>>()?;
+        let recurrence_dates = dates
+            .iter()
+            .filter(|date| !rule_dates.contains(date))
+            .map(to_start)
+            .collect::<eyre::Result<_>>()?;
+
+        let (start, end) = Self::period_start_end(period, first, tz)?;
+        let until = to_start(rule_dates.last().unwrap())?;
+
+        Ok(Some(WeeklySeries {
+            time: EventTime::Timed { start, end },
+            recurrence: RecurrenceRule::weekly(interval, until),
+            exception_dates,
+            recurrence_dates,
+        }))
+    }
+
+    /// Generates a single event repeating for all occurrences of the class, see [`Schedule::weekly_series`].
+    fn class_to_series(&self, class: &Class, tz: &Tz, now: DateTime<Tz>) -> eyre::Result<Option<VEvent>> {
+        let dates: Vec<NaiveDate> = self.occurrences_of(class).map(|o| o.date).collect();
 
-        for (week_index, week) in self.weeks.iter().enumerate() {
-            for (day_index, day) in week.iter().enumerate() {
-                let scheduled_weekday = Weekday::try_from(day_index as u8)?;
-                let real_weekday = day.weekday();
-
-                let now = Local::now().with_timezone(tz);
-
-                // Generate all-day event
-                let mut event_name = format!("{} of week {}", scheduled_weekday, week_index + 1);
-
-                if scheduled_weekday != real_weekday {
-                    warn!(
-                        "Weekday mismatch on week {}, day {}: scheduled {}, real {}",
-                        week_index + 1,
-                        day_index + 1,
-                        scheduled_weekday,
-                        real_weekday
-                    );
-                    event_name = event_name.to_uppercase();
+        let Some(series) = Self::weekly_series(&dates, class.day, class.weeks.as_ref(), &class.time, tz)? else {
+            return Ok(None);
+        };
+
+        let event = self.class_event(class, self.series_uid(class), now, series.time.clone())?;
+        Ok(Some(series.apply(event)))
+    }
+
+    /// Generates an event overriding a single occurrence of the recurring event of the class,
+    /// identified by `RECURRENCE-ID`, which is either cancelled or replaced.
+    fn change_to_override(&self, change: &ChangedOccurrence, tz: &Tz, now: DateTime<Tz>) -> eyre::Result<VEvent> {
+        let class = self.changed_class(change);
+        let (start, end) = Self::class_start_end(class, change.date, tz)?;
+        let uid = self.series_uid(class);
+
+        Ok(match &change.replacement {
+            Some(replacement) => {
+                let (new_start, new_end) = Self::class_start_end(&replacement.class, replacement.date, tz)?;
+                VEvent {
+                    recurrence_id: Some(start),
+                    ..self.class_event(&replacement.class, uid, now, EventTime::Timed { start: new_start, end: new_end })?
                 }
+            }
+            None => VEvent {
+                recurrence_id: Some(start),
+                status: Some(EventStatus::Cancelled),
+                ..self.class_event(class, uid, now, EventTime::Timed { start, end })?
+            },
+        })
+    }
 
-                {
-                    let event = VEvent {
-                        uid: format!("w{}-d{}", week_index + 1, day_index + 1),
-                        created: now,
-                        time: EventTime::FullDay(*day),
-                        summary: event_name,
-                        description: None,
-                        location: None,
-                    };
-
-                    events.push(event);
+    pub fn to_ical(&self, tz: &Tz, options: &ICalOptions) -> eyre::Result<VCalendar> {
+        self.check_weeks()?;
+        self.check_class_ids()?;
+        self.check_events()?;
+        self.templates.check()?;
+        self.day_markers.check()?;
+
+        let mut events = Vec::with_capacity(64);
+
+        let now = Local::now().with_timezone(tz);
+
+        for (week_no, scheduled_weekday, day) in self.days() {
+            let real_weekday = day.weekday();
+            let day_no = self.day_number(scheduled_weekday).unwrap_or_default();
+
+            if scheduled_weekday != real_weekday {
+                warn!(
+                    "Weekday mismatch on week {}, day {}: scheduled {}, real {}",
+                    week_no,
+                    day_no,
+                    scheduled_weekday,
+                    real_weekday
+                );
+            }
+
+            let Some(title) = self.day_markers.title_for(week_no.get(), day_no, scheduled_weekday, day) else {
+                continue;
+            };
+
+            events.push(VEvent {
+                transparent: self.day_markers.transparent,
+                ..VEvent::new(self.day_marker_uid(day), now, EventTime::full_day(day), title)
+            });
+        }
+
+        if options.recurring {
+            for class in self.schedule.iter().filter(|class| options.filter.matches(class)) {
+                let Some(event) = self.class_to_series(class, tz, now)? else {
+                    continue;
+                };
+                events.push(event);
+
+                for change in self.changes.iter().filter(|change| std::ptr::eq(self.changed_class(change), class)) {
+                    events.push(self.change_to_override(change, tz, now)?);
                 }
+            }
+        } else {
+            for occurrence in self.occurrences().filter(|o| options.filter.matches(o.class)) {
+                let class = occurrence.class;
+                let (start, end) = Self::class_start_end(class, occurrence.date, tz)?;
+                // Replacements keep the UID of the occurrence they replace, so that it's updated
+                let uid = match occurrence.change {
+                    Some(change) => self.occurrence_uid(self.changed_class(change), change.date),
+                    None => self.occurrence_uid(class, occurrence.date),
+                };
+
+                events.push(self.class_event(class, uid, now, EventTime::Timed { start, end })?);
+            }
 
-                for class in &self.schedule {
-                    if class.weeks.is_some()
-                        && !class.weeks.as_ref().unwrap().happens_in_week(
-                            NonZero::new(week_index + 1).ok_or_eyre("week number would be zero")?,
-                        )
-                    {
-                        continue;
-                    }
-
-                    if class.day != scheduled_weekday {
-                        continue;
-                    }
-
-                    let subject = self
-                        .subjects
-                        .get(&class.subject)
-                        .ok_or_else(|| eyre!("subject not found: {}", class.subject))?;
-                    let teachers: Vec<String> = match &class.teachers {
-                        Some(teacher_ids) => teacher_ids
-                            .iter()
-                            .map(|id| {
-                                self.teachers
-                                    .get(id)
-                                    .map(|t| t.name.clone())
-                                    .ok_or_else(|| eyre!("teacher not found: {}", id))
-                            })
-                            .collect::<Result<_, _>>()?,
-                        None => vec![],
-                    };
-                    let location = match &class.location {
-                        Some(crate::calendar::class::Location::Offline { building, room }) => {
-                            Some(format!("{}/{}", room, building))
-                        }
-                        Some(crate::calendar::class::Location::Online) => Some("Online".to_string()),
-                        None => None,
-                    };
-
-                    // TODO Better UID generation
-                    let uid = format!(
-                        "{}-{}-{}-{}-{}",
-                        class.class_type.to_name(),
-                        class.subject.replace(" ", "_"),
-                        class.day,
-                        week_index,
-                        class.time.start.format("%H%M"),
-                    );
-
-                    let start = day
-                        .and_time(class.time.start)
-                        .and_local_timezone(*tz)
-                        .single()
-                        .ok_or_eyre("ambiguous or non-existent start time")?;
-                    let end = day
-                        .and_time(class.time.end)
-                        .and_local_timezone(*tz)
-                        .single()
-                        .ok_or_eyre("ambiguous or non-existent end time")?;
-
-                    let event = VEvent {
-                        uid,
-                        created: now,
-                        time: EventTime::Timed { start, end },
-                        summary: format!("{} {}", class.class_type.to_emoji(), subject.get_short_or_name()),
-                        description: Some(format!(
-                            "{}: {}\n{}",
-                            class.class_type.to_name(),
-                            subject.name,
-                            teachers.join("\n"),
-                        )),
-                        location: Some(location.unwrap_or_default()),
-                    };
-
-                    events.push(event);
+            for change in &self.changes {
+                let class = self.changed_class(change);
+                if change.replacement.is_some() || !options.filter.matches(class) {
+                    continue;
                 }
+
+                let (start, end) = Self::class_start_end(class, change.date, tz)?;
+                events.push(VEvent {
+                    status: Some(EventStatus::Cancelled),
+                    ..self.class_event(class, self.occurrence_uid(class, change.date), now, EventTime::Timed { start, end })?
+                });
             }
         }
 
-        let cal = VCalendar {
-            prod_id: "-//TheChilliPL//Planner//PL".to_string(),
+        events.extend(self.events_to_ical(tz, options.recurring, now)?);
+
+        let mut cal = VCalendar {
+            prod_id: self.calendar.get_prod_id().to_string(),
             version: "2.0".to_string(),
+            timezones: Vec::new(),
             events,
+            extra_properties: self.calendar.to_ical_properties(tz),
         };
 
+        cal.add_referenced_timezones();
+
         Ok(cal)
     }
 }
@@ -232,6 +638,7 @@ impl Schedule {
 #[cfg(test)]
 mod test {
     use super::*;
+    use crate::ical::SerializeToICal;
     use serde_json::json;
 
     #[test]
@@ -259,11 +666,326 @@ mod test {
         assert_eq!(schedule.weeks.len(), 1);
         assert_eq!(
             schedule.weeks[0][0],
-            NaiveDate::from_ymd_opt(2025, 01, 01).unwrap()
+            Some(NaiveDate::from_ymd_opt(2025, 01, 01).unwrap())
         );
         assert_eq!(schedule.subjects.len(), 1);
         assert_eq!(schedule.subjects.get("subj").unwrap().name, "Subject");
         assert_eq!(schedule.teachers.get("teacher1").unwrap().name, "Teacher");
         assert_eq!(schedule.schedule.len(), 0);
     }
+
+    #[test]
+    fn recurring_class_series() {
+        let json = json!({
+            "weeks": [
+                ["2025-10-20", "2025-10-21", "2025-10-22", "2025-10-23", "2025-10-24"],
+                ["2025-10-29", "2025-10-28", "2025-10-29", "2025-10-30", "2025-10-31"],
+                ["2025-11-03", "2025-11-04", "2025-11-05", "2025-11-06", "2025-11-07"],
+                ["2025-11-10", "2025-11-11", "2025-11-12", "2025-11-13", "2025-11-14"]
+            ],
+            "subjects": {
+                "subj": { "name": "Subject" }
+            },
+            "teachers": {},
+            "colors": { "lab": "olive" },
+            "schedule": [
+                {
+                    "subject": "subj",
+                    "type": "lab",
+                    "day": "monday",
+                    "time": "10:15-12:00",
+                    "weeks": { "only": [1, 2, 4] }
+                },
+                {
+                    "subject": "subj",
+                    "type": "lecture",
+                    "day": "monday",
+                    "time": "10:15-12:00",
+                    "weeks": { "only": [2, 3, 4] }
+                }
+            ]
+        });
+
+        let schedule: Schedule = serde_json::from_value(json).unwrap();
+        let tz = Tz::Europe__Warsaw;
+        let at = |date: NaiveDate| {
+            date.and_hms_opt(10, 15, 0)
+                .unwrap()
+                .and_local_timezone(tz)
+                .unwrap()
+        };
+
+        let event = schedule
+            .class_to_series(&schedule.schedule[0], &tz, Local::now().with_timezone(&tz))
+            .unwrap()
+            .unwrap();
+
+        assert_eq!(
+            event.recurrence,
+            Some(RecurrenceRule::weekly(1, at(NaiveDate::from_ymd_opt(2025, 11, 10).unwrap())))
+        );
+        assert_eq!(
+            event.exception_dates,
+            vec![
+                at(NaiveDate::from_ymd_opt(2025, 10, 27).unwrap()),
+                at(NaiveDate::from_ymd_opt(2025, 11, 3).unwrap()),
+            ]
+        );
+        assert_eq!(
+            event.recurrence_dates,
+            vec![at(NaiveDate::from_ymd_opt(2025, 10, 29).unwrap())]
+        );
+        assert_eq!(event.categories, vec!["Laboratorium", "Subject"]);
+        assert_eq!(event.color.as_deref(), Some("olive"));
+        assert_eq!(schedule.class_color(ClassType::Lecture), "mediumpurple");
+
+        // The first occurrence is on a swapped day, so the rule starts on the first Monday
+        let event = schedule
+            .class_to_series(&schedule.schedule[1], &tz, Local::now().with_timezone(&tz))
+            .unwrap()
+            .unwrap();
+        assert!(matches!(event.time, EventTime::Timed { start, .. } if start == at(NaiveDate::from_ymd_opt(2025, 11, 3).unwrap())));
+        assert!(event.exception_dates.is_empty());
+        assert_eq!(
+            event.recurrence_dates,
+            vec![at(NaiveDate::from_ymd_opt(2025, 10, 29).unwrap())]
+        );
+    }
+
+    #[test]
+    fn class_event_teachers() {
+        let schedule: Schedule = serde_json::from_value(json!({
+            "weeks": [
+                ["2025-10-20", "2025-10-21", "2025-10-22", "2025-10-23", "2025-10-24"]
+            ],
+            "subjects": {
+                "aisd": { "name": "Algorytmy", "short": "AiSD", "subjectUrl": "https://example.com/aisd" }
+            },
+            "teachers": {
+                "kowalski": { "name": "dr Jan Kowalski", "email": "jan.kowalski@example.com",
+                              "profileUrl": "https://example.com/~kowalski" },
+                "nowak": { "name": "mgr Anna Nowak" },
+                "wisniewska": { "name": "dr Ewa Wiśniewska", "email": "ewa@example.com" }
+            },
+            "schedule": [
+                { "subject": "aisd", "type": "lecture", "day": "monday", "time": "8:15-10:00",
+                  "teachers": ["nowak", "kowalski", "wisniewska"] }
+            ]
+        }))
+        .unwrap();
+
+        let tz = Tz::Europe__Warsaw;
+        let event = schedule
+            .class_event(
+                &schedule.schedule[0],
+                "uid".to_string(),
+                Local::now().with_timezone(&tz),
+                EventTime::full_day(NaiveDate::from_ymd_opt(2025, 10, 20).unwrap()),
+            )
+            .unwrap();
+
+        assert_eq!(event.url.as_deref(), Some("https://example.com/aisd"));
+        assert!(event.description.unwrap().contains("https://example.com/~kowalski"));
+
+        let calendar = schedule.to_ical(&tz, &ICalOptions::default()).unwrap();
+        let mut buffer = Vec::new();
+        (&calendar as &dyn SerializeToICal).serialize_to_ical(&mut buffer).unwrap();
+        let serialized = String::from_utf8(buffer).unwrap();
+
+        assert!(serialized.contains("ORGANIZER;CN=dr Jan Kowalski:mailto:jan.kowalski@example.com\r\n"));
+        assert!(serialized.contains("ATTENDEE;CN=dr Ewa Wiśniewska:mailto:ewa@example.com\r\n"));
+        assert_eq!(serialized.matches("ATTENDEE").count(), 1);
+        assert!(!serialized.contains("Nowak:mailto"));
+    }
+
+    #[test]
+    fn weekend_days() {
+        let schedule: Schedule = serde_json::from_value(json!({
+            "days": ["friday", "saturday", "sunday"],
+            "weeks": [
+                ["2025-10-17", "2025-10-18", "2025-10-19"],
+                [null, "2025-11-08", "2025-11-09"]
+            ],
+            "subjects": { "aisd": { "name": "AiSD" } },
+            "teachers": {},
+            "schedule": [
+                { "subject": "aisd", "type": "lab", "day": "saturday", "time": "8:15-10:00" },
+                { "subject": "aisd", "type": "lecture", "day": "sunday", "time": "12:15-14:00",
+                  "weeks": { "only": [2] } }
+            ]
+        }))
+        .unwrap();
+        schedule.check_weeks().unwrap();
+
+        let date = |month, day| NaiveDate::from_ymd_opt(2025, month, day).unwrap();
+        assert_eq!(schedule.get_day(date(11, 8)).unwrap(), (NonZero::new(2).unwrap(), Weekday::Sat));
+        assert!(schedule.get_day(date(11, 7)).is_err());
+        assert_eq!(schedule.days().count(), 5);
+
+        let dates: Vec<_> = schedule.occurrences().map(|o| o.date).collect();
+        assert_eq!(dates, vec![date(10, 18), date(11, 8), date(11, 9)]);
+
+        let calendar = schedule.to_ical(&Tz::Europe__Warsaw, &ICalOptions::default()).unwrap();
+        assert_eq!(calendar.events.iter().filter(|e| e.summary.contains("AiSD")).count(), 3);
+
+        let mut invalid = schedule;
+        invalid.weeks.push(vec![Some(date(11, 14))]);
+        assert!(invalid.check_weeks().is_err());
+        invalid.weeks.pop();
+        invalid.days[2] = Weekday::Fri;
+        assert!(invalid.check_weeks().is_err());
+    }
+
+    #[test]
+    fn holidays() {
+        let mut schedule: Schedule = serde_json::from_value(json!({
+            "holidays": { "calendar": "pl" },
+            "semester": { "start": "2025-11-03", "weeks": 2 },
+            "subjects": { "aisd": { "name": "AiSD" } },
+            "teachers": {},
+            "schedule": [
+                { "subject": "aisd", "type": "lab", "day": "tuesday", "time": "8:15-10:00" }
+            ]
+        }))
+        .unwrap();
+
+        let date = |month, day| NaiveDate::from_ymd_opt(2025, month, day).unwrap();
+        // 11 November is skipped by the semester rules
+        assert_eq!(schedule.weeks[1][1], None);
+        assert!(schedule.holiday_conflicts().is_empty());
+
+        schedule.semester = None;
+        schedule.weeks[1][1] = Some(date(11, 11));
+        assert_eq!(
+            schedule.holiday_conflicts(),
+            vec![(NonZero::new(2).unwrap(), Weekday::Tue, date(11, 11), "Narodowe Święto Niepodległości")]
+        );
+    }
+
+    #[test]
+    fn semester_schedule() {
+        let mut value = json!({
+            "semester": { "start": "2025-11-03", "weeks": 2 },
+            "subjects": { "aisd": { "name": "AiSD" } },
+            "teachers": {},
+            "schedule": [
+                { "subject": "aisd", "type": "lab", "day": "tuesday", "time": "8:15-10:00" }
+            ]
+        });
+
+        // The weeks are generated without going through Schedule::load
+        let schedule: Schedule = serde_json::from_value(value.clone()).unwrap();
+        assert_eq!(schedule.weeks.len(), 2);
+        let calendar = schedule.to_ical(&Tz::Europe__Warsaw, &ICalOptions::default()).unwrap();
+        assert_eq!(calendar.events.iter().filter(|e| e.summary.contains("AiSD")).count(), 2);
+
+        value["weeks"] = json!([]);
+        assert!(serde_json::from_value::<Schedule>(value.clone()).is_err());
+        value.as_object_mut().unwrap().remove("semester");
+        assert!(serde_json::from_value::<Schedule>(value.clone()).is_ok());
+        value.as_object_mut().unwrap().remove("weeks");
+        assert!(serde_json::from_value::<Schedule>(value).is_err());
+    }
+
+    #[test]
+    fn custom_days_markers() {
+        let schedule: Schedule = serde_json::from_value(json!({
+            "days": ["friday", "saturday", "sunday"],
+            "weeks": [["2025-10-24", "2025-10-25", "2025-10-26"]],
+            "dayMarkers": { "title": "W{week}D{day} {weekday}" },
+            "subjects": {},
+            "teachers": {},
+            "schedule": []
+        }))
+        .unwrap();
+
+        assert_eq!(schedule.day_number(Weekday::Sun), Some(3));
+        assert_eq!(schedule.day_number(Weekday::Mon), None);
+
+        // Day numbers match the W<week>D<day> date syntax
+        let calendar = schedule.to_ical(&Tz::Europe__Warsaw, &ICalOptions::default()).unwrap();
+        let titles: Vec<_> = calendar.events.iter().map(|e| e.summary.as_str()).collect();
+        assert_eq!(titles, vec!["W1D1 Fri", "W1D2 Sat", "W1D3 Sun"]);
+    }
+
+    #[test]
+    fn recurring_timezone_transitions() {
+        let schedule: Schedule = serde_json::from_value(json!({
+            "semester": { "start": "2025-10-06", "weeks": 10 },
+            "subjects": { "aisd": { "name": "AiSD" } },
+            "teachers": {},
+            "schedule": [
+                { "subject": "aisd", "type": "lab", "day": "monday", "time": "10:15-12:00" }
+            ]
+        }))
+        .unwrap();
+
+        let options = ICalOptions { recurring: true, ..ICalOptions::default() };
+        let calendar = schedule.to_ical(&Tz::Europe__Warsaw, &options).unwrap();
+        let mut buffer = Vec::new();
+        calendar.serialize_to_ical(&mut buffer).unwrap();
+        let ical = String::from_utf8(buffer).unwrap();
+
+        // The series starts in summer time, but most of its occurrences are after the change on 26 October
+        assert!(ical.contains("RRULE:FREQ=WEEKLY;UNTIL=20251208T091500Z\r\n"));
+        assert!(ical.contains("BEGIN:DAYLIGHT\r\n"));
+        assert!(ical.contains("BEGIN:STANDARD\r\nDTSTART:20251026T030000\r\n"));
+    }
+
+    #[test]
+    fn exceptions_to_ical() {
+        let schedule: Schedule = serde_json::from_value(json!({
+            "weeks": [
+                ["2025-11-03", "2025-11-04", "2025-11-05", "2025-11-06", "2025-11-07"],
+                ["2025-11-10", "2025-11-11", "2025-11-12", "2025-11-13", "2025-11-14"],
+                ["2025-11-17", "2025-11-18", "2025-11-19", "2025-11-20", "2025-11-21"]
+            ],
+            "subjects": { "aisd": { "name": "AiSD" } },
+            "teachers": {},
+            "schedule": [
+                { "id": "aisd-lab", "subject": "aisd", "type": "lab", "day": "wednesday", "time": "10:15-12:00" }
+            ],
+            "exceptions": [
+                { "class": "aisd-lab", "week": 1, "cancel": true },
+                { "class": "aisd-lab", "week": 2, "newDate": "2025-11-13", "time": "14:00-15:45",
+                  "location": { "building": "C3", "room": "105" } }
+            ]
+        }))
+        .unwrap();
+
+        let tz = Tz::Europe__Warsaw;
+        let at = |day, hour, minute| {
+            NaiveDate::from_ymd_opt(2025, 11, day)
+                .unwrap()
+                .and_hms_opt(hour, minute, 0)
+                .unwrap()
+                .and_local_timezone(tz)
+                .unwrap()
+        };
+        let class = &schedule.schedule[0];
+
+        let calendar = schedule.to_ical(&tz, &ICalOptions::default()).unwrap();
+        let events: Vec<_> = calendar.events.iter().filter(|e| e.summary.contains("AiSD")).collect();
+        assert_eq!(events.len(), 3);
+        let moved = events.iter().find(|e| e.time == EventTime::Timed { start: at(13, 14, 0), end: at(13, 15, 45) });
+        assert_eq!(moved.unwrap().uid, schedule.occurrence_uid(class, NaiveDate::from_ymd_opt(2025, 11, 12).unwrap()));
+        assert_eq!(moved.unwrap().location.as_deref(), Some("105/C3"));
+        let cancelled = events.iter().find(|e| e.status == Some(EventStatus::Cancelled)).unwrap();
+        assert_eq!(cancelled.time, EventTime::Timed { start: at(5, 10, 15), end: at(5, 12, 0) });
+
+        let options = ICalOptions { recurring: true, ..ICalOptions::default() };
+        let calendar = schedule.to_ical(&tz, &options).unwrap();
+        let events: Vec<_> = calendar.events.iter().filter(|e| e.summary.contains("AiSD")).collect();
+        assert_eq!(events.len(), 3);
+        assert!(events.iter().all(|e| e.uid == schedule.series_uid(class)));
+        assert_eq!(events[0].recurrence_id, None);
+        assert_eq!(events[1].recurrence_id, Some(at(5, 10, 15)));
+        assert_eq!(events[1].status, Some(EventStatus::Cancelled));
+        assert_eq!(events[2].recurrence_id, Some(at(12, 10, 15)));
+        assert_eq!(events[2].time, EventTime::Timed { start: at(13, 14, 0), end: at(13, 15, 45) });
+
+        let mut buffer = Vec::new();
+        (&calendar as &dyn SerializeToICal).serialize_to_ical(&mut buffer).unwrap();
+        assert!(String::from_utf8(buffer).unwrap().contains("RECURRENCE-ID;TZID=Europe/Warsaw:20251112T101500"));
+    }
 }
@@ -0,0 +1,225 @@
+//! Generation of the `weeks` table from the rules of a semester,
+//! defined in the `semester` property of the schedule.
+
+use crate::calendar::class::WeekdayDef;
+use crate::calendar::holidays::HolidaySettings;
+use crate::calendar::schedule::{deserialize_date, deserialize_optional_date, Week};
+use chrono::{Datelike, NaiveDate, TimeDelta, Weekday};
+use eyre::eyre;
+use serde::{Deserialize, Deserializer};
+
+/// A period without classes, given as a single date or a `from`-`to` range (inclusive).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Break {
+    pub from: NaiveDate,
+    pub to: NaiveDate,
+    pub name: Option<String>,
+}
+
+impl Break {
+    pub fn contains(&self, date: NaiveDate) -> bool {
+        self.from <= date && date <= self.to
+    }
+}
+
+impl<'de> Deserialize<'de> for Break {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum RawBreak {
+            Date(#[serde(deserialize_with = "deserialize_date")] NaiveDate),
+            Range {
+                #[serde(deserialize_with = "deserialize_date")]
+                from: NaiveDate,
+                #[serde(default, deserialize_with = "deserialize_optional_date")]
+                to: Option<NaiveDate>,
+                name: Option<String>,
+            },
+        }
+
+        Ok(match RawBreak::deserialize(deserializer)? {
+            RawBreak::Date(date) => Break {
+                from: date,
+                to: date,
+                name: None,
+            },
+            RawBreak::Range { from, to, name } => Break {
+                from,
+                to: to.unwrap_or(from),
+                name,
+            },
+        })
+    }
+}
+
+/// A date on which the classes of another weekday take place.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct DaySwap {
+    #[serde(deserialize_with = "deserialize_date")]
+    pub date: NaiveDate,
+    /// The weekday whose classes take place on the date.
+    #[serde(with = "WeekdayDef")]
+    pub day: Weekday,
+}
+
+/// Rules of a semester, from which the `weeks` table is generated.
+#[derive(Debug, Deserialize)]
+pub struct SemesterRules {
+    /// The first day of the semester.
+    #[serde(deserialize_with = "deserialize_date")]
+    pub start: NaiveDate,
+    /// Number of weeks with classes. Weeks entirely within breaks aren't counted.
+    pub weeks: usize,
+    #[serde(default)]
+    pub breaks: Vec<Break>,
+    #[serde(default)]
+    pub swaps: Vec<DaySwap>,
+}
+
+impl SemesterRules {
+    /// Weeks without classes after which the generation gives up, e.g. because of a break spanning years.
+    const MAX_EMPTY_WEEKS: usize = 26;
+
+    /// Returns the break the date falls in, if any.
+    pub fn break_on(&self, date: NaiveDate) -> Option<&Break> {
+        self.breaks.iter().find(|b| b.contains(date))
+    }
+
+    /// Returns the weekday whose classes take place on the date.
+    pub fn scheduled_weekday(&self, date: NaiveDate) -> Weekday {
+        self.swaps
+            .iter()
+            .find(|swap| swap.date == date)
+            .map(|swap| swap.day)
+            .unwrap_or(date.weekday())
+    }
+
+    /// Generates the `weeks` table with a column for each of the `days`.
+    ///
+    /// Weeks start on Monday. Every date since the start of the semester which isn't in a break or a holiday
+    /// is put in the column of its weekday, or of the weekday it's swapped to, in the same week.
+    pub fn generate_weeks(&self, days: &[Weekday], holidays: &HolidaySettings) -> eyre::Result<Vec<Week>> {
+        for swap in &self.swaps {
+            if !days.contains(&swap.day) {
+                return Err(eyre!("{} is swapped to {}, which isn't one of the days", swap.date, swap.day));
+            }
+            if swap.date < self.start {
+                return Err(eyre!("{} is swapped, but it's before the start of the semester", swap.date));
+            }
+        }
+
+        let mut weeks = Vec::with_capacity(self.weeks);
+        let mut monday = self.start.week(Weekday::Mon).first_day();
+        let mut empty_weeks = 0;
+
+        while weeks.len() < self.weeks {
+            let mut week: Week = vec![None; days.len()];
+
+            for date in monday.iter_days().take(7) {
+                if date < self.start || self.break_on(date).is_some() || holidays.holiday_on(date).is_some() {
+                    continue;
+                }
+
+                let weekday = self.scheduled_weekday(date);
+                let Some(column) = days.iter().position(|day| *day == weekday) else {
+                    continue;
+                };
+
+                if let Some(other) = week[column] {
+                    return Err(eyre!(
+                        "both {} and {} take place on {}'s schedule, add one of them to the breaks",
+                        other,
+                        date,
+                        weekday
+                    ));
+                }
+                week[column] = Some(date);
+            }
+
+            if week.iter().any(Option::is_some) {
+                weeks.push(week);
+                empty_weeks = 0;
+            } else {
+                empty_weeks += 1;
+                if empty_weeks > Self::MAX_EMPTY_WEEKS {
+                    return Err(eyre!("no classes for {} weeks since {}", empty_weeks, monday));
+                }
+            }
+
+            monday += TimeDelta::weeks(1);
+        }
+
+        Ok(weeks)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn date(month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(2025, month, day).unwrap()
+    }
+
+    const WORKDAYS: [Weekday; 5] = [Weekday::Mon, Weekday::Tue, Weekday::Wed, Weekday::Thu, Weekday::Fri];
+
+    #[test]
+    fn generate_weeks() {
+        let rules: SemesterRules = serde_json::from_value(json!({
+            "start": "2025-10-01",
+            "weeks": 4,
+            "breaks": [
+                "2025-11-10",
+                { "from": "2025-10-13", "to": "2025-10-19", "name": "Rektorskie" },
+                { "from": "2025-11-11", "name": "Święto Niepodległości" }
+            ],
+            "swaps": [{ "date": "2025-11-12", "day": "monday" }]
+        }))
+        .unwrap();
+
+        let weeks = rules.generate_weeks(&WORKDAYS, &HolidaySettings::default()).unwrap();
+        assert_eq!(
+            weeks,
+            vec![
+                vec![None, None, Some(date(10, 1)), Some(date(10, 2)), Some(date(10, 3))],
+                vec![Some(date(10, 6)), Some(date(10, 7)), Some(date(10, 8)), Some(date(10, 9)), Some(date(10, 10))],
+                vec![Some(date(10, 20)), Some(date(10, 21)), Some(date(10, 22)), Some(date(10, 23)), Some(date(10, 24))],
+                vec![Some(date(10, 27)), Some(date(10, 28)), Some(date(10, 29)), Some(date(10, 30)), Some(date(10, 31))],
+            ]
+        );
+
+        let rules = SemesterRules { weeks: 6, ..rules };
+        let weeks = rules.generate_weeks(&WORKDAYS, &HolidaySettings::default()).unwrap();
+        assert_eq!(weeks[5], vec![Some(date(11, 12)), None, None, Some(date(11, 13)), Some(date(11, 14))]);
+        assert_eq!(rules.break_on(date(11, 11)).and_then(|b| b.name.as_deref()), Some("Święto Niepodległości"));
+    }
+
+    #[test]
+    fn invalid_rules() {
+        let conflicting = SemesterRules {
+            start: date(10, 1),
+            weeks: 5,
+            breaks: vec![],
+            swaps: vec![DaySwap { date: date(10, 8), day: Weekday::Mon }],
+        };
+        assert!(conflicting.generate_weeks(&WORKDAYS, &HolidaySettings::default()).is_err());
+
+        let weekend = SemesterRules {
+            swaps: vec![DaySwap { date: date(10, 8), day: Weekday::Sat }],
+            ..conflicting
+        };
+        assert!(weekend.generate_weeks(&WORKDAYS, &HolidaySettings::default()).is_err());
+
+        let endless = SemesterRules {
+            start: date(10, 1),
+            weeks: 5,
+            breaks: vec![Break { from: date(10, 6), to: date(12, 31) + TimeDelta::days(365), name: None }],
+            swaps: vec![],
+        };
+        assert!(endless.generate_weeks(&WORKDAYS, &HolidaySettings::default()).is_err());
+    }
+}
@@ -0,0 +1,227 @@
+use crate::calendar::template::Template;
+use crate::ical::contentline::ContentLine;
+use crate::time::timeext::TimeDeltaExt;
+use crate::time::timezones::get_timezone_by_name;
+use chrono::{Datelike, NaiveDate, TimeDelta, Weekday};
+use chrono_tz::Tz;
+use eyre::eyre;
+use serde::Deserialize;
+
+/// Settings of the generated calendar, defined in the `calendar` property of the schedule.
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CalendarSettings {
+    /// Identifier of the schedule, used to derive event UIDs.
+    ///
+    /// If omitted, the first date of the schedule is used instead.
+    pub id: Option<String>,
+    /// Domain appended to event UIDs after `@`.
+    pub uid_domain: Option<String>,
+    /// Display name of the calendar.
+    pub name: Option<String>,
+    /// Description of the calendar.
+    pub description: Option<String>,
+    /// Color of the whole calendar, as a `#RRGGBB` hex code.
+    pub color: Option<String>,
+    /// IANA name of the time zone the classes take place in, e.g. `Europe/Warsaw`.
+    ///
+    /// If omitted, the local time zone is used.
+    pub timezone: Option<String>,
+    /// Suggested interval between refreshes of subscribed calendars, in minutes.
+    pub refresh_interval: Option<u32>,
+    /// URL the calendar can be refreshed from.
+    pub source: Option<String>,
+    /// Identifier of the program that generated the calendar, written as `PRODID`.
+    pub prod_id: Option<String>,
+}
+
+impl CalendarSettings {
+    pub const DEFAULT_UID_DOMAIN: &'static str = "planner.thechilli.dev";
+    pub const DEFAULT_PROD_ID: &'static str = "-//TheChilliPL//Planner//PL";
+
+    pub fn get_uid_domain(&self) -> &str {
+        self.uid_domain.as_deref().unwrap_or(Self::DEFAULT_UID_DOMAIN)
+    }
+
+    pub fn get_prod_id(&self) -> &str {
+        self.prod_id.as_deref().unwrap_or(Self::DEFAULT_PROD_ID)
+    }
+
+    /// Returns the configured time zone, if any.
+    pub fn get_timezone(&self) -> eyre::Result<Option<Tz>> {
+        self.timezone
+            .as_deref()
+            .map(|name| get_timezone_by_name(name).ok_or_else(|| eyre!("unknown time zone: {}", name)))
+            .transpose()
+    }
+
+    /// Returns the calendar-level properties describing the calendar whose events are in `tz`.
+    ///
+    /// Both the [RFC 7986](https://www.rfc-editor.org/rfc/rfc7986) properties
+    /// and the non-standard `X-WR-*` ones are written, as many clients only support the latter.
+    pub fn to_ical_properties(&self, tz: &Tz) -> Vec<ContentLine> {
+        let mut properties = Vec::new();
+
+        if let Some(name) = &self.name {
+            properties.push(ContentLine::text("NAME", name));
+            properties.push(ContentLine::text("X-WR-CALNAME", name));
+        }
+        if let Some(description) = &self.description {
+            properties.push(ContentLine::text("DESCRIPTION", description));
+            properties.push(ContentLine::text("X-WR-CALDESC", description));
+        }
+        properties.push(ContentLine::text("X-WR-TIMEZONE", tz.name()));
+        if let Some(minutes) = self.refresh_interval {
+            let duration = TimeDelta::minutes(minutes as i64).to_ical_duration();
+            properties.push(ContentLine::new("REFRESH-INTERVAL", &duration).param("VALUE", "DURATION"));
+            properties.push(ContentLine::new("X-PUBLISHED-TTL", duration));
+        }
+        if let Some(source) = &self.source {
+            properties.push(ContentLine::new("SOURCE", source).param("VALUE", "URI"));
+        }
+        if let Some(color) = &self.color {
+            properties.push(ContentLine::new("X-APPLE-CALENDAR-COLOR", color));
+        }
+
+        properties
+    }
+}
+
+/// Settings of the all-day events marking every day of the schedule,
+/// defined in the `dayMarkers` property of the schedule.
+#[derive(Debug, Deserialize)]
+#[serde(default, rename_all = "camelCase")]
+pub struct DayMarkerSettings {
+    pub enabled: bool,
+    /// Template of the marker's title.
+    ///
+    /// Supports the `{weekday}` (scheduled weekday), `{realWeekday}`, `{week}`, `{day}` (number of the day
+    /// in the week, i.e. of its column of the `weeks` table, as in the `W<week>D<day>` date syntax)
+    /// and `{date}` placeholders.
+    pub title: Template,
+    /// Whether the markers should be shown as free time (`TRANSP:TRANSPARENT`).
+    pub transparent: bool,
+    /// Whether to only generate markers for days on which the schedule of another weekday applies.
+    pub only_mismatched: bool,
+    /// Whether to uppercase the title of markers for days on which the schedule of another weekday applies.
+    pub uppercase_mismatched: bool,
+}
+
+impl Default for DayMarkerSettings {
+    fn default() -> Self {
+        DayMarkerSettings {
+            enabled: true,
+            title: Template::parse(Self::DEFAULT_TITLE).unwrap(),
+            transparent: false,
+            only_mismatched: false,
+            uppercase_mismatched: true,
+        }
+    }
+}
+
+impl DayMarkerSettings {
+    pub const DEFAULT_TITLE: &'static str = "{weekday} of week {week}";
+    pub const PLACEHOLDERS: &'static [&'static str] = &["weekday", "realWeekday", "week", "day", "date"];
+
+    /// Ensures the title template only uses the supported placeholders.
+    pub fn check(&self) -> eyre::Result<()> {
+        self.title.check(Self::PLACEHOLDERS)
+    }
+
+    /// Returns the title of the marker of the given day, or `None` if it shouldn't be generated.
+    ///
+    /// `day` is the number of the day in the week,
+    /// see [`Schedule::day_number`](crate::calendar::schedule::Schedule::day_number).
+    pub fn title_for(&self, week: usize, day: usize, weekday: Weekday, date: NaiveDate) -> Option<String> {
+        let mismatched = weekday != date.weekday();
+
+        if !self.enabled || self.only_mismatched && !mismatched {
+            return None;
+        }
+
+        let mut title = self.title.render(|placeholder| match placeholder {
+            "weekday" => Some(weekday.to_string()),
+            "realWeekday" => Some(date.weekday().to_string()),
+            "week" => Some(week.to_string()),
+            "day" => Some(day.to_string()),
+            "date" => Some(date.to_string()),
+            _ => None,
+        });
+
+        if mismatched && self.uppercase_mismatched {
+            title = title.to_uppercase();
+        }
+
+        Some(title)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn calendar_properties() {
+        let settings: CalendarSettings = serde_json::from_value(json!({
+            "name": "Plan zajęć, semestr 5",
+            "timezone": "Europe/Warsaw",
+            "refreshInterval": 720,
+            "source": "https://example.com/plan.ics",
+        }))
+        .unwrap();
+
+        let tz = settings.get_timezone().unwrap().unwrap();
+        let lines: Vec<_> = settings
+            .to_ical_properties(&tz)
+            .iter()
+            .map(ContentLine::to_unfolded)
+            .collect();
+
+        assert_eq!(
+            lines,
+            vec![
+                "NAME:Plan zajęć\\, semestr 5",
+                "X-WR-CALNAME:Plan zajęć\\, semestr 5",
+                "X-WR-TIMEZONE:Europe/Warsaw",
+                "REFRESH-INTERVAL;VALUE=DURATION:PT12H",
+                "X-PUBLISHED-TTL:PT12H",
+                "SOURCE;VALUE=URI:https://example.com/plan.ics",
+            ]
+        );
+        assert_eq!(settings.get_prod_id(), CalendarSettings::DEFAULT_PROD_ID);
+
+        let invalid: CalendarSettings =
+            serde_json::from_value(json!({ "timezone": "Europe/Nowhere" })).unwrap();
+        assert!(invalid.get_timezone().is_err());
+    }
+
+    #[test]
+    fn day_marker_titles() {
+        let monday = NaiveDate::from_ymd_opt(2025, 10, 20).unwrap();
+        let wednesday = NaiveDate::from_ymd_opt(2025, 10, 22).unwrap();
+
+        let default = DayMarkerSettings::default();
+        assert_eq!(default.title_for(3, 1, Weekday::Mon, monday).as_deref(), Some("Mon of week 3"));
+        assert_eq!(default.title_for(3, 5, Weekday::Fri, wednesday).as_deref(), Some("FRI OF WEEK 3"));
+
+        let custom: DayMarkerSettings = serde_json::from_value(json!({
+            "title": "W{week}D{day} ({realWeekday} {date} as {weekday})",
+            "onlyMismatched": true,
+            "uppercaseMismatched": false
+        }))
+        .unwrap();
+        assert_eq!(custom.title_for(3, 1, Weekday::Mon, monday), None);
+        assert_eq!(
+            custom.title_for(3, 5, Weekday::Fri, wednesday).as_deref(),
+            Some("W3D5 (Wed 2025-10-22 as Fri)")
+        );
+
+        assert!(custom.check().is_ok());
+        let unknown: DayMarkerSettings = serde_json::from_value(json!({ "title": "{weekday} {month}" })).unwrap();
+        assert!(unknown.check().is_err());
+
+        let disabled: DayMarkerSettings = serde_json::from_value(json!({ "enabled": false })).unwrap();
+        assert_eq!(disabled.title_for(3, 1, Weekday::Mon, monday), None);
+    }
+}
@@ -0,0 +1,303 @@
+//! Templates of event texts, with `{placeholder}` syntax.
+//!
+//! Literal braces are written as `{{` and `}}`.
+//! Placeholders without a value (e.g. `{room}` of an online class) are replaced with nothing.
+
+use crate::calendar::class::{Class, Location};
+use crate::calendar::schedule::{Schedule, Subject, Teacher};
+use eyre::eyre;
+use serde::{de, Deserialize, Deserializer};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Literal(String),
+    Placeholder(String),
+}
+
+/// A parsed text template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    segments: Vec<Segment>,
+}
+
+impl Template {
+    pub fn parse(text: &str) -> eyre::Result<Self> {
+        let mut segments = Vec::new();
+        let mut literal = String::new();
+        let mut chars = text.chars().peekable();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' if chars.peek() == Some(&'{') => {
+                    chars.next();
+                    literal.push('{');
+                }
+                '}' if chars.peek() == Some(&'}') => {
+                    chars.next();
+                    literal.push('}');
+                }
+                '{' => {
+                    let mut name = String::new();
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some(c) if c.is_ascii_alphanumeric() => name.push(c),
+                            _ => return Err(eyre!("unterminated placeholder in template: {}", text)),
+                        }
+                    }
+                    if !literal.is_empty() {
+                        segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                    }
+                    segments.push(Segment::Placeholder(name));
+                }
+                '}' => return Err(eyre!("unmatched '}}' in template: {}", text)),
+                _ => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            segments.push(Segment::Literal(literal));
+        }
+
+        Ok(Template { segments })
+    }
+
+    /// Ensures the template only uses the given placeholders.
+    pub fn check(&self, known: &[&str]) -> eyre::Result<()> {
+        for segment in &self.segments {
+            if let Segment::Placeholder(name) = segment {
+                if !known.contains(&name.as_str()) {
+                    return Err(eyre!(
+                        "unknown placeholder {{{}}}, expected one of: {}",
+                        name,
+                        known.join(", ")
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Fills the template, taking the values of placeholders from `value`.
+    pub fn render(&self, value: impl Fn(&str) -> Option<String>) -> String {
+        self.segments
+            .iter()
+            .map(|segment| match segment {
+                Segment::Literal(text) => text.clone(),
+                Segment::Placeholder(name) => value(name).unwrap_or_default(),
+            })
+            .collect()
+    }
+}
+
+impl<'de> Deserialize<'de> for Template {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        let text = String::deserialize(deserializer)?;
+        Template::parse(&text).map_err(de::Error::custom)
+    }
+}
+
+/// Placeholders available in templates of class events.
+pub const CLASS_PLACEHOLDERS: &[&str] = &[
+    "id",
+    "emoji",
+    "type",
+    "typeId",
+    "subject",
+    "short",
+    "subjectId",
+    "subjectUrl",
+    "notesUrl",
+    "day",
+    "time",
+    "start",
+    "end",
+    "location",
+    "room",
+    "building",
+    "teachers",
+    "teacherList",
+    "emails",
+    "links",
+];
+
+/// Templates of the texts of class events, defined in the `templates` property of the schedule.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TemplateSettings {
+    pub summary: Template,
+    pub description: Template,
+    pub location: Template,
+}
+
+impl TemplateSettings {
+    pub const DEFAULT_SUMMARY: &'static str = "{emoji} {short}";
+    pub const DEFAULT_DESCRIPTION: &'static str = "{type}: {subject}\n{teachers}\n\n{links}";
+    pub const DEFAULT_LOCATION: &'static str = "{location}";
+
+    /// Ensures the templates only use placeholders from [`CLASS_PLACEHOLDERS`].
+    pub fn check(&self) -> eyre::Result<()> {
+        self.summary.check(CLASS_PLACEHOLDERS)?;
+        self.description.check(CLASS_PLACEHOLDERS)?;
+        self.location.check(CLASS_PLACEHOLDERS)
+    }
+}
+
+impl Default for TemplateSettings {
+    fn default() -> Self {
+        TemplateSettings {
+            summary: Template::parse(Self::DEFAULT_SUMMARY).unwrap(),
+            description: Template::parse(Self::DEFAULT_DESCRIPTION).unwrap(),
+            location: Template::parse(Self::DEFAULT_LOCATION).unwrap(),
+        }
+    }
+}
+
+/// A class with its subject and teachers, providing the values of [`CLASS_PLACEHOLDERS`].
+pub struct ClassContext<'a> {
+    pub class: &'a Class,
+    pub subject: &'a Subject,
+    pub teachers: Vec<&'a Teacher>,
+}
+
+impl ClassContext<'_> {
+    pub fn value(&self, placeholder: &str) -> Option<String> {
+        let class = self.class;
+        let subject = self.subject;
+        let offline = match &class.location {
+            Some(Location::Offline { building, room }) => Some((building, room)),
+            _ => None,
+        };
+        let join = |parts: Vec<String>, separator| Some(parts.join(separator)).filter(|s| !s.is_empty());
+
+        match placeholder {
+            "id" => class.id.clone(),
+            "emoji" => Some(class.class_type.to_emoji().to_string()),
+            "type" => Some(class.class_type.to_name().to_string()),
+            "typeId" => Some(class.class_type.to_id().to_string()),
+            "subject" => Some(subject.name.clone()),
+            "short" => Some(subject.get_short_or_name().to_string()),
+            "subjectId" => Some(class.subject.clone()),
+            "subjectUrl" => subject.subject_url.clone(),
+            "notesUrl" => subject.notes_url.clone(),
+            "day" => Some(class.day.to_string()),
+            "time" => Some(class.time.to_string()),
+            "start" => Some(class.time.start.format("%H:%M").to_string()),
+            "end" => Some(class.time.end.format("%H:%M").to_string()),
+            "location" => class.location.as_ref().map(Location::to_string),
+            "room" => offline.map(|(_, room)| room.clone()),
+            "building" => offline.map(|(building, _)| building.clone()),
+            "teachers" => join(self.teachers.iter().map(|t| t.name.clone()).collect(), "\n"),
+            "teacherList" => join(self.teachers.iter().map(|t| t.name.clone()).collect(), ", "),
+            "emails" => join(self.teachers.iter().filter_map(|t| t.email.clone()).collect(), ", "),
+            "links" => {
+                let notes = subject.notes_url.iter().map(|url| format!("Notatki: {}", url));
+                let profiles = self
+                    .teachers
+                    .iter()
+                    .filter_map(|t| Some(format!("{}: {}", t.name, t.profile_url.as_ref()?)));
+                join(notes.chain(profiles).collect(), "\n")
+            }
+            _ => None,
+        }
+    }
+
+    /// Fills a template with the values of this class.
+    ///
+    /// Consecutive blank lines and trailing whitespace, left by placeholders without values, are removed.
+    pub fn render(&self, template: &Template) -> String {
+        let text = template.render(|placeholder| self.value(placeholder));
+        let mut lines: Vec<&str> = Vec::new();
+        for line in text.trim_end().lines() {
+            if line.trim().is_empty() && lines.last().is_some_and(|last| last.trim().is_empty()) {
+                continue;
+            }
+            lines.push(line);
+        }
+
+        lines.join("\n")
+    }
+}
+
+impl Schedule {
+    /// Returns the context of a class, failing if its subject or teachers aren't defined.
+    pub fn class_context<'a>(&'a self, class: &'a Class) -> eyre::Result<ClassContext<'a>> {
+        let subject = self
+            .subjects
+            .get(&class.subject)
+            .ok_or_else(|| eyre!("subject not found: {}", class.subject))?;
+        let teachers = class
+            .teachers
+            .iter()
+            .flatten()
+            .map(|id| {
+                self.teachers
+                    .get(id)
+                    .ok_or_else(|| eyre!("teacher not found: {}", id))
+            })
+            .collect::<eyre::Result<_>>()?;
+
+        Ok(ClassContext {
+            class,
+            subject,
+            teachers,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn parse_templates() {
+        let template = Template::parse("{{{a}}} {b}").unwrap();
+        assert_eq!(template.render(|name| Some(name.to_uppercase())), "{A} B");
+
+        assert!(Template::parse("{a").is_err());
+        assert!(Template::parse("a}").is_err());
+        assert!(Template::parse("{a b}").is_err());
+        assert!(template.check(&["a"]).is_err());
+        assert!(template.check(&["a", "b"]).is_ok());
+    }
+
+    #[test]
+    fn render_class() {
+        let schedule: Schedule = serde_json::from_value(json!({
+            "weeks": [],
+            "subjects": { "aisd": { "name": "Algorytmy", "short": "AiSD", "notesUrl": "https://n.example.com" } },
+            "teachers": {
+                "kowalski": { "name": "dr Kowalski", "email": "k@example.com", "profileUrl": "https://k.example.com" },
+                "nowak": { "name": "mgr Nowak" }
+            },
+            "schedule": [
+                { "subject": "aisd", "type": "lab", "day": "monday", "time": "8:15-10:00",
+                  "location": { "building": "C3", "room": "204" }, "teachers": ["kowalski", "nowak"] },
+                { "subject": "aisd", "type": "lecture", "day": "friday", "time": "12:15-14:00", "location": "online" }
+            ]
+        }))
+        .unwrap();
+        let templates = TemplateSettings::default();
+
+        let lab = schedule.class_context(&schedule.schedule[0]).unwrap();
+        assert_eq!(lab.render(&templates.summary), "🧪 AiSD");
+        assert_eq!(
+            lab.render(&templates.description),
+            "Laboratorium: Algorytmy\ndr Kowalski\nmgr Nowak\n\nNotatki: https://n.example.com\ndr Kowalski: https://k.example.com"
+        );
+        assert_eq!(lab.render(&templates.location), "204/C3");
+
+        let custom = Template::parse("{room} {building} | {start}-{end} {day} | {teacherList} <{emails}>").unwrap();
+        assert_eq!(lab.render(&custom), "204 C3 | 08:15-10:00 Mon | dr Kowalski, mgr Nowak <k@example.com>");
+
+        let lecture = schedule.class_context(&schedule.schedule[1]).unwrap();
+        assert_eq!(lecture.render(&templates.location), "Online");
+        assert_eq!(lecture.render(&templates.description), "Wykład: Algorytmy\n\nNotatki: https://n.example.com");
+        assert_eq!(lecture.render(&custom), "  | 12:15-14:00 Fri |  <>");
+    }
+}
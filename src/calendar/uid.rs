@@ -0,0 +1,258 @@
+//! Deterministic generation of event UIDs.
+//!
+//! UIDs are derived from the identity of the schedule, the identity of the class
+//! and the date of the occurrence, so regenerating the calendar after editing the time,
+//! location or teachers of a class keeps the UIDs (and thus updates the events on re-import).
+
+use crate::calendar::class::Class;
+use crate::calendar::events::Event;
+use crate::calendar::schedule::Schedule;
+use chrono::NaiveDate;
+use eyre::eyre;
+use std::collections::HashSet;
+
+/// Computes a 64-bit FNV-1a hash of the parts, separated with a unit separator.
+///
+/// Unlike [`std::hash::DefaultHasher`], the result is guaranteed to stay the same
+/// across Rust versions and platforms.
+pub fn stable_hash(parts: &[&str]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf29ce484222325;
+    const PRIME: u64 = 0x100000001b3;
+
+    let mut hash = OFFSET_BASIS;
+
+    for (i, part) in parts.iter().enumerate() {
+        if i > 0 {
+            hash ^= 0x1f;
+            hash = hash.wrapping_mul(PRIME);
+        }
+
+        for byte in part.bytes() {
+            hash ^= byte as u64;
+            hash = hash.wrapping_mul(PRIME);
+        }
+    }
+
+    hash
+}
+
+impl Schedule {
+    /// Returns the identity of the schedule, i.e. its explicit ID or its first date.
+    fn schedule_identity(&self) -> String {
+        match &self.calendar.id {
+            Some(id) => id.clone(),
+            None => self
+                .weeks
+                .first()
+                .and_then(|week| week.iter().flatten().next())
+                .map(|date| date.to_string())
+                .unwrap_or_default(),
+        }
+    }
+
+    /// Returns the identity of the class.
+    ///
+    /// For classes without an explicit `id`, it's derived from the subject, type and day of the class,
+    /// and its position among other classes with the same subject, type and day.
+    /// This way, changing the time or location of a class doesn't change its identity.
+    pub fn class_identity(&self, class: &Class) -> String {
+        if let Some(id) = &class.id {
+            return format!("id:{}", id);
+        }
+
+        let ordinal = self
+            .schedule
+            .iter()
+            .filter(|other| {
+                other.id.is_none()
+                    && other.subject == class.subject
+                    && other.class_type == class.class_type
+                    && other.day == class.day
+            })
+            .position(|other| std::ptr::eq(other, class))
+            .unwrap_or_default();
+
+        format!(
+            "{}/{}/{}/{}",
+            class.subject,
+            class.class_type.to_id(),
+            class.day,
+            ordinal
+        )
+    }
+
+    /// Ensures explicit class IDs are unique.
+    pub fn check_class_ids(&self) -> eyre::Result<()> {
+        let mut ids = HashSet::new();
+
+        for id in self.schedule.iter().filter_map(|class| class.id.as_ref()) {
+            if !ids.insert(id) {
+                return Err(eyre!("duplicate class id: {}", id));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn make_uid(&self, parts: &[&str]) -> String {
+        let schedule_identity = self.schedule_identity();
+        let mut all_parts = vec![schedule_identity.as_str()];
+        all_parts.extend_from_slice(parts);
+
+        format!(
+            "{:016x}@{}",
+            stable_hash(&all_parts),
+            self.calendar.get_uid_domain()
+        )
+    }
+
+    /// Returns the UID of a single occurrence of the class.
+    pub fn occurrence_uid(&self, class: &Class, date: NaiveDate) -> String {
+        self.make_uid(&[&self.class_identity(class), &date.to_string()])
+    }
+
+    /// Returns the UID of the recurring event covering all occurrences of the class.
+    pub fn series_uid(&self, class: &Class) -> String {
+        self.make_uid(&[&self.class_identity(class)])
+    }
+
+    /// Returns the identity of an event other than a class.
+    ///
+    /// For events without an explicit `id`, it's derived from the kind of the event, its subject and teacher,
+    /// and its position among other events with the same ones, so that editing the event's title,
+    /// date or time keeps its UID.
+    pub fn event_identity(&self, event: &Event) -> String {
+        if let Some(id) = &event.id {
+            return format!("event-id:{}", id);
+        }
+
+        let same = |other: &Event| {
+            other.id.is_none()
+                && other.kind == event.kind
+                && other.subject == event.subject
+                && other.teacher == event.teacher
+        };
+        let ordinal = self
+            .events
+            .iter()
+            .filter(|other| same(other))
+            .position(|other| std::ptr::eq(other, event))
+            .unwrap_or_default();
+
+        format!(
+            "event/{}/{}/{}/{}",
+            event.kind.to_id(),
+            event.subject.as_deref().unwrap_or_default(),
+            event.teacher.as_deref().unwrap_or_default(),
+            ordinal
+        )
+    }
+
+    /// Returns the UID of an event other than a class on the date.
+    pub fn event_uid(&self, event: &Event, date: NaiveDate) -> String {
+        self.make_uid(&[&self.event_identity(event), &date.to_string()])
+    }
+
+    /// Returns the UID of the recurring event covering all dates of an event other than a class.
+    pub fn event_series_uid(&self, event: &Event) -> String {
+        self.make_uid(&[&self.event_identity(event)])
+    }
+
+    /// Returns the UID of the all-day event marking a day of the schedule.
+    pub fn day_marker_uid(&self, date: NaiveDate) -> String {
+        self.make_uid(&["day", &date.to_string()])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::calendar::periods::NaiveTimePeriod;
+    use crate::times;
+    use serde_json::json;
+
+    #[test]
+    fn fnv1a_reference_values() {
+        assert_eq!(stable_hash(&[""]), 0xcbf29ce484222325);
+        assert_eq!(stable_hash(&["a"]), 0xaf63dc4c8601ec8c);
+        assert_eq!(stable_hash(&["foobar"]), 0x85944171f73967e8);
+        assert_ne!(stable_hash(&["ab", "c"]), stable_hash(&["a", "bc"]));
+    }
+
+    #[test]
+    fn uids_are_stable_and_unique() {
+        let schedule: Schedule = serde_json::from_value(json!({
+            "calendar": { "id": "group-1", "uidDomain": "example.com" },
+            "weeks": [
+                ["2025-10-20", "2025-10-21", "2025-10-22", "2025-10-23", "2025-10-24"]
+            ],
+            "subjects": { "subj": { "name": "Subject" } },
+            "teachers": {},
+            "schedule": [
+                { "subject": "subj", "type": "lab", "day": "monday", "time": "8:15-10:00",
+                  "location": { "building": "A", "room": "1" } },
+                { "subject": "subj", "type": "lab", "day": "monday", "time": "8:15-10:00",
+                  "location": { "building": "A", "room": "2" } },
+                { "id": "explicit", "subject": "subj", "type": "lab", "day": "monday", "time": "8:16-10:00" }
+            ]
+        }))
+        .unwrap();
+
+        let date = NaiveDate::from_ymd_opt(2025, 10, 20).unwrap();
+        let uids: Vec<_> = schedule
+            .schedule
+            .iter()
+            .map(|class| schedule.occurrence_uid(class, date))
+            .collect();
+
+        assert!(uids.iter().all(|uid| uid.ends_with("@example.com")));
+        assert_ne!(uids[0], uids[1]);
+        assert_ne!(uids[1], uids[2]);
+        assert_ne!(uids[0], uids[2]);
+
+        let mut moved = schedule;
+        moved.schedule[2].time = times!(9:00--10:30);
+        assert_eq!(moved.occurrence_uid(&moved.schedule[2], date), uids[2]);
+    }
+
+    #[test]
+    fn event_uids() {
+        let events = json!([
+            { "type": "exam", "title": "Egzamin", "subject": "subj", "date": "2025-10-22" },
+            { "type": "deadline", "title": "Lista 1", "subject": "subj", "date": "2025-10-22" },
+            { "id": "list-2", "type": "deadline", "title": "Lista 2", "subject": "subj", "date": "2025-10-29" }
+        ]);
+        let schedule = |events| -> Schedule {
+            serde_json::from_value(json!({
+                "weeks": [],
+                "subjects": { "subj": { "name": "Subject" } },
+                "teachers": {},
+                "schedule": [],
+                "events": events
+            }))
+            .unwrap()
+        };
+
+        let before = schedule(events.clone());
+        let identities: Vec<_> = before.events.iter().map(|event| before.event_identity(event)).collect();
+        assert_ne!(identities[1], identities[2]);
+
+        // Renaming or moving an event keeps its identity
+        let mut edited = events.clone();
+        edited[0]["title"] = json!("Egzamin poprawkowy");
+        edited[0]["date"] = json!("2025-11-05");
+        let after = schedule(edited);
+        assert_eq!(after.event_identity(&after.events[0]), identities[0]);
+
+        // Adding an event before ones of the same kind and subject only keeps the identities of those with an ID
+        let mut added = events;
+        added.as_array_mut().unwrap().insert(
+            0,
+            json!({ "type": "deadline", "title": "Lista 0", "subject": "subj", "date": "2025-10-15" }),
+        );
+        let after = schedule(added);
+        assert_eq!(after.event_identity(&after.events[1]), identities[0]);
+        assert_ne!(after.event_identity(&after.events[2]), identities[1]);
+        assert_eq!(after.event_identity(&after.events[3]), identities[2]);
+    }
+}
@@ -0,0 +1,180 @@
+//! Export of the expanded list of class occurrences to tabular formats.
+
+use crate::calendar::schedule::Schedule;
+use clap::ValueEnum;
+use serde::Serialize;
+use std::io::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ExportFormat {
+    Csv,
+    #[value(name = "md")]
+    Markdown,
+    Html,
+    Json,
+}
+
+/// A single occurrence of a class, as exported.
+#[derive(Debug, Serialize)]
+pub struct ExportRow {
+    pub date: String,
+    pub week: usize,
+    pub weekday: String,
+    pub start: String,
+    pub end: String,
+    pub subject: String,
+    #[serde(rename = "type")]
+    pub class_type: String,
+    pub location: String,
+    pub teachers: Vec<String>,
+}
+
+impl ExportRow {
+    const HEADERS: [&'static str; 9] = [
+        "Date", "Week", "Weekday", "Start", "End", "Subject", "Type", "Location", "Teachers",
+    ];
+
+    fn cells(&self) -> [String; 9] {
+        [
+            self.date.clone(),
+            self.week.to_string(),
+            self.weekday.clone(),
+            self.start.clone(),
+            self.end.clone(),
+            self.subject.clone(),
+            self.class_type.clone(),
+            self.location.clone(),
+            self.teachers.join(", "),
+        ]
+    }
+}
+
+/// Returns the rows of all class occurrences, in the order [`Schedule::occurrences`] generates them.
+pub fn occurrence_rows(schedule: &Schedule) -> eyre::Result<Vec<ExportRow>> {
+    schedule
+        .occurrences()
+        .map(|occurrence| {
+            let class = occurrence.class;
+            let context = schedule.class_context(class)?;
+
+            Ok(ExportRow {
+                date: occurrence.date.to_string(),
+                week: occurrence.week.get(),
+                weekday: occurrence.weekday.to_string(),
+                start: class.time.start.format("%H:%M").to_string(),
+                end: class.time.end.format("%H:%M").to_string(),
+                subject: context.subject.name.clone(),
+                class_type: class.class_type.to_name().to_string(),
+                location: context.value("location").unwrap_or_default(),
+                teachers: context.teachers.iter().map(|t| t.name.clone()).collect(),
+            })
+        })
+        .collect()
+}
+
+/// Escapes the characters with special meaning in HTML.
+pub fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Quotes a CSV field if needed, as described in [RFC 4180](https://www.rfc-editor.org/rfc/rfc4180).
+fn escape_csv(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_string()
+    }
+}
+
+fn escape_markdown(text: &str) -> String {
+    text.replace('|', "\\|").replace('\n', "<br>")
+}
+
+/// Writes the rows in the given format.
+pub fn write_rows(rows: &[ExportRow], format: ExportFormat, out: &mut dyn Write) -> eyre::Result<()> {
+    match format {
+        ExportFormat::Csv => {
+            writeln!(out, "{}", ExportRow::HEADERS.join(","))?;
+            for row in rows {
+                let cells: Vec<_> = row.cells().iter().map(|cell| escape_csv(cell)).collect();
+                writeln!(out, "{}", cells.join(","))?;
+            }
+        }
+        ExportFormat::Markdown => {
+            writeln!(out, "| {} |", ExportRow::HEADERS.join(" | "))?;
+            writeln!(out, "|{}", "---|".repeat(ExportRow::HEADERS.len()))?;
+            for row in rows {
+                let cells: Vec<_> = row.cells().iter().map(|cell| escape_markdown(cell)).collect();
+                writeln!(out, "| {} |", cells.join(" | "))?;
+            }
+        }
+        ExportFormat::Html => {
+            writeln!(out, "<table>")?;
+            writeln!(out, "  <thead>")?;
+            writeln!(out, "    <tr><th>{}</th></tr>", ExportRow::HEADERS.join("</th><th>"))?;
+            writeln!(out, "  </thead>")?;
+            writeln!(out, "  <tbody>")?;
+            for row in rows {
+                let cells: Vec<_> = row.cells().iter().map(|cell| escape_html(cell)).collect();
+                writeln!(out, "    <tr><td>{}</td></tr>", cells.join("</td><td>"))?;
+            }
+            writeln!(out, "  </tbody>")?;
+            writeln!(out, "</table>")?;
+        }
+        ExportFormat::Json => {
+            serde_json::to_writer_pretty(&mut *out, rows)?;
+            writeln!(out)?;
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn export(format: ExportFormat) -> String {
+        let schedule: Schedule = serde_json::from_value(json!({
+            "weeks": [["2025-10-20", "2025-10-21", "2025-10-22", "2025-10-23", "2025-10-24"]],
+            "subjects": { "aisd": { "name": "Algorytmy, struktury | dane" } },
+            "teachers": { "kowalski": { "name": "dr Kowalski" }, "nowak": { "name": "mgr Nowak" } },
+            "schedule": [
+                { "subject": "aisd", "type": "lab", "day": "tuesday", "time": "10:15-12:00",
+                  "location": { "building": "C3", "room": "204" }, "teachers": ["kowalski", "nowak"] },
+                { "subject": "aisd", "type": "lecture", "day": "monday", "time": "8:15-10:00", "location": "online" }
+            ]
+        }))
+        .unwrap();
+
+        let rows = occurrence_rows(&schedule).unwrap();
+        let mut buffer = Vec::new();
+        write_rows(&rows, format, &mut buffer).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    #[test]
+    fn export_formats() {
+        assert_eq!(
+            export(ExportFormat::Csv),
+            "Date,Week,Weekday,Start,End,Subject,Type,Location,Teachers\n\
+             2025-10-20,1,Mon,08:15,10:00,\"Algorytmy, struktury | dane\",Wykład,Online,\n\
+             2025-10-21,1,Tue,10:15,12:00,\"Algorytmy, struktury | dane\",Laboratorium,204/C3,\"dr Kowalski, mgr Nowak\"\n"
+        );
+
+        let markdown = export(ExportFormat::Markdown);
+        assert!(markdown.starts_with("| Date | Week |"));
+        assert!(markdown.contains("| 2025-10-20 | 1 | Mon | 08:15 | 10:00 | Algorytmy, struktury \\| dane | Wykład | Online |  |"));
+
+        let html = export(ExportFormat::Html);
+        assert!(html.contains("<tr><td>2025-10-21</td><td>1</td><td>Tue</td>"));
+
+        let exported: serde_json::Value = serde_json::from_str(&export(ExportFormat::Json)).unwrap();
+        assert_eq!(exported[1]["type"], "Laboratorium");
+        assert_eq!(exported[1]["teachers"], json!(["dr Kowalski", "mgr Nowak"]));
+    }
+}
@@ -0,0 +1,249 @@
+use std::io::Write;
+
+/// Maximum length of a single physical line, in octets, excluding the line break.
+///
+/// See [RFC 5545, section 3.1](https://www.rfc-editor.org/rfc/rfc5545#section-3.1).
+pub const MAX_LINE_OCTETS: usize = 75;
+
+/// A single iCalendar content line, i.e. a property with its parameters and value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContentLine {
+    /// Name of the property, e.g. `DTSTART`.
+    pub name: String,
+    /// Parameters of the property, in the order they should be written.
+    pub params: Vec<(String, String)>,
+    /// Already-encoded value of the property.
+    ///
+    /// TEXT values should be escaped with [`escape_text`] first, see [`ContentLine::text`].
+    pub value: String,
+}
+
+impl ContentLine {
+    /// Creates a content line with a raw (already-encoded) value.
+    pub fn new(name: &str, value: impl Into<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            params: Vec::new(),
+            value: value.into(),
+        }
+    }
+
+    /// Creates a content line with a TEXT value, escaping it as needed.
+    pub fn text(name: &str, text: &str) -> Self {
+        Self::new(name, escape_text(text))
+    }
+
+    /// Creates a content line with a list of TEXT values, escaping each of them as needed.
+    pub fn text_list(name: &str, texts: &[String]) -> Self {
+        let escaped: Vec<_> = texts.iter().map(|text| escape_text(text)).collect();
+        Self::new(name, escaped.join(","))
+    }
+
+    /// Adds a parameter to the content line.
+    pub fn param(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.params.push((name.to_string(), value.into()));
+        self
+    }
+
+    /// Returns the unfolded representation of the content line, without the line break.
+    pub fn to_unfolded(&self) -> String {
+        let mut line = self.name.clone();
+
+        for (name, value) in &self.params {
+            line.push(';');
+            line.push_str(name);
+            line.push('=');
+            line.push_str(&quote_param_value(value));
+        }
+
+        line.push(':');
+        line.push_str(&self.value);
+        line
+    }
+}
+
+/// Escapes a TEXT value.
+///
+/// Backslashes, semicolons and commas are backslash-escaped, and line breaks are replaced with `\n`.
+///
+/// See [RFC 5545, section 3.3.11](https://www.rfc-editor.org/rfc/rfc5545#section-3.3.11).
+pub fn escape_text(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => escaped.push_str("\\\\"),
+            ';' => escaped.push_str("\\;"),
+            ',' => escaped.push_str("\\,"),
+            '\r' => {
+                if chars.peek() == Some(&'\n') {
+                    chars.next();
+                }
+                escaped.push_str("\\n");
+            }
+            '\n' => escaped.push_str("\\n"),
+            _ => escaped.push(c),
+        }
+    }
+
+    escaped
+}
+
+/// Quotes a parameter value if it contains characters not allowed in unquoted values.
+///
+/// Double quotes are not allowed in parameter values at all, so they are replaced with single quotes.
+fn quote_param_value(value: &str) -> String {
+    let value = value.replace('"', "'");
+
+    if value.contains([':', ';', ',']) {
+        format!("\"{}\"", value)
+    } else {
+        value
+    }
+}
+
+/// Folds a single unfolded content line into physical lines of at most [`MAX_LINE_OCTETS`] octets.
+///
+/// Each physical line, including the last one, ends with CRLF.
+/// Continuation lines start with a single space, which counts towards their length.
+/// Lines are never split in the middle of a UTF-8 sequence.
+pub fn fold_line(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + line.len() / MAX_LINE_OCTETS * 3 + 2);
+    let mut current_len = 0;
+
+    for c in line.chars() {
+        if current_len + c.len_utf8() > MAX_LINE_OCTETS {
+            folded.push_str("\r\n ");
+            current_len = 1;
+        }
+
+        folded.push(c);
+        current_len += c.len_utf8();
+    }
+
+    folded.push_str("\r\n");
+    folded
+}
+
+/// Writer of iCalendar content lines, taking care of folding.
+pub struct ContentLineWriter<'a> {
+    write: &'a mut dyn Write,
+}
+
+impl<'a> ContentLineWriter<'a> {
+    pub fn new(write: &'a mut dyn Write) -> Self {
+        Self { write }
+    }
+
+    /// Writes a content line, folding it if needed.
+    pub fn write_line(&mut self, line: &ContentLine) -> eyre::Result<()> {
+        self.write.write_all(fold_line(&line.to_unfolded()).as_bytes())?;
+        Ok(())
+    }
+
+    /// Writes a property with a raw (already-encoded) value.
+    pub fn write_property(&mut self, name: &str, value: &str) -> eyre::Result<()> {
+        self.write_line(&ContentLine::new(name, value))
+    }
+
+    /// Writes a property with a TEXT value, escaping it as needed.
+    pub fn write_text(&mut self, name: &str, text: &str) -> eyre::Result<()> {
+        self.write_line(&ContentLine::text(name, text))
+    }
+
+    /// Writes the `BEGIN` line of a component.
+    pub fn begin(&mut self, component: &str) -> eyre::Result<()> {
+        self.write_property("BEGIN", component)
+    }
+
+    /// Writes the `END` line of a component.
+    pub fn end(&mut self, component: &str) -> eyre::Result<()> {
+        self.write_property("END", component)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escape_rfc_examples() {
+        // RFC 5545, section 3.8.1.5
+        assert_eq!(
+            ContentLine::text("DESCRIPTION", "Meeting to provide technical review for \"Phoenix\" design.\nHappy Face Conference Room. Phoenix design team MUST attend this meeting.\nRSVP to team leader.")
+                .to_unfolded(),
+            "DESCRIPTION:Meeting to provide technical review for \"Phoenix\" design.\\nHappy Face Conference Room. Phoenix design team MUST attend this meeting.\\nRSVP to team leader."
+        );
+        // RFC 5545, section 3.8.1.7
+        assert_eq!(
+            ContentLine::text("LOCATION", "Conference Room - F123, Bldg. 002").to_unfolded(),
+            "LOCATION:Conference Room - F123\\, Bldg. 002"
+        );
+        // RFC 5545, section 3.3.11
+        assert_eq!(
+            escape_text("Project XYZ Final Review\r\nConference Room - 3B\nCome Prepared."),
+            "Project XYZ Final Review\\nConference Room - 3B\\nCome Prepared."
+        );
+    }
+
+    #[test]
+    fn escape_special_characters() {
+        assert_eq!(escape_text("a\\b;c,d"), "a\\\\b\\;c\\,d");
+        assert_eq!(escape_text("zażółć gęślą jaźń"), "zażółć gęślą jaźń");
+    }
+
+    #[test]
+    fn escape_text_list() {
+        let line = ContentLine::text_list(
+            "CATEGORIES",
+            &["Laboratorium".to_string(), "Algorytmy, część 1".to_string()],
+        );
+
+        assert_eq!(line.to_unfolded(), "CATEGORIES:Laboratorium,Algorytmy\\, część 1");
+    }
+
+    #[test]
+    fn quote_params() {
+        let line = ContentLine::new("ATTENDEE", "mailto:jsmith@example.com")
+            .param("CN", "Smith, John")
+            .param("ROLE", "REQ-PARTICIPANT");
+
+        assert_eq!(
+            line.to_unfolded(),
+            "ATTENDEE;CN=\"Smith, John\";ROLE=REQ-PARTICIPANT:mailto:jsmith@example.com"
+        );
+    }
+
+    #[test]
+    fn short_lines_are_not_folded() {
+        assert_eq!(fold_line("SUMMARY:Short"), "SUMMARY:Short\r\n");
+
+        let exactly_max = format!("SUMMARY:{}", "a".repeat(MAX_LINE_OCTETS - 8));
+        assert_eq!(fold_line(&exactly_max), format!("{}\r\n", exactly_max));
+    }
+
+    #[test]
+    fn long_lines_are_folded() {
+        let line = format!("DESCRIPTION:{}", "x".repeat(200));
+        let folded = fold_line(&line);
+
+        for physical in folded.split_terminator("\r\n") {
+            assert!(physical.len() <= MAX_LINE_OCTETS);
+        }
+
+        assert_eq!(folded.replace("\r\n ", ""), format!("{}\r\n", line));
+    }
+
+    #[test]
+    fn folding_does_not_split_utf8() {
+        let line = format!("SUMMARY:🧪 {}", "Żółć ".repeat(30));
+        let folded = fold_line(&line);
+
+        for physical in folded.split_terminator("\r\n") {
+            assert!(physical.len() <= MAX_LINE_OCTETS);
+        }
+
+        assert_eq!(folded.replace("\r\n ", ""), format!("{}\r\n", line));
+    }
+}
@@ -1,6 +1,12 @@
+pub mod contentline;
+pub mod parser;
+pub mod recurrence;
+pub mod valarm;
 pub mod vcalendar;
 pub mod vevent;
+pub mod vtimezone;
 
+use crate::ical::parser::Component;
 use std::io::Write;
 use std::path::Path;
 
@@ -27,3 +33,12 @@ impl dyn SerializeToICal {
         Ok(())
     }
 }
+
+/// Trait for deserializing an object from a parsed iCal component.
+pub trait DeserializeFromICal: Sized {
+    /// Builds the object from a component.
+    ///
+    /// Implementations should fail if required properties are missing or malformed,
+    /// and should preserve unknown properties where possible.
+    fn from_component(component: &Component) -> eyre::Result<Self>;
+}
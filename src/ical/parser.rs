@@ -0,0 +1,414 @@
+//! Parsing of iCalendar data into generic components.
+//!
+//! See [RFC 5545, section 3](https://www.rfc-editor.org/rfc/rfc5545#section-3).
+
+use crate::ical::contentline::ContentLine;
+use crate::ical::DeserializeFromICal;
+use crate::time::timezones::{get_timezone_by_name, get_timezone_by_windows_name};
+use chrono::{DateTime, NaiveDate, NaiveDateTime, TimeDelta, TimeZone};
+use chrono_tz::Tz;
+use eyre::{eyre, OptionExt};
+use log::warn;
+
+/// A generic iCalendar component, like `VCALENDAR` or `VEVENT`, with its properties and sub-components.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Component {
+    pub name: String,
+    pub properties: Vec<ContentLine>,
+    pub components: Vec<Component>,
+}
+
+impl Component {
+    /// Returns the first property with the given name.
+    pub fn property(&self, name: &str) -> Option<&ContentLine> {
+        self.properties.iter().find(|p| p.name == name)
+    }
+
+    /// Returns the value of the first property with the given name, failing if there is none.
+    pub fn required(&self, name: &str) -> eyre::Result<&ContentLine> {
+        self.property(name)
+            .ok_or_else(|| eyre!("{} is missing the required {} property", self.name, name))
+    }
+
+    /// Returns all sub-components with the given name.
+    pub fn components<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a Component> {
+        self.components.iter().filter(move |c| c.name == name)
+    }
+}
+
+impl DeserializeFromICal for Component {
+    /// Returns the component as-is, to be interpreted by the caller.
+    fn from_component(component: &Component) -> eyre::Result<Self> {
+        Ok(component.clone())
+    }
+}
+
+impl ContentLine {
+    /// Returns the value of the given parameter, if present.
+    pub fn get_param(&self, name: &str) -> Option<&str> {
+        self.params
+            .iter()
+            .find(|(n, _)| n.eq_ignore_ascii_case(name))
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Returns the unescaped TEXT value.
+    pub fn text_value(&self) -> String {
+        unescape_text(&self.value)
+    }
+
+    /// Returns the unescaped values of a property with a comma-separated list of TEXT values.
+    pub fn text_list_value(&self) -> Vec<String> {
+        let mut values = Vec::new();
+        let mut current = String::new();
+        let mut escaped = false;
+
+        for c in self.value.chars() {
+            match c {
+                ',' if !escaped => values.push(unescape_text(&std::mem::take(&mut current))),
+                _ => {
+                    escaped = c == '\\' && !escaped;
+                    current.push(c);
+                }
+            }
+        }
+
+        values.push(unescape_text(&current));
+        values
+    }
+}
+
+/// Unfolds the physical lines of iCalendar data into logical content lines.
+///
+/// Both CRLF and bare LF line breaks are accepted. Empty lines are skipped.
+pub fn unfold(input: &str) -> Vec<String> {
+    let mut lines: Vec<String> = Vec::new();
+
+    for physical in input.split('\n') {
+        let physical = physical.strip_suffix('\r').unwrap_or(physical);
+
+        if let Some(continuation) = physical.strip_prefix([' ', '\t']) {
+            if let Some(last) = lines.last_mut() {
+                last.push_str(continuation);
+                continue;
+            }
+        }
+
+        if !physical.is_empty() {
+            lines.push(physical.to_string());
+        }
+    }
+
+    lines
+}
+
+/// Reverses [`escape_text`](crate::ical::contentline::escape_text).
+pub fn unescape_text(text: &str) -> String {
+    let mut unescaped = String::with_capacity(text.len());
+    let mut chars = text.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            unescaped.push(c);
+            continue;
+        }
+
+        match chars.next() {
+            Some('n' | 'N') => unescaped.push('\n'),
+            Some(other) => unescaped.push(other),
+            None => unescaped.push('\\'),
+        }
+    }
+
+    unescaped
+}
+
+/// Parses a single unfolded content line.
+pub fn parse_content_line(line: &str) -> eyre::Result<ContentLine> {
+    let name_end = line
+        .find([';', ':'])
+        .ok_or_else(|| eyre!("content line without a value: {}", line))?;
+    let name = line[..name_end].to_ascii_uppercase();
+
+    if name.is_empty() {
+        return Err(eyre!("content line without a name: {}", line));
+    }
+
+    let mut params = Vec::new();
+    let mut rest = &line[name_end..];
+
+    while let Some(param) = rest.strip_prefix(';') {
+        let eq = param
+            .find('=')
+            .ok_or_else(|| eyre!("parameter without a value: {}", line))?;
+        let param_name = param[..eq].to_ascii_uppercase();
+        let mut value = String::new();
+        let mut in_quotes = false;
+        let mut end = param.len();
+
+        for (i, c) in param[eq + 1..].char_indices() {
+            match c {
+                '"' => in_quotes = !in_quotes,
+                ';' | ':' if !in_quotes => {
+                    end = eq + 1 + i;
+                    break;
+                }
+                _ => value.push(c),
+            }
+        }
+
+        params.push((param_name, value));
+        rest = &param[end..];
+    }
+
+    let value = rest
+        .strip_prefix(':')
+        .ok_or_else(|| eyre!("content line without a value: {}", line))?;
+
+    Ok(ContentLine {
+        name,
+        params,
+        value: value.to_string(),
+    })
+}
+
+/// Parses iCalendar data into a list of top-level components.
+pub fn parse_components(input: &str) -> eyre::Result<Vec<Component>> {
+    let mut stack: Vec<Component> = Vec::new();
+    let mut top_level = Vec::new();
+
+    for line in unfold(input) {
+        let line = parse_content_line(&line)?;
+
+        match line.name.as_str() {
+            "BEGIN" => stack.push(Component {
+                name: line.value.to_ascii_uppercase(),
+                properties: Vec::new(),
+                components: Vec::new(),
+            }),
+            "END" => {
+                let component = stack
+                    .pop()
+                    .ok_or_else(|| eyre!("unexpected END:{}", line.value))?;
+
+                if !component.name.eq_ignore_ascii_case(&line.value) {
+                    return Err(eyre!(
+                        "expected END:{}, found END:{}",
+                        component.name,
+                        line.value
+                    ));
+                }
+
+                match stack.last_mut() {
+                    Some(parent) => parent.components.push(component),
+                    None => top_level.push(component),
+                }
+            }
+            _ => stack
+                .last_mut()
+                .ok_or_else(|| eyre!("property {} outside of any component", line.name))?
+                .properties
+                .push(line),
+        }
+    }
+
+    if let Some(unclosed) = stack.last() {
+        return Err(eyre!("missing END:{}", unclosed.name));
+    }
+
+    Ok(top_level)
+}
+
+/// Parses iCalendar data containing exactly one top-level component of the given type.
+pub fn parse_single<T: DeserializeFromICal>(input: &str, name: &str) -> eyre::Result<T> {
+    let mut components = parse_components(input)?.into_iter();
+
+    let component = components
+        .next()
+        .ok_or_else(|| eyre!("no {} component found", name))?;
+
+    if component.name != name {
+        return Err(eyre!("expected {}, found {}", name, component.name));
+    }
+
+    if components.next().is_some() {
+        return Err(eyre!("more than one top-level component found"));
+    }
+
+    T::from_component(&component)
+}
+
+/// Parses a `DATE` value, like `20251020`.
+pub fn parse_date(value: &str) -> eyre::Result<NaiveDate> {
+    NaiveDate::parse_from_str(value, "%Y%m%d").map_err(|_| eyre!("invalid date: {}", value))
+}
+
+/// Returns the time zone of a `TZID` parameter, given as an IANA or a Windows time zone name.
+///
+/// Unknown time zones are interpreted as UTC, with a warning.
+/// Definitions in `VTIMEZONE` components are resolved by [`VCalendar`](crate::ical::vcalendar::VCalendar) beforehand.
+pub fn resolve_tz_id(tz_id: &str) -> Tz {
+    get_timezone_by_name(tz_id)
+        .or_else(|| get_timezone_by_windows_name(tz_id))
+        .unwrap_or_else(|| {
+            warn!("Unknown time zone {}, using UTC instead", tz_id);
+            Tz::UTC
+        })
+}
+
+/// Parses a single `DATE-TIME` value, using the time zone from the `TZID` parameter if present.
+///
+/// Values in UTC (ending with `Z`) are returned in the UTC time zone.
+/// Floating values (without a time zone) are interpreted as UTC as well;
+/// [`VCalendar::parse_in`](crate::ical::vcalendar::VCalendar::parse_in) gives them the `TZID`
+/// of the time zone they're meant to be local to beforehand.
+pub fn parse_datetime(value: &str, tz_id: Option<&str>) -> eyre::Result<DateTime<Tz>> {
+    let (value, tz) = match (value.strip_suffix('Z'), tz_id) {
+        (Some(utc), _) => (utc, Tz::UTC),
+        (None, Some(tz_id)) => (value, resolve_tz_id(tz_id)),
+        (None, None) => (value, Tz::UTC),
+    };
+
+    let naive = NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .map_err(|_| eyre!("invalid date-time: {}", value))?;
+
+    // Times in a DST gap are shifted forward, as required by RFC 5545, section 3.3.5.
+    tz.from_local_datetime(&naive)
+        .earliest()
+        .or_else(|| tz.from_local_datetime(&(naive + TimeDelta::hours(1))).earliest())
+        .ok_or_eyre("non-existent local time")
+}
+
+/// Parses a date-time property, using its `TZID` parameter.
+pub fn parse_datetime_property(line: &ContentLine) -> eyre::Result<DateTime<Tz>> {
+    parse_datetime(&line.value, line.get_param("TZID"))
+}
+
+/// Parses a `DURATION` value, like `PT1H30M` or `-P1D`.
+pub fn parse_duration(value: &str) -> eyre::Result<TimeDelta> {
+    let invalid = || eyre!("invalid duration: {}", value);
+
+    let (negative, rest) = match value.as_bytes().first() {
+        Some(b'-') => (true, &value[1..]),
+        Some(b'+') => (false, &value[1..]),
+        _ => (false, value),
+    };
+    let rest = rest.strip_prefix('P').ok_or_else(invalid)?;
+
+    let mut total = TimeDelta::zero();
+    let mut number = String::new();
+    let mut in_time = false;
+
+    for c in rest.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => in_time = true,
+            _ => {
+                let n: i64 = number.parse().map_err(|_| invalid())?;
+                number.clear();
+
+                total += match (c, in_time) {
+                    ('W', false) => TimeDelta::weeks(n),
+                    ('D', false) => TimeDelta::days(n),
+                    ('H', true) => TimeDelta::hours(n),
+                    ('M', true) => TimeDelta::minutes(n),
+                    ('S', true) => TimeDelta::seconds(n),
+                    _ => return Err(invalid()),
+                };
+            }
+        }
+    }
+
+    if !number.is_empty() {
+        return Err(invalid());
+    }
+
+    Ok(if negative { -total } else { total })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unfold_rfc_example() {
+        // RFC 5545, section 3.1
+        let input = "DESCRIPTION:This is a lo\r\n ng description\r\n  that exists on a long line.\r\n";
+
+        assert_eq!(
+            unfold(input),
+            vec!["DESCRIPTION:This is a long description that exists on a long line."]
+        );
+    }
+
+    #[test]
+    fn unescape() {
+        assert_eq!(
+            unescape_text("Conference Room - F123\\, Bldg. 002"),
+            "Conference Room - F123, Bldg. 002"
+        );
+        assert_eq!(unescape_text("a\\\\b\\;c\\nd\\Ne"), "a\\b;c\nd\ne");
+    }
+
+    #[test]
+    fn parse_text_list() {
+        let line = parse_content_line("CATEGORIES:APPOINTMENT,a\\,b,c\\\\,d").unwrap();
+
+        assert_eq!(line.text_list_value(), vec!["APPOINTMENT", "a,b", "c\\", "d"]);
+    }
+
+    #[test]
+    fn parse_params() {
+        let line = parse_content_line(
+            "ATTENDEE;CN=\"Smith, John\";role=REQ-PARTICIPANT:mailto:jsmith@example.com",
+        )
+        .unwrap();
+
+        assert_eq!(line.name, "ATTENDEE");
+        assert_eq!(line.get_param("CN"), Some("Smith, John"));
+        assert_eq!(line.get_param("ROLE"), Some("REQ-PARTICIPANT"));
+        assert_eq!(line.value, "mailto:jsmith@example.com");
+    }
+
+    #[test]
+    fn parse_nested_components() {
+        let components = parse_components(
+            "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VEVENT\r\nUID:1\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n",
+        )
+        .unwrap();
+
+        assert_eq!(components.len(), 1);
+        assert_eq!(components[0].name, "VCALENDAR");
+        assert_eq!(components[0].required("VERSION").unwrap().value, "2.0");
+        assert_eq!(components[0].components("VEVENT").count(), 1);
+
+        assert!(parse_components("BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nEND:VCALENDAR\r\n").is_err());
+        assert!(parse_components("BEGIN:VCALENDAR\r\n").is_err());
+    }
+
+    #[test]
+    fn parse_datetimes() {
+        let warsaw = parse_datetime("20251020T081500", Some("Europe/Warsaw")).unwrap();
+        assert_eq!(warsaw.timezone(), Tz::Europe__Warsaw);
+        assert_eq!(warsaw.to_rfc3339(), "2025-10-20T08:15:00+02:00");
+
+        let utc = parse_datetime("20251020T061500Z", None).unwrap();
+        assert_eq!(utc, warsaw);
+
+        let windows = parse_datetime("20251020T081500", Some("Central European Standard Time")).unwrap();
+        assert_eq!(windows, warsaw);
+
+        let unknown = parse_datetime("20251020T081500", Some("Nowhere/Nothing")).unwrap();
+        assert_eq!(unknown.timezone(), Tz::UTC);
+    }
+
+    #[test]
+    fn parse_durations() {
+        assert_eq!(parse_duration("PT15M").unwrap(), TimeDelta::minutes(15));
+        assert_eq!(parse_duration("-PT1H30M").unwrap(), -TimeDelta::minutes(90));
+        assert_eq!(parse_duration("P1W").unwrap(), TimeDelta::weeks(1));
+        assert_eq!(parse_duration("P1DT12H").unwrap(), TimeDelta::hours(36));
+        assert!(parse_duration("15M").is_err());
+        assert!(parse_duration("P15M").is_err());
+    }
+}
@@ -0,0 +1,216 @@
+use crate::ical::parser::{parse_date, parse_datetime};
+use crate::time::timeext::TimeExt;
+use chrono::{DateTime, Datelike, Days, Months};
+use eyre::{eyre, OptionExt};
+use chrono_tz::Tz;
+use std::fmt::{Display, Formatter};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Frequency {
+    Daily,
+    Weekly,
+    Monthly,
+    Yearly,
+}
+
+impl Frequency {
+    pub fn to_ical(self) -> &'static str {
+        match self {
+            Frequency::Daily => "DAILY",
+            Frequency::Weekly => "WEEKLY",
+            Frequency::Monthly => "MONTHLY",
+            Frequency::Yearly => "YEARLY",
+        }
+    }
+
+    pub fn from_ical(value: &str) -> Option<Self> {
+        match value {
+            "DAILY" => Some(Frequency::Daily),
+            "WEEKLY" => Some(Frequency::Weekly),
+            "MONTHLY" => Some(Frequency::Monthly),
+            "YEARLY" => Some(Frequency::Yearly),
+            _ => None,
+        }
+    }
+}
+
+/// Recurrence rule of an event.
+///
+/// Corresponds to the `RRULE` property in iCalendar.
+/// Only the subset of [RFC 5545, section 3.3.10](https://www.rfc-editor.org/rfc/rfc5545#section-3.3.10)
+/// needed for regularly repeating classes is supported.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecurrenceRule {
+    pub frequency: Frequency,
+    /// How many periods of [`RecurrenceRule::frequency`] pass between occurrences.
+    pub interval: u32,
+    /// Last instant at which an occurrence can start (inclusive).
+    pub until: Option<DateTime<Tz>>,
+    /// Total number of occurrences.
+    pub count: Option<u32>,
+}
+
+impl RecurrenceRule {
+    pub fn weekly(interval: u32, until: DateTime<Tz>) -> Self {
+        RecurrenceRule {
+            frequency: Frequency::Weekly,
+            interval,
+            until: Some(until),
+            count: None,
+        }
+    }
+}
+
+impl RecurrenceRule {
+    /// Parses an `RRULE` value, interpreting `UNTIL` in the time zone of the event start.
+    ///
+    /// Returns `None` if the rule uses parts or frequencies this type can't represent, like `BYDAY`.
+    pub fn parse(value: &str, tz: Tz) -> eyre::Result<Option<Self>> {
+        let mut frequency = None;
+        let mut rule = RecurrenceRule {
+            frequency: Frequency::Weekly,
+            interval: 1,
+            until: None,
+            count: None,
+        };
+
+        for part in value.split(';') {
+            let (name, value) = part
+                .split_once('=')
+                .ok_or_else(|| eyre!("invalid RRULE part: {}", part))?;
+
+            match name.to_ascii_uppercase().as_str() {
+                "FREQ" => frequency = Frequency::from_ical(value),
+                "INTERVAL" => rule.interval = value.parse()?,
+                "COUNT" => rule.count = Some(value.parse()?),
+                "UNTIL" => {
+                    rule.until = Some(if value.len() == 8 {
+                        let date = parse_date(value)?;
+                        date.and_hms_opt(23, 59, 59)
+                            .unwrap()
+                            .and_local_timezone(tz)
+                            .earliest()
+                            .ok_or_eyre("non-existent UNTIL time")?
+                    } else {
+                        parse_datetime(value, Some(tz.name()))?.with_timezone(&tz)
+                    })
+                }
+                "WKST" => {}
+                _ => return Ok(None),
+            }
+        }
+
+        Ok(frequency.map(|frequency| RecurrenceRule { frequency, ..rule }))
+    }
+
+    /// Returns the start times of all occurrences generated by the rule for an event starting at `start`,
+    /// including `start` itself.
+    ///
+    /// Occurrences are generated up to `UNTIL` or `COUNT`, but never past `limit`,
+    /// so unbounded rules can be expanded too.
+    /// Local times are kept across DST changes; occurrences on non-existent dates
+    /// (e.g. the 31st of a shorter month) are skipped.
+    pub fn expand(&self, start: DateTime<Tz>, limit: DateTime<Tz>) -> Vec<DateTime<Tz>> {
+        let tz = start.timezone();
+        let naive_start = start.naive_local();
+        let interval = self.interval.max(1);
+        let mut occurrences = Vec::new();
+
+        for step in 0.. {
+            let n = step * interval;
+            let naive = match self.frequency {
+                Frequency::Daily => naive_start.checked_add_days(Days::new(n as u64)),
+                Frequency::Weekly => naive_start.checked_add_days(Days::new(7 * n as u64)),
+                Frequency::Monthly => naive_start.checked_add_months(Months::new(n)),
+                Frequency::Yearly => naive_start.checked_add_months(Months::new(12 * n)),
+            };
+
+            let Some(naive) = naive else {
+                break;
+            };
+
+            // Adding months clamps to the end of the month; such occurrences don't exist
+            let adds_months = matches!(self.frequency, Frequency::Monthly | Frequency::Yearly);
+            if adds_months && naive.day() != naive_start.day() {
+                continue;
+            }
+
+            let Some(occurrence) = naive.and_local_timezone(tz).earliest() else {
+                continue;
+            };
+
+            if occurrence > limit || self.until.is_some_and(|until| occurrence > until) {
+                break;
+            }
+
+            occurrences.push(occurrence);
+
+            if self.count.is_some_and(|count| occurrences.len() >= count as usize) {
+                break;
+            }
+        }
+
+        occurrences
+    }
+}
+
+impl Display for RecurrenceRule {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "FREQ={}", self.frequency.to_ical())?;
+
+        if self.interval != 1 {
+            write!(f, ";INTERVAL={}", self.interval)?;
+        }
+
+        // UNTIL must be in UTC when DTSTART is specified with a time zone.
+        if let Some(until) = &self.until {
+            write!(f, ";UNTIL={}", until.to_utc_stamp())?;
+        }
+
+        if let Some(count) = self.count {
+            write!(f, ";COUNT={}", count)?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn parse_and_expand() {
+        let tz = Tz::Europe__Warsaw;
+        let start = tz.with_ymd_and_hms(2025, 10, 20, 10, 15, 0).unwrap();
+        let limit = tz.with_ymd_and_hms(2026, 12, 31, 0, 0, 0).unwrap();
+
+        let rule = RecurrenceRule::parse("FREQ=WEEKLY;INTERVAL=2;UNTIL=20251117T091500Z", tz)
+            .unwrap()
+            .unwrap();
+        assert_eq!(rule.to_string(), "FREQ=WEEKLY;INTERVAL=2;UNTIL=20251117T091500Z");
+        // Local time is kept across the DST change
+        assert_eq!(
+            rule.expand(start, limit),
+            vec![
+                start,
+                tz.with_ymd_and_hms(2025, 11, 3, 10, 15, 0).unwrap(),
+                tz.with_ymd_and_hms(2025, 11, 17, 10, 15, 0).unwrap(),
+            ]
+        );
+
+        let rule = RecurrenceRule::parse("FREQ=MONTHLY;COUNT=3", tz).unwrap().unwrap();
+        let start = tz.with_ymd_and_hms(2025, 10, 31, 12, 0, 0).unwrap();
+        assert_eq!(
+            rule.expand(start, limit),
+            vec![
+                start,
+                tz.with_ymd_and_hms(2025, 12, 31, 12, 0, 0).unwrap(),
+                tz.with_ymd_and_hms(2026, 1, 31, 12, 0, 0).unwrap(),
+            ]
+        );
+
+        assert_eq!(RecurrenceRule::parse("FREQ=WEEKLY;BYDAY=MO,WE", tz).unwrap(), None);
+    }
+}
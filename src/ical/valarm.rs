@@ -0,0 +1,71 @@
+use crate::ical::contentline::ContentLineWriter;
+use crate::ical::parser::{parse_duration, Component};
+use crate::ical::{DeserializeFromICal, SerializeToICal};
+use crate::time::timeext::TimeDeltaExt;
+use chrono::TimeDelta;
+use eyre::eyre;
+use std::io::Write;
+
+/// Alarm (reminder) of an event.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VAlarm {
+    /// Action taken when the alarm triggers, like `DISPLAY`.
+    ///
+    /// Corresponds to the `ACTION` property in iCalendar.
+    pub action: String,
+    /// When the alarm triggers, relative to the start of the event.
+    /// Negative values trigger before the start.
+    ///
+    /// Corresponds to the `TRIGGER` property in iCalendar.
+    pub trigger: TimeDelta,
+    /// Text displayed when the alarm triggers.
+    ///
+    /// Corresponds to the `DESCRIPTION` property in iCalendar.
+    pub description: Option<String>,
+}
+
+impl VAlarm {
+    /// Creates an alarm displaying the text the given time before the start of the event.
+    pub fn display_before(before: TimeDelta, description: String) -> Self {
+        VAlarm {
+            action: "DISPLAY".to_string(),
+            trigger: -before,
+            description: Some(description),
+        }
+    }
+}
+
+impl SerializeToICal for VAlarm {
+    fn serialize_to_ical(&self, write: &mut dyn Write) -> eyre::Result<()> {
+        let mut w = ContentLineWriter::new(write);
+
+        w.begin("VALARM")?;
+        w.write_property("ACTION", &self.action)?;
+        w.write_property("TRIGGER", &self.trigger.to_ical_duration())?;
+        if let Some(description) = &self.description {
+            w.write_text("DESCRIPTION", description)?;
+        }
+        w.end("VALARM")?;
+
+        Ok(())
+    }
+}
+
+impl DeserializeFromICal for VAlarm {
+    fn from_component(component: &Component) -> eyre::Result<Self> {
+        let trigger = component.required("TRIGGER")?;
+
+        if trigger.get_param("VALUE") == Some("DATE-TIME") {
+            return Err(eyre!("absolute alarm triggers are not supported"));
+        }
+        if trigger.get_param("RELATED") == Some("END") {
+            return Err(eyre!("alarms relative to the end of the event are not supported"));
+        }
+
+        Ok(VAlarm {
+            action: component.required("ACTION")?.value.clone(),
+            trigger: parse_duration(&trigger.value)?,
+            description: component.property("DESCRIPTION").map(|p| p.text_value()),
+        })
+    }
+}
@@ -1,24 +1,415 @@
-use crate::ical::vevent::VEvent;
-use crate::ical::SerializeToICal;
+use crate::ical::contentline::{ContentLine, ContentLineWriter};
+use crate::ical::parser::{parse_single, Component};
+use crate::ical::vevent::{EventTime, VEvent};
+use crate::ical::vtimezone::VTimezone;
+use crate::ical::{DeserializeFromICal, SerializeToICal};
+use crate::time::timezones::get_timezone_by_name;
+use chrono::{NaiveDate, TimeDelta};
+use chrono_tz::Tz;
+use log::debug;
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct VCalendar {
     pub prod_id: String,
     pub version: String,
+    pub timezones: Vec<VTimezone>,
     pub events: Vec<VEvent>,
+    /// Any other properties, written as-is.
+    pub extra_properties: Vec<ContentLine>,
+}
+
+impl VCalendar {
+    /// Number of years after their start unbounded recurring events are covered by time zone definitions for.
+    pub const UNBOUNDED_RECURRENCE_YEARS: i64 = 5;
+
+    /// Parses iCalendar data containing a single `VCALENDAR`, interpreting floating times as UTC.
+    pub fn parse(input: &str) -> eyre::Result<Self> {
+        Self::parse_in(input, Tz::UTC)
+    }
+
+    /// Parses iCalendar data containing a single `VCALENDAR`,
+    /// interpreting floating times (with neither `Z` nor `TZID`) as local times in `floating_tz`.
+    pub fn parse_in(input: &str, floating_tz: Tz) -> eyre::Result<Self> {
+        let component: Component = parse_single(input, "VCALENDAR")?;
+        Self::from_component_in(&component, floating_tz)
+    }
+
+    /// Parses an iCalendar (.ics) file containing a single `VCALENDAR`, interpreting floating times as UTC.
+    pub fn parse_file(path: &Path) -> eyre::Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    /// Parses an iCalendar (.ics) file containing a single `VCALENDAR`,
+    /// interpreting floating times as local times in `floating_tz`.
+    pub fn parse_file_in(path: &Path, floating_tz: Tz) -> eyre::Result<Self> {
+        Self::parse_in(&std::fs::read_to_string(path)?, floating_tz)
+    }
+
+    /// Adds a [`VTimezone`] for every time zone referenced by the events' times,
+    /// unless the calendar already defines it.
+    ///
+    /// Each definition covers the span from the earliest to the latest event in that time zone,
+    /// including all occurrences of recurring events, their `RDATE`, `EXDATE` and `RECURRENCE-ID` times.
+    /// Unbounded recurrence rules are covered for [`VCalendar::UNBOUNDED_RECURRENCE_YEARS`] years.
+    pub fn add_referenced_timezones(&mut self) {
+        let mut spans: BTreeMap<&str, (Tz, NaiveDate, NaiveDate)> = BTreeMap::new();
+
+        for event in &self.events {
+            let EventTime::Timed { start, end } = &event.time else {
+                continue;
+            };
+
+            let mut times = vec![*start, *end];
+            let occurrences = event.occurrences(*start + TimeDelta::days(366 * Self::UNBOUNDED_RECURRENCE_YEARS));
+            if let (Some(first), Some(last)) = (occurrences.first(), occurrences.last()) {
+                times.extend([*first, *last + (*end - *start)]);
+            }
+            times.extend(&event.exception_dates);
+            times.extend(event.recurrence_id);
+
+            for time in times {
+                let tz = time.timezone();
+
+                // UTC times are written without TZID
+                if tz == Tz::UTC {
+                    continue;
+                }
+                let date = time.date_naive();
+
+                spans
+                    .entry(tz.name())
+                    .and_modify(|(_, from, to)| {
+                        *from = (*from).min(date);
+                        *to = (*to).max(date);
+                    })
+                    .or_insert((tz, date, date));
+            }
+        }
+
+        let timezones: Vec<_> = spans
+            .into_values()
+            .filter(|(tz, _, _)| !self.timezones.iter().any(|vtz| vtz.tz_id == tz.name()))
+            .map(|(tz, from, to)| VTimezone::for_span(tz, from, to))
+            .collect();
+
+        self.timezones.extend(timezones);
+    }
 }
 
 impl SerializeToICal for VCalendar {
     fn serialize_to_ical(&self, write: &mut dyn std::io::Write) -> eyre::Result<()> {
-        write!(write, "BEGIN:VCALENDAR\r\n")?;
-        write!(write, "PRODID:{}\r\n", self.prod_id)?;
-        write!(write, "VERSION:{}\r\n", self.version)?;
+        let mut w = ContentLineWriter::new(write);
+
+        w.begin("VCALENDAR")?;
+        w.write_text("PRODID", &self.prod_id)?;
+        w.write_text("VERSION", &self.version)?;
+        for property in &self.extra_properties {
+            w.write_line(property)?;
+        }
+
+        for timezone in &self.timezones {
+            timezone.serialize_to_ical(write)?;
+        }
 
         for event in &self.events {
             event.serialize_to_ical(write)?;
         }
 
-        write!(write, "END:VCALENDAR\r\n")?;
+        ContentLineWriter::new(write).end("VCALENDAR")?;
 
         Ok(())
     }
 }
+
+/// Returns a copy of the component with the `TZID` parameters of its properties replaced
+/// by the IANA names of the time zones they're resolved to,
+/// and with floating date-time properties given the `TZID` of `floating_tz`.
+fn with_resolved_tz_ids(component: &Component, resolved: &HashMap<&str, Tz>, floating_tz: Tz) -> Component {
+    let mut component = component.clone();
+
+    for property in &mut component.properties {
+        for (name, value) in &mut property.params {
+            if let Some(tz) = resolved.get(value.as_str()).filter(|_| name == "TZID") {
+                *value = tz.name().to_string();
+            }
+        }
+
+        let is_floating = matches!(
+            property.name.as_str(),
+            "DTSTART" | "DTEND" | "RECURRENCE-ID" | "EXDATE" | "RDATE"
+        ) && property.get_param("TZID").is_none()
+            && property.get_param("VALUE").is_none()
+            && !property.value.split(',').any(|value| value.ends_with('Z'));
+
+        if is_floating && floating_tz != Tz::UTC {
+            property.params.push(("TZID".to_string(), floating_tz.name().to_string()));
+        }
+    }
+
+    component
+}
+
+impl DeserializeFromICal for VCalendar {
+    /// Builds the calendar from a component, interpreting floating times as UTC.
+    fn from_component(component: &Component) -> eyre::Result<Self> {
+        Self::from_component_in(component, Tz::UTC)
+    }
+}
+
+impl VCalendar {
+    /// Builds the calendar from a component, interpreting floating times as local times in `floating_tz`.
+    ///
+    /// Time zones defined in `VTIMEZONE` components under names other than IANA ones
+    /// are resolved with [`VTimezone::to_tz`] for the times of the events.
+    fn from_component_in(component: &Component, floating_tz: Tz) -> eyre::Result<Self> {
+        let extra_properties = component
+            .properties
+            .iter()
+            .filter(|p| p.name != "PRODID" && p.name != "VERSION")
+            .cloned()
+            .collect();
+
+        for unknown in component
+            .components
+            .iter()
+            .filter(|c| c.name != "VTIMEZONE" && c.name != "VEVENT")
+        {
+            debug!("Skipping unsupported component {}", unknown.name);
+        }
+
+        let timezones: Vec<VTimezone> = component
+            .components("VTIMEZONE")
+            .map(VTimezone::from_component)
+            .collect::<eyre::Result<_>>()?;
+
+        let resolved: HashMap<&str, Tz> = timezones
+            .iter()
+            .filter(|vtz| get_timezone_by_name(&vtz.tz_id).is_none())
+            .filter_map(|vtz| Some((vtz.tz_id.as_str(), vtz.to_tz()?)))
+            .collect();
+
+        let events = component
+            .components("VEVENT")
+            .map(|event| VEvent::from_component(&with_resolved_tz_ids(event, &resolved, floating_tz)))
+            .collect::<eyre::Result<_>>()?;
+
+        Ok(VCalendar {
+            prod_id: component.required("PRODID")?.text_value(),
+            version: component.required("VERSION")?.text_value(),
+            timezones,
+            events,
+            extra_properties,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::date;
+    use crate::ical::recurrence::RecurrenceRule;
+    use crate::ical::valarm::VAlarm;
+    use crate::ical::vevent::{CalendarUser, EventStatus};
+    use chrono::{DateTime, NaiveDate, TimeDelta, TimeZone};
+
+    fn at(date: NaiveDate, hour: u32, minute: u32) -> DateTime<Tz> {
+        Tz::Europe__Warsaw
+            .from_local_datetime(&date.and_hms_opt(hour, minute, 0).unwrap())
+            .unwrap()
+    }
+
+    fn serialize(calendar: &VCalendar) -> String {
+        let mut buffer = Vec::new();
+        calendar.serialize_to_ical(&mut buffer).unwrap();
+        String::from_utf8(buffer).unwrap()
+    }
+
+    fn sample_calendar() -> VCalendar {
+        let created = Tz::UTC.with_ymd_and_hms(2025, 10, 1, 12, 0, 0).unwrap();
+
+        let mut calendar = VCalendar {
+            prod_id: "-//TheChilliPL//Planner//PL".to_string(),
+            version: "2.0".to_string(),
+            timezones: Vec::new(),
+            events: vec![
+                VEvent {
+                    transparent: true,
+                    ..VEvent::new(
+                        "day@example.com".to_string(),
+                        created,
+                        EventTime::full_day(date!(2025-10-20)),
+                        "Mon of week 1".to_string(),
+                    )
+                },
+                VEvent {
+                    description: Some(
+                        "Laboratorium: Algorytmy i struktury danych, część 1; grupa \\A\\\ndr inż. Jan Kowalski"
+                            .to_string(),
+                    ),
+                    location: Some("204/C3".to_string()),
+                    url: Some("https://example.com/aisd?lang=pl".to_string()),
+                    last_modified: Some(created),
+                    sequence: Some(2),
+                    status: Some(EventStatus::Confirmed),
+                    organizer: Some(CalendarUser {
+                        name: Some("dr inż. Jan Kowalski".to_string()),
+                        email: "jan.kowalski@example.com".to_string(),
+                    }),
+                    attendees: vec![CalendarUser {
+                        name: None,
+                        email: "anna.nowak@example.com".to_string(),
+                    }],
+                    recurrence: Some(RecurrenceRule::weekly(2, at(date!(2025-12-15), 10, 15))),
+                    exception_dates: vec![at(date!(2025-11-3), 10, 15), at(date!(2025-11-17), 10, 15)],
+                    recurrence_dates: vec![at(date!(2025-11-12), 10, 15)],
+                    categories: vec![
+                        "Laboratorium".to_string(),
+                        "Algorytmy i struktury danych, część 1".to_string(),
+                    ],
+                    color: Some("seagreen".to_string()),
+                    alarms: vec![VAlarm::display_before(
+                        TimeDelta::minutes(15),
+                        "🧪 AiSD".to_string(),
+                    )],
+                    extra_properties: vec![ContentLine::new("X-CUSTOM", "value;with:colons")
+                        .param("X-PARAM", "a, b")],
+                    ..VEvent::new(
+                        "lab@example.com".to_string(),
+                        created,
+                        EventTime::Timed {
+                            start: at(date!(2025-10-20), 10, 15),
+                            end: at(date!(2025-10-20), 12, 0),
+                        },
+                        "🧪 Algorytmy i struktury danych — laboratorium z bardzo długą nazwą".to_string(),
+                    )
+                },
+            ],
+            extra_properties: vec![ContentLine::text("X-WR-CALNAME", "Plan zajęć")],
+        };
+
+        calendar.add_referenced_timezones();
+        calendar
+    }
+
+    #[test]
+    fn round_trip() {
+        let calendar = sample_calendar();
+        let serialized = serialize(&calendar);
+
+        let parsed = VCalendar::parse(&serialized).unwrap();
+
+        assert_eq!(parsed, calendar);
+        assert_eq!(serialize(&parsed), serialized);
+    }
+
+    #[test]
+    fn parse_outlook_calendar() {
+        let input = "BEGIN:VCALENDAR\r\n\
+            VERSION:2.0\r\n\
+            PRODID:Microsoft Exchange Server 2010\r\n\
+            BEGIN:VTIMEZONE\r\n\
+            TZID:Customized Time Zone\r\n\
+            BEGIN:STANDARD\r\n\
+            DTSTART:16010101T030000\r\n\
+            TZOFFSETFROM:+0200\r\n\
+            TZOFFSETTO:+0100\r\n\
+            RRULE:FREQ=YEARLY;INTERVAL=1;BYDAY=-1SU;BYMONTH=10\r\n\
+            END:STANDARD\r\n\
+            BEGIN:DAYLIGHT\r\n\
+            DTSTART:16010101T020000\r\n\
+            TZOFFSETFROM:+0100\r\n\
+            TZOFFSETTO:+0200\r\n\
+            RRULE:FREQ=YEARLY;INTERVAL=1;BYDAY=-1SU;BYMONTH=3\r\n\
+            END:DAYLIGHT\r\n\
+            END:VTIMEZONE\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:1@example.com\r\n\
+            DTSTART;TZID=Central European Standard Time:20251020T081500\r\n\
+            DTEND;TZID=Customized Time Zone:20251020T100000\r\n\
+            SUMMARY:Lecture\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:2@example.com\r\n\
+            DTSTART;VALUE=DATE:20251222\r\n\
+            DTEND;VALUE=DATE:20260102\r\n\
+            SUMMARY:Christmas break\r\n\
+            END:VEVENT\r\n\
+            END:VCALENDAR\r\n";
+
+        let calendar = VCalendar::parse(input).unwrap();
+
+        let EventTime::Timed { start, end } = &calendar.events[0].time else {
+            panic!("expected a timed event");
+        };
+        assert_eq!(*start, at(date!(2025-10-20), 8, 15));
+        assert_eq!(end.to_rfc3339(), "2025-10-20T10:00:00+02:00");
+        assert_eq!(
+            calendar.events[1].time,
+            EventTime::FullDay {
+                start: date!(2025-12-22),
+                end: date!(2026-1-2),
+            }
+        );
+
+        let serialized = serialize(&calendar);
+        assert!(serialized.contains("DTEND;VALUE=DATE:20260102\r\n"));
+        let reparsed = VCalendar::parse(&serialized).unwrap();
+        assert_eq!(reparsed.events[0].time, calendar.events[0].time);
+        assert_eq!(reparsed.events[1].time, calendar.events[1].time);
+    }
+
+    #[test]
+    fn parse_foreign_calendar() {
+        let input = "BEGIN:VCALENDAR\r\n\
+            VERSION:2.0\r\n\
+            PRODID:-//Example//Other//EN\r\n\
+            METHOD:PUBLISH\r\n\
+            BEGIN:VEVENT\r\n\
+            UID:1@example.com\r\n\
+            DTSTAMP:20251001T120000Z\r\n\
+            DTSTART:20251020T061500Z\r\n\
+            DURATION:PT1H45M\r\n\
+            RRULE:FREQ=WEEKLY;BYDAY=MO,WE\r\n\
+            SUMMARY:Lecture\\, room 1\r\n\
+            BEGIN:VALARM\r\n\
+            ACTION:DISPLAY\r\n\
+            TRIGGER:-PT15M\r\n\
+            END:VALARM\r\n\
+            END:VEVENT\r\n\
+            BEGIN:VTODO\r\n\
+            UID:2@example.com\r\n\
+            END:VTODO\r\n\
+            END:VCALENDAR\r\n";
+
+        let calendar = VCalendar::parse(input).unwrap();
+
+        assert_eq!(calendar.prod_id, "-//Example//Other//EN");
+        assert_eq!(calendar.extra_properties, vec![ContentLine::new("METHOD", "PUBLISH")]);
+        assert_eq!(calendar.events.len(), 1);
+
+        let event = &calendar.events[0];
+        assert_eq!(event.summary, "Lecture, room 1");
+        assert_eq!(
+            event.time,
+            EventTime::Timed {
+                start: Tz::UTC.with_ymd_and_hms(2025, 10, 20, 6, 15, 0).unwrap(),
+                end: Tz::UTC.with_ymd_and_hms(2025, 10, 20, 8, 0, 0).unwrap(),
+            }
+        );
+        assert_eq!(event.recurrence, None);
+        assert_eq!(
+            event.alarms,
+            vec![VAlarm {
+                action: "DISPLAY".to_string(),
+                trigger: -TimeDelta::minutes(15),
+                description: None,
+            }]
+        );
+        assert_eq!(
+            event.extra_properties,
+            vec![ContentLine::new("RRULE", "FREQ=WEEKLY;BYDAY=MO,WE")]
+        );
+    }
+}
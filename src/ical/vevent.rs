@@ -1,11 +1,30 @@
-use crate::ical::SerializeToICal;
+use crate::ical::contentline::{ContentLine, ContentLineWriter};
+use crate::ical::parser::{
+    parse_date, parse_datetime, parse_datetime_property, parse_duration, Component,
+};
+use crate::ical::recurrence::RecurrenceRule;
+use crate::ical::valarm::VAlarm;
+use crate::ical::{DeserializeFromICal, SerializeToICal};
 use crate::time::timeext::TimeExt;
-use chrono::{DateTime, NaiveDate};
+use chrono::{DateTime, NaiveDate, NaiveTime, TimeDelta, Utc};
 use chrono_tz::Tz;
+use eyre::eyre;
+use log::warn;
 use std::io::Write;
 
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub enum EventTime {
-    FullDay(NaiveDate),
+    FullDay {
+        /// First day of the event.
+        ///
+        /// Corresponds to the `DTSTART` property (with `VALUE=DATE`) in iCalendar.
+        start: NaiveDate,
+        /// The day after the last day of the event.
+        ///
+        /// Corresponds to the `DTEND` property (with `VALUE=DATE`) in iCalendar,
+        /// which is only written for events lasting more than one day.
+        end: NaiveDate,
+    },
     Timed {
         start: DateTime<Tz>,
         /// Start time of the event.
@@ -13,11 +32,87 @@ pub enum EventTime {
         /// Corresponds to the `DTSTART` property in iCalendar.
         /// End time of the event.
         ///
-        /// Corresponds to the `DTEND` property in iCalendar.
+        /// Corresponds to the `DTEND` property in iCalendar,
+        /// which is omitted for events ending when they start, e.g. deadlines.
         end: DateTime<Tz>,
     }
 }
 
+impl EventTime {
+    /// Returns the time of an event taking the whole given day.
+    pub fn full_day(date: NaiveDate) -> Self {
+        EventTime::FullDay {
+            start: date,
+            end: date + TimeDelta::days(1),
+        }
+    }
+}
+
+/// Confirmation status of an event.
+///
+/// Corresponds to the `STATUS` property in iCalendar.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EventStatus {
+    Tentative,
+    Confirmed,
+    Cancelled,
+}
+
+impl EventStatus {
+    pub fn to_ical(self) -> &'static str {
+        match self {
+            EventStatus::Tentative => "TENTATIVE",
+            EventStatus::Confirmed => "CONFIRMED",
+            EventStatus::Cancelled => "CANCELLED",
+        }
+    }
+
+    pub fn from_ical(value: &str) -> Option<Self> {
+        match value {
+            "TENTATIVE" => Some(EventStatus::Tentative),
+            "CONFIRMED" => Some(EventStatus::Confirmed),
+            "CANCELLED" => Some(EventStatus::Cancelled),
+            _ => None,
+        }
+    }
+}
+
+/// A calendar user identified by an email address, e.g. the organizer or an attendee of an event.
+///
+/// See [RFC 5545, section 3.3.3](https://www.rfc-editor.org/rfc/rfc5545#section-3.3.3).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CalendarUser {
+    /// Common name of the user.
+    ///
+    /// Corresponds to the `CN` parameter in iCalendar.
+    pub name: Option<String>,
+    pub email: String,
+}
+
+impl CalendarUser {
+    fn to_ical_line(&self, property: &str) -> ContentLine {
+        let line = ContentLine::new(property, format!("mailto:{}", self.email));
+
+        match &self.name {
+            Some(name) => line.param("CN", name),
+            None => line,
+        }
+    }
+
+    fn from_ical_line(line: &ContentLine) -> Self {
+        let email = match line.value.get(..7) {
+            Some(scheme) if scheme.eq_ignore_ascii_case("mailto:") => &line.value[7..],
+            _ => &line.value,
+        };
+
+        CalendarUser {
+            name: line.get_param("CN").map(str::to_string),
+            email: email.to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
 pub struct VEvent {
     /// Unique identifier of the event
     ///
@@ -27,7 +122,25 @@ pub struct VEvent {
     ///
     /// Corresponds to the `DTSTAMP` property in iCalendar.
     pub created: DateTime<Tz>,
+    /// Timestamp of when the event was last modified.
+    ///
+    /// Corresponds to the `LAST-MODIFIED` property in iCalendar.
+    pub last_modified: Option<DateTime<Tz>>,
+    /// Revision number of the event, incremented on every significant change.
+    ///
+    /// Corresponds to the `SEQUENCE` property in iCalendar.
+    pub sequence: Option<u32>,
+    /// Corresponds to the `STATUS` property in iCalendar.
+    pub status: Option<EventStatus>,
+    /// Whether the event doesn't block time, e.g. in free/busy searches.
+    ///
+    /// Corresponds to `TRANSP:TRANSPARENT` in iCalendar.
+    pub transparent: bool,
     pub time: EventTime,
+    /// Original start time of the occurrence of a recurring event this event overrides.
+    ///
+    /// Corresponds to the `RECURRENCE-ID` property in iCalendar.
+    pub recurrence_id: Option<DateTime<Tz>>,
     /// Summary or title of the event.
     ///
     /// Corresponds to the `SUMMARY` property in iCalendar.
@@ -40,40 +153,326 @@ pub struct VEvent {
     ///
     /// Corresponds to the `LOCATION` property in iCalendar.
     pub location: Option<String>,
+    /// URL of a page associated with the event.
+    ///
+    /// Corresponds to the `URL` property in iCalendar.
+    pub url: Option<String>,
+    /// Organizer of the event.
+    ///
+    /// Corresponds to the `ORGANIZER` property in iCalendar.
+    pub organizer: Option<CalendarUser>,
+    /// Attendees of the event.
+    ///
+    /// Corresponds to the `ATTENDEE` properties in iCalendar.
+    pub attendees: Vec<CalendarUser>,
+    /// Rule by which the event repeats.
+    ///
+    /// Corresponds to the `RRULE` property in iCalendar.
+    pub recurrence: Option<RecurrenceRule>,
+    /// Start times of occurrences excluded from the recurrence rule.
+    ///
+    /// Corresponds to the `EXDATE` property in iCalendar.
+    pub exception_dates: Vec<DateTime<Tz>>,
+    /// Start times of additional occurrences not covered by the recurrence rule.
+    ///
+    /// Corresponds to the `RDATE` property in iCalendar.
+    pub recurrence_dates: Vec<DateTime<Tz>>,
+    /// Categories of the event.
+    ///
+    /// Corresponds to the `CATEGORIES` property in iCalendar.
+    pub categories: Vec<String>,
+    /// CSS3 color name of the event.
+    ///
+    /// Corresponds to the `COLOR` property, defined in
+    /// [RFC 7986, section 5.9](https://www.rfc-editor.org/rfc/rfc7986#section-5.9).
+    pub color: Option<String>,
+    /// Alarms (reminders) of the event.
+    pub alarms: Vec<VAlarm>,
+    /// Any other properties, written as-is.
+    pub extra_properties: Vec<ContentLine>,
+}
+
+impl VEvent {
+    /// Creates an event with only the required properties set.
+    pub fn new(uid: String, created: DateTime<Tz>, time: EventTime, summary: String) -> Self {
+        VEvent {
+            uid,
+            created,
+            last_modified: None,
+            sequence: None,
+            status: None,
+            transparent: false,
+            time,
+            recurrence_id: None,
+            summary,
+            description: None,
+            location: None,
+            url: None,
+            organizer: None,
+            attendees: Vec::new(),
+            recurrence: None,
+            exception_dates: Vec::new(),
+            recurrence_dates: Vec::new(),
+            categories: Vec::new(),
+            color: None,
+            alarms: Vec::new(),
+            extra_properties: Vec::new(),
+        }
+    }
+}
+
+impl VEvent {
+    /// Returns the key identifying the event among the ones with the same UID,
+    /// i.e. the UID alone, or followed by the `RECURRENCE-ID` for events overriding an occurrence.
+    pub fn instance_key(&self) -> String {
+        match &self.recurrence_id {
+            Some(recurrence_id) => format!("{}/{}", self.uid, recurrence_id.to_utc_stamp()),
+            None => self.uid.clone(),
+        }
+    }
+
+    /// Returns the start times of all occurrences of the event, in chronological order.
+    ///
+    /// Takes `RRULE`, `RDATE` and `EXDATE` into account.
+    /// Unbounded recurrence rules are expanded up to `limit`.
+    /// Full-day events occur at midnight UTC of their dates, like their recurrence rules are parsed.
+    pub fn occurrences(&self, limit: DateTime<Tz>) -> Vec<DateTime<Tz>> {
+        let start = match &self.time {
+            EventTime::Timed { start, .. } => *start,
+            EventTime::FullDay { start, .. } => start.and_time(NaiveTime::MIN).and_utc().with_timezone(&Tz::UTC),
+        };
+
+        let mut occurrences = match &self.recurrence {
+            Some(rule) => rule.expand(start, limit),
+            None => vec![start],
+        };
+
+        occurrences.extend(&self.recurrence_dates);
+        occurrences.retain(|occurrence| !self.exception_dates.contains(occurrence));
+        occurrences.sort();
+        occurrences.dedup();
+
+        occurrences
+    }
+}
+
+/// Returns a date-time property, in UTC if the time zone is UTC, or with `TZID` otherwise.
+fn datetime_to_ical_line(name: &str, dt: &DateTime<Tz>) -> ContentLine {
+    if dt.timezone() == Tz::UTC {
+        ContentLine::new(name, dt.to_utc_stamp())
+    } else {
+        ContentLine::new(name, dt.to_stamp()).param("TZID", dt.timezone().name())
+    }
 }
 
 impl SerializeToICal for VEvent {
     fn serialize_to_ical(&self, write: &mut dyn Write) -> eyre::Result<()> {
-        fn datetime_to_ical_string(dt: &DateTime<Tz>) -> String {
-            let tz = dt.timezone().name();
-            format!(";TZID={}:{}", tz, dt.to_stamp())
-        }
-
-        write!(write, "BEGIN:VEVENT\r\n")?;
-        write!(write, "UID:{}\r\n", self.uid)?;
-        write!(
-            write,
-            "DTSTAMP{}\r\n",
-            datetime_to_ical_string(&self.created)
-        )?;
+        fn datetimes_to_ical_lines(name: &str, dts: &[DateTime<Tz>]) -> Vec<ContentLine> {
+            let mut lines: Vec<ContentLine> = Vec::new();
+
+            for chunk in dts.chunk_by(|a, b| a.timezone() == b.timezone()) {
+                let mut line = datetime_to_ical_line(name, &chunk[0]);
+                for dt in &chunk[1..] {
+                    line.value.push(',');
+                    line.value.push_str(&datetime_to_ical_line(name, dt).value);
+                }
+                lines.push(line);
+            }
+
+            lines
+        }
+
+        let mut w = ContentLineWriter::new(write);
+
+        w.begin("VEVENT")?;
+        w.write_text("UID", &self.uid)?;
+        w.write_property("DTSTAMP", &self.created.to_utc_stamp())?;
+        if let Some(last_modified) = &self.last_modified {
+            w.write_property("LAST-MODIFIED", &last_modified.to_utc_stamp())?;
+        }
+        if let Some(sequence) = self.sequence {
+            w.write_property("SEQUENCE", &sequence.to_string())?;
+        }
+        if let Some(status) = self.status {
+            w.write_property("STATUS", status.to_ical())?;
+        }
+        if self.transparent {
+            w.write_property("TRANSP", "TRANSPARENT")?;
+        }
         match &self.time {
-            EventTime::FullDay(date) => {
-                write!(write, "DTSTART;VALUE=DATE:{}\r\n", date.format("%Y%m%d"))?;
+            EventTime::FullDay { start, end } => {
+                w.write_line(
+                    &ContentLine::new("DTSTART", start.format("%Y%m%d").to_string())
+                        .param("VALUE", "DATE"),
+                )?;
+                if *end > *start + TimeDelta::days(1) {
+                    w.write_line(
+                        &ContentLine::new("DTEND", end.format("%Y%m%d").to_string())
+                            .param("VALUE", "DATE"),
+                    )?;
+                }
             }
             EventTime::Timed { start, end } => {
-                write!(write, "DTSTART{}\r\n", datetime_to_ical_string(start))?;
-                write!(write, "DTEND{}\r\n", datetime_to_ical_string(end))?;
+                w.write_line(&datetime_to_ical_line("DTSTART", start))?;
+                // DTEND must be later than DTSTART, an event without it ends when it starts
+                if end != start {
+                    w.write_line(&datetime_to_ical_line("DTEND", end))?;
+                }
             }
         }
-        write!(write, "SUMMARY:{}\r\n", self.summary.replace("\n", "\\n"))?;
+        if let Some(recurrence_id) = &self.recurrence_id {
+            w.write_line(&datetime_to_ical_line("RECURRENCE-ID", recurrence_id))?;
+        }
+        if let Some(recurrence) = &self.recurrence {
+            w.write_property("RRULE", &recurrence.to_string())?;
+        }
+        for line in datetimes_to_ical_lines("EXDATE", &self.exception_dates) {
+            w.write_line(&line)?;
+        }
+        for line in datetimes_to_ical_lines("RDATE", &self.recurrence_dates) {
+            w.write_line(&line)?;
+        }
+        w.write_text("SUMMARY", &self.summary)?;
         if let Some(location) = &self.location {
-            write!(write, "LOCATION:{}\r\n", location.replace("\n", "\\n"))?;
+            w.write_text("LOCATION", location)?;
         }
         if let Some(description) = &self.description {
-            write!(write, "DESCRIPTION:{}\r\n", description.replace("\n", "\\n"))?;
+            w.write_text("DESCRIPTION", description)?;
+        }
+        if let Some(url) = &self.url {
+            w.write_property("URL", url)?;
+        }
+        if let Some(organizer) = &self.organizer {
+            w.write_line(&organizer.to_ical_line("ORGANIZER"))?;
+        }
+        for attendee in &self.attendees {
+            w.write_line(&attendee.to_ical_line("ATTENDEE"))?;
+        }
+        if !self.categories.is_empty() {
+            w.write_line(&ContentLine::text_list("CATEGORIES", &self.categories))?;
+        }
+        if let Some(color) = &self.color {
+            w.write_property("COLOR", color)?;
         }
-        write!(write, "END:VEVENT\r\n")?;
+        for property in &self.extra_properties {
+            w.write_line(property)?;
+        }
+        for alarm in &self.alarms {
+            alarm.serialize_to_ical(write)?;
+        }
+        ContentLineWriter::new(write).end("VEVENT")?;
 
         Ok(())
     }
 }
+
+impl DeserializeFromICal for VEvent {
+    fn from_component(component: &Component) -> eyre::Result<Self> {
+        let uid = component.required("UID")?.text_value();
+        let created = match component.property("DTSTAMP") {
+            Some(dtstamp) => parse_datetime_property(dtstamp)?,
+            None => Utc::now().with_timezone(&Tz::UTC),
+        };
+
+        let dtstart = component.required("DTSTART")?;
+        let time = if dtstart.get_param("VALUE") == Some("DATE") {
+            let start = parse_date(&dtstart.value)?;
+            let end = match (component.property("DTEND"), component.property("DURATION")) {
+                (Some(dtend), _) => parse_date(&dtend.value)?,
+                (None, Some(duration)) => start + TimeDelta::days(parse_duration(&duration.value)?.num_days()),
+                (None, None) => start,
+            };
+            // Events without an end, or ending before they start, take a single day
+            EventTime::FullDay {
+                start,
+                end: end.max(start + TimeDelta::days(1)),
+            }
+        } else {
+            let start = parse_datetime_property(dtstart)?;
+            let end = match (component.property("DTEND"), component.property("DURATION")) {
+                (Some(dtend), _) => parse_datetime_property(dtend)?,
+                (None, Some(duration)) => start + parse_duration(&duration.value)?,
+                (None, None) => start,
+            };
+            EventTime::Timed { start, end }
+        };
+
+        let mut event = VEvent::new(
+            uid,
+            created,
+            time,
+            component
+                .property("SUMMARY")
+                .map(|p| p.text_value())
+                .unwrap_or_default(),
+        );
+        event.description = component.property("DESCRIPTION").map(|p| p.text_value());
+        event.location = component.property("LOCATION").map(|p| p.text_value());
+
+        let start_tz = match &event.time {
+            EventTime::Timed { start, .. } => start.timezone(),
+            EventTime::FullDay { .. } => Tz::UTC,
+        };
+
+        for property in &component.properties {
+            match property.name.as_str() {
+                "UID" | "DTSTAMP" | "DTSTART" | "DTEND" | "DURATION" | "SUMMARY"
+                | "DESCRIPTION" | "LOCATION" => {}
+                "LAST-MODIFIED" => event.last_modified = Some(parse_datetime_property(property)?),
+                "RECURRENCE-ID" if property.get_param("VALUE").is_none() => {
+                    event.recurrence_id = Some(parse_datetime_property(property)?)
+                }
+                "SEQUENCE" => {
+                    event.sequence = Some(
+                        property
+                            .value
+                            .parse()
+                            .map_err(|_| eyre!("invalid SEQUENCE: {}", property.value))?,
+                    )
+                }
+                "STATUS" if EventStatus::from_ical(&property.value).is_some() => {
+                    event.status = EventStatus::from_ical(&property.value)
+                }
+                "TRANSP" if property.value == "TRANSPARENT" => event.transparent = true,
+                "TRANSP" if property.value == "OPAQUE" => {}
+                "CATEGORIES" => event.categories.extend(property.text_list_value()),
+                "COLOR" => event.color = Some(property.value.clone()),
+                "URL" => event.url = Some(property.value.clone()),
+                "ORGANIZER" => event.organizer = Some(CalendarUser::from_ical_line(property)),
+                "ATTENDEE" => event.attendees.push(CalendarUser::from_ical_line(property)),
+                "RRULE" if event.recurrence.is_none() => {
+                    match RecurrenceRule::parse(&property.value, start_tz)? {
+                        Some(rule) => event.recurrence = Some(rule),
+                        None => {
+                            warn!("Unsupported RRULE kept as-is: {}", property.value);
+                            event.extra_properties.push(property.clone());
+                        }
+                    }
+                }
+                "EXDATE" | "RDATE" if property.get_param("VALUE").is_none() => {
+                    let dates = property
+                        .value
+                        .split(',')
+                        .map(|value| parse_datetime(value, property.get_param("TZID")))
+                        .collect::<eyre::Result<Vec<_>>>()?;
+
+                    if property.name == "EXDATE" {
+                        event.exception_dates.extend(dates);
+                    } else {
+                        event.recurrence_dates.extend(dates);
+                    }
+                }
+                _ => event.extra_properties.push(property.clone()),
+            }
+        }
+
+        for alarm in component.components("VALARM") {
+            match VAlarm::from_component(alarm) {
+                Ok(alarm) => event.alarms.push(alarm),
+                Err(e) => warn!("Skipping alarm of {}: {}", event.uid, e),
+            }
+        }
+
+        Ok(event)
+    }
+}
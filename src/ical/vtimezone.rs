@@ -0,0 +1,393 @@
+use crate::ical::contentline::{ContentLine, ContentLineWriter};
+use crate::ical::parser::Component;
+use crate::ical::{DeserializeFromICal, SerializeToICal};
+use crate::time::timezones::{get_timezone_by_name, get_timezone_by_windows_name};
+use eyre::eyre;
+use chrono::{DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, TimeZone, Utc};
+use chrono_tz::{OffsetComponents, OffsetName, Tz, TZ_VARIANTS};
+use std::io::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ObservanceKind {
+    Standard,
+    Daylight,
+}
+
+impl ObservanceKind {
+    fn component_name(self) -> &'static str {
+        match self {
+            ObservanceKind::Standard => "STANDARD",
+            ObservanceKind::Daylight => "DAYLIGHT",
+        }
+    }
+}
+
+/// A single `STANDARD` or `DAYLIGHT` sub-component of a [`VTimezone`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Observance {
+    pub kind: ObservanceKind,
+    /// Local time at which the observance starts, relative to [`Observance::offset_from`].
+    ///
+    /// Corresponds to the `DTSTART` property in iCalendar.
+    pub start: NaiveDateTime,
+    /// UTC offset, in seconds, in use before the observance starts.
+    ///
+    /// Corresponds to the `TZOFFSETFROM` property in iCalendar.
+    pub offset_from: i32,
+    /// UTC offset, in seconds, in use during the observance.
+    ///
+    /// Corresponds to the `TZOFFSETTO` property in iCalendar.
+    pub offset_to: i32,
+    /// Abbreviated name of the observance, like `CEST`.
+    ///
+    /// Corresponds to the `TZNAME` property in iCalendar.
+    pub name: Option<String>,
+}
+
+/// Time zone definition, referenced by `TZID` parameters of other components.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VTimezone {
+    /// Identifier of the time zone, e.g. `Europe/Warsaw`.
+    ///
+    /// Corresponds to the `TZID` property in iCalendar.
+    pub tz_id: String,
+    pub observances: Vec<Observance>,
+}
+
+/// UTC offset and DST status of a time zone at a given instant.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct OffsetState {
+    offset: i32,
+    is_dst: bool,
+}
+
+impl OffsetState {
+    fn at(tz: Tz, instant: DateTime<Utc>) -> Self {
+        let offset = tz.offset_from_utc_datetime(&instant.naive_utc());
+        let total = offset.base_utc_offset() + offset.dst_offset();
+
+        OffsetState {
+            offset: total.num_seconds() as i32,
+            is_dst: !offset.dst_offset().is_zero(),
+        }
+    }
+}
+
+impl VTimezone {
+    /// Builds a time zone definition from the `chrono_tz` database,
+    /// covering all transitions between the two dates (inclusive).
+    pub fn for_span(tz: Tz, from: NaiveDate, to: NaiveDate) -> Self {
+        let span_start = from.and_hms_opt(0, 0, 0).unwrap().and_utc() - Duration::days(1);
+        let span_end = to.and_hms_opt(0, 0, 0).unwrap().and_utc() + Duration::days(2);
+
+        let abbreviation = |instant: DateTime<Utc>| {
+            tz.offset_from_utc_datetime(&instant.naive_utc())
+                .abbreviation()
+                .map(str::to_string)
+        };
+
+        let initial = OffsetState::at(tz, span_start);
+        let mut observances = vec![Observance {
+            kind: Self::kind_of(initial),
+            start: span_start.naive_utc() + Duration::seconds(initial.offset as i64),
+            offset_from: initial.offset,
+            offset_to: initial.offset,
+            name: abbreviation(span_start),
+        }];
+
+        let mut previous = initial;
+        let mut cursor = span_start;
+
+        while cursor < span_end {
+            let next = cursor + Duration::days(1);
+            let state = OffsetState::at(tz, next);
+
+            if state != previous {
+                let transition = Self::find_transition(tz, cursor, next, previous);
+
+                observances.push(Observance {
+                    kind: Self::kind_of(state),
+                    start: transition.naive_utc() + Duration::seconds(previous.offset as i64),
+                    offset_from: previous.offset,
+                    offset_to: state.offset,
+                    name: abbreviation(transition),
+                });
+
+                previous = state;
+            }
+
+            cursor = next;
+        }
+
+        VTimezone {
+            tz_id: tz.name().to_string(),
+            observances,
+        }
+    }
+
+    /// Resolves the definition to a time zone of the `chrono_tz` database.
+    ///
+    /// The `TZID` is looked up as an IANA or a Windows time zone name first.
+    /// Otherwise, the first time zone observing the same standard and daylight offsets is used,
+    /// in the year of the latest observance, or the current year for definitions
+    /// repeating since before 1970, like the ones exported by Outlook.
+    pub fn to_tz(&self) -> Option<Tz> {
+        if let Some(tz) = get_timezone_by_name(&self.tz_id).or_else(|| get_timezone_by_windows_name(&self.tz_id)) {
+            return Some(tz);
+        }
+
+        let latest_offset = |kind| {
+            self.observances
+                .iter()
+                .filter(|o| o.kind == kind)
+                .max_by_key(|o| o.start)
+                .map(|o| o.offset_to)
+        };
+        let standard = latest_offset(ObservanceKind::Standard)?;
+        let daylight = latest_offset(ObservanceKind::Daylight).unwrap_or(standard);
+
+        let year = match self.observances.iter().map(|o| o.start.year()).max()? {
+            year if year < 1970 => Utc::now().year(),
+            year => year,
+        };
+        let offsets_in = |tz: Tz| {
+            let [winter, summer] = [1, 7].map(|month| {
+                let instant = NaiveDate::from_ymd_opt(year, month, 1).unwrap().and_hms_opt(0, 0, 0).unwrap();
+                OffsetState::at(tz, instant.and_utc()).offset
+            });
+            (winter.min(summer), winter.max(summer))
+        };
+
+        TZ_VARIANTS
+            .iter()
+            .copied()
+            .find(|tz| offsets_in(*tz) == (standard.min(daylight), standard.max(daylight)))
+    }
+
+    fn kind_of(state: OffsetState) -> ObservanceKind {
+        if state.is_dst {
+            ObservanceKind::Daylight
+        } else {
+            ObservanceKind::Standard
+        }
+    }
+
+    /// Finds the first instant in `(low, high]` at which the offset differs from `before`,
+    /// with a precision of one second.
+    fn find_transition(
+        tz: Tz,
+        low: DateTime<Utc>,
+        high: DateTime<Utc>,
+        before: OffsetState,
+    ) -> DateTime<Utc> {
+        let (mut low, mut high) = (low.timestamp(), high.timestamp());
+
+        while high - low > 1 {
+            let middle = low + (high - low) / 2;
+            let instant = DateTime::from_timestamp(middle, 0).unwrap();
+
+            if OffsetState::at(tz, instant) == before {
+                low = middle;
+            } else {
+                high = middle;
+            }
+        }
+
+        DateTime::from_timestamp(high, 0).unwrap()
+    }
+}
+
+/// Parses an iCalendar `UTC-OFFSET` value, e.g. `+0200`, into seconds.
+fn parse_utc_offset(value: &str) -> eyre::Result<i32> {
+    let invalid = || eyre!("invalid UTC offset: {}", value);
+
+    let sign = match value.chars().next() {
+        Some('+') => 1,
+        Some('-') => -1,
+        _ => return Err(invalid()),
+    };
+    let digits = &value[1..];
+
+    if !matches!(digits.len(), 4 | 6) || !digits.bytes().all(|b| b.is_ascii_digit()) {
+        return Err(invalid());
+    }
+
+    let part = |range: std::ops::Range<usize>| digits.get(range).map_or(Ok(0), str::parse::<i32>);
+    let seconds = part(0..2)? * 3600 + part(2..4)? * 60 + part(4..6)?;
+
+    Ok(sign * seconds)
+}
+
+/// Formats a UTC offset in seconds as an iCalendar `UTC-OFFSET` value, e.g. `+0200`.
+fn format_utc_offset(offset: i32) -> String {
+    let sign = if offset < 0 { '-' } else { '+' };
+    let offset = offset.abs();
+    let (hours, minutes, seconds) = (offset / 3600, offset % 3600 / 60, offset % 60);
+
+    if seconds != 0 {
+        format!("{}{:02}{:02}{:02}", sign, hours, minutes, seconds)
+    } else {
+        format!("{}{:02}{:02}", sign, hours, minutes)
+    }
+}
+
+impl SerializeToICal for VTimezone {
+    fn serialize_to_ical(&self, write: &mut dyn Write) -> eyre::Result<()> {
+        let mut w = ContentLineWriter::new(write);
+
+        w.begin("VTIMEZONE")?;
+        w.write_text("TZID", &self.tz_id)?;
+
+        for observance in &self.observances {
+            let component = observance.kind.component_name();
+
+            w.begin(component)?;
+            w.write_line(&ContentLine::new(
+                "DTSTART",
+                observance.start.format("%Y%m%dT%H%M%S").to_string(),
+            ))?;
+            w.write_property("TZOFFSETFROM", &format_utc_offset(observance.offset_from))?;
+            w.write_property("TZOFFSETTO", &format_utc_offset(observance.offset_to))?;
+            if let Some(name) = &observance.name {
+                w.write_text("TZNAME", name)?;
+            }
+            w.end(component)?;
+        }
+
+        w.end("VTIMEZONE")?;
+
+        Ok(())
+    }
+}
+
+impl DeserializeFromICal for VTimezone {
+    fn from_component(component: &Component) -> eyre::Result<Self> {
+        let observances = component
+            .components
+            .iter()
+            .filter_map(|c| {
+                let kind = match c.name.as_str() {
+                    "STANDARD" => ObservanceKind::Standard,
+                    "DAYLIGHT" => ObservanceKind::Daylight,
+                    _ => return None,
+                };
+
+                Some((kind, c))
+            })
+            .map(|(kind, c)| {
+                Ok(Observance {
+                    kind,
+                    start: NaiveDateTime::parse_from_str(
+                        &c.required("DTSTART")?.value,
+                        "%Y%m%dT%H%M%S",
+                    )?,
+                    offset_from: parse_utc_offset(&c.required("TZOFFSETFROM")?.value)?,
+                    offset_to: parse_utc_offset(&c.required("TZOFFSETTO")?.value)?,
+                    name: c.property("TZNAME").map(|p| p.text_value()),
+                })
+            })
+            .collect::<eyre::Result<_>>()?;
+
+        Ok(VTimezone {
+            tz_id: component.required("TZID")?.text_value(),
+            observances,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::date;
+    use chrono::Offset;
+
+    #[test]
+    fn warsaw_winter_semester() {
+        let vtimezone = VTimezone::for_span(Tz::Europe__Warsaw, date!(2025-10-1), date!(2026-3-31));
+
+        let transitions: Vec<_> = vtimezone
+            .observances
+            .iter()
+            .map(|o| (o.kind, o.start, o.offset_from, o.offset_to, o.name.as_deref()))
+            .collect();
+
+        assert_eq!(
+            transitions,
+            vec![
+                (
+                    ObservanceKind::Daylight,
+                    date!(2025-9-30).and_hms_opt(2, 0, 0).unwrap(),
+                    7200,
+                    7200,
+                    Some("CEST"),
+                ),
+                (
+                    ObservanceKind::Standard,
+                    date!(2025-10-26).and_hms_opt(3, 0, 0).unwrap(),
+                    7200,
+                    3600,
+                    Some("CET"),
+                ),
+                (
+                    ObservanceKind::Daylight,
+                    date!(2026-3-29).and_hms_opt(2, 0, 0).unwrap(),
+                    3600,
+                    7200,
+                    Some("CEST"),
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn resolve_foreign_definitions() {
+        let observance = |kind, offset_from, offset_to| Observance {
+            kind,
+            start: date!(1601-1-1).and_hms_opt(3, 0, 0).unwrap(),
+            offset_from,
+            offset_to,
+            name: None,
+        };
+        let definition = |tz_id: &str| VTimezone {
+            tz_id: tz_id.to_string(),
+            observances: vec![
+                observance(ObservanceKind::Standard, 7200, 3600),
+                observance(ObservanceKind::Daylight, 3600, 7200),
+            ],
+        };
+
+        assert_eq!(definition("Europe/Warsaw").to_tz(), Some(Tz::Europe__Warsaw));
+        assert_eq!(definition("Central European Standard Time").to_tz(), Some(Tz::Europe__Warsaw));
+
+        let custom = definition("Customized Time Zone").to_tz().unwrap();
+        let summer = custom.with_ymd_and_hms(2025, 7, 1, 12, 0, 0).unwrap();
+        assert_eq!(summer.offset().fix().local_minus_utc(), 7200);
+
+        let nowhere = VTimezone {
+            observances: vec![observance(ObservanceKind::Standard, 0, 1234)],
+            ..definition("Nowhere")
+        };
+        assert_eq!(nowhere.to_tz(), None);
+    }
+
+    #[test]
+    fn serialize_without_transitions() {
+        let vtimezone = VTimezone::for_span(Tz::UTC, date!(2025-1-1), date!(2025-12-31));
+
+        let mut buffer = Vec::new();
+        vtimezone.serialize_to_ical(&mut buffer).unwrap();
+
+        assert_eq!(
+            String::from_utf8(buffer).unwrap(),
+            "BEGIN:VTIMEZONE\r\n\
+            TZID:UTC\r\n\
+            BEGIN:STANDARD\r\n\
+            DTSTART:20241231T000000\r\n\
+            TZOFFSETFROM:+0000\r\n\
+            TZOFFSETTO:+0000\r\n\
+            TZNAME:UTC\r\n\
+            END:STANDARD\r\n\
+            END:VTIMEZONE\r\n"
+        );
+    }
+}
@@ -1,19 +1,31 @@
-use crate::calendar::schedule::Schedule;
+use crate::calendar::events::Event;
+use crate::calendar::schedule::{ICalOptions, Schedule};
+use crate::calendar::template::Template;
+use crate::ical::vcalendar::VCalendar;
 use crate::ical::SerializeToICal;
 use clap::{Parser, Subcommand};
-use eyre::{eyre, OptionExt};
+use eyre::eyre;
 use log::{debug, info, LevelFilter};
 use qolor::color::BasicColor::Green;
 use qolor::shorthands::Formattable;
 use std::fs::File;
+use std::io::Write;
 use std::num::NonZero;
 use std::path::PathBuf;
 use chrono::{Local, NaiveDate, Weekday};
 use crate::time::timeext::TimeDeltaExt;
 
+mod agenda;
+mod caldav;
 mod calendar;
+mod export;
+mod http;
 mod ical;
+mod server;
+mod status;
 mod time;
+mod timetable;
+mod week_view;
 
 #[derive(Parser, Debug)]
 #[command(version, about)]
@@ -22,6 +34,70 @@ struct Args {
     command: Commands,
 }
 
+/// Options overriding the templates of class events from the schedule file.
+#[derive(clap::Args, Debug)]
+struct TemplateArgs {
+    /// Template of the event titles, e.g. "{type} {short} ({room})".
+    ///
+    /// Placeholders of class, subject, teacher and location fields are written in braces,
+    /// see the schedule schema for the full list.
+    #[arg(long, value_name = "TEMPLATE")]
+    summary_template: Option<String>,
+    /// Template of the event descriptions.
+    #[arg(long, value_name = "TEMPLATE")]
+    description_template: Option<String>,
+    /// Template of the event locations.
+    #[arg(long, value_name = "TEMPLATE")]
+    location_template: Option<String>,
+}
+
+impl TemplateArgs {
+    /// Replaces the templates of the schedule with the ones given on the command line.
+    fn apply(&self, schedule: &mut Schedule) -> eyre::Result<()> {
+        let templates = &mut schedule.templates;
+        for (arg, template) in [
+            (&self.summary_template, &mut templates.summary),
+            (&self.description_template, &mut templates.description),
+            (&self.location_template, &mut templates.location),
+        ] {
+            if let Some(text) = arg {
+                *template = Template::parse(text)?;
+            }
+        }
+
+        templates.check()
+    }
+}
+
+/// Options of the `now` and `next` commands.
+#[derive(clap::Args, Debug)]
+struct StatusArgs {
+    /// Path to the schedule .json file
+    #[arg(short, long, value_name = "SCHEDULE_PATH", env = "PLANNER_SCHEDULE_PATH")]
+    path: PathBuf,
+    /// Format of the output.
+    #[arg(short, long, default_value = "text")]
+    format: status::StatusFormat,
+    #[command(flatten)]
+    templates: TemplateArgs,
+}
+
+impl StatusArgs {
+    /// Prints the status rendered by `render` and exits with the code of its state.
+    fn run(
+        &self,
+        render: fn(&Schedule, &status::Status, status::StatusFormat) -> eyre::Result<String>,
+    ) -> eyre::Result<()> {
+        let mut schedule: Schedule = Schedule::load(&self.path)?;
+        self.templates.apply(&mut schedule)?;
+
+        let status = status::Status::at(&schedule, Local::now().naive_local());
+        println!("{}", render(&schedule, &status, self.format)?);
+
+        std::process::exit(status.state.exit_code());
+    }
+}
+
 #[derive(Subcommand, Debug)]
 enum Commands {
     /// Shows the plan for the day.
@@ -40,6 +116,59 @@ enum Commands {
         /// By default, uses today's date.
         #[arg(value_name = "DATE")]
         date: Option<String>,
+        /// Show the whole week as a grid instead of a single day.
+        ///
+        /// Can be the week number, "this" or "next". By default, uses the current week.
+        #[arg(
+            short,
+            long,
+            value_name = "WEEK",
+            num_args = 0..=1,
+            default_missing_value = "this",
+            conflicts_with = "date"
+        )]
+        week: Option<String>,
+        /// Width of the week grid, in terminal columns.
+        ///
+        /// Narrower grids are shown as a list of days instead.
+        /// Defaults to the width of the terminal, the COLUMNS environment variable, or 80.
+        #[arg(long)]
+        width: Option<usize>,
+        #[command(flatten)]
+        templates: TemplateArgs,
+    },
+    /// Lists the classes on every day of a date range.
+    Agenda {
+        /// Path to the schedule .json file
+        #[arg(short, long, value_name = "SCHEDULE_PATH", env = "PLANNER_SCHEDULE_PATH")]
+        path: PathBuf,
+        /// The first date of the range, in any format accepted by `show`.
+        ///
+        /// By default, uses today's date.
+        #[arg(long, value_name = "DATE", default_value = "today")]
+        from: String,
+        /// The last date of the range, in any format accepted by `show`.
+        ///
+        /// By default, the range spans a week.
+        #[arg(long, value_name = "DATE")]
+        to: Option<String>,
+        #[command(flatten)]
+        templates: TemplateArgs,
+    },
+    /// Shows the ongoing class and the time left until its end.
+    ///
+    /// Exits with code 0 during a class, 10 during a break between classes and 11 when there are no more
+    /// classes today.
+    Now {
+        #[command(flatten)]
+        status: StatusArgs,
+    },
+    /// Shows the next class and the time until it starts, looking ahead through the whole schedule.
+    ///
+    /// Exits with the same codes as `now`.
+    Next {
+        #[command(flatten)]
+        status: StatusArgs,
     },
     /// Generates an iCal (.ics) file of the specified schedule.
     Generate {
@@ -51,7 +180,128 @@ enum Commands {
         /// By default, uses the schedule .json path with .json replaced with `.ics`.
         #[arg(short, long, value_name = "OUTPUT_PATH")]
         output: Option<PathBuf>,
+        /// Generate a single recurring event per class instead of one event per occurrence.
+        #[arg(short, long)]
+        recurring: bool,
+        /// Don't track changes between generations.
+        ///
+        /// By default, the revisions of all events are stored next to the output,
+        /// with .ics replaced with `.state.json`, so that only changed events get a new `SEQUENCE`,
+        /// and events removed from the schedule are cancelled.
+        #[arg(long)]
+        no_state: bool,
+        #[command(flatten)]
+        templates: TemplateArgs,
+    },
+    /// Exports all occurrences of the classes of the specified schedule as a table.
+    Export {
+        /// Path to the schedule .json file
+        #[arg(value_name = "SCHEDULE_PATH", env = "PLANNER_SCHEDULE_PATH")]
+        path: PathBuf,
+        /// Format of the table.
+        #[arg(short, long)]
+        format: export::ExportFormat,
+        /// Path at which the table will be saved.
+        ///
+        /// By default, the table is written to the standard output.
+        #[arg(short, long, value_name = "OUTPUT_PATH")]
+        output: Option<PathBuf>,
+    },
+    /// Renders a printable weekly timetable of the specified schedule.
+    Timetable {
+        /// Path to the schedule .json file
+        #[arg(value_name = "SCHEDULE_PATH", env = "PLANNER_SCHEDULE_PATH")]
+        path: PathBuf,
+        /// Format of the timetable.
+        #[arg(short, long, default_value = "html")]
+        format: timetable::TimetableFormat,
+        /// Path at which the timetable will be saved.
+        ///
+        /// By default, uses the schedule .json path with .json replaced with `.html` or `.svg`.
+        #[arg(short, long, value_name = "OUTPUT_PATH")]
+        output: Option<PathBuf>,
+    },
+    /// Prints the weeks table of the specified schedule, e.g. to verify the one generated from semester rules.
+    Weeks {
+        /// Path to the schedule .json file
+        #[arg(value_name = "SCHEDULE_PATH", env = "PLANNER_SCHEDULE_PATH")]
+        path: PathBuf,
+        /// Print the table as JSON, in the format of the `weeks` property of the schedule.
+        #[arg(long)]
+        json: bool,
     },
+    /// Converts an iCal (.ics) file with weekly classes into a schedule .json file.
+    ImportIcs {
+        /// Path to the .ics file to import
+        #[arg(value_name = "ICS_PATH")]
+        path: PathBuf,
+        /// Path at which the schedule .json file will be saved.
+        ///
+        /// By default, uses the .ics path with .ics replaced with `.json`.
+        #[arg(short, long, value_name = "OUTPUT_PATH")]
+        output: Option<PathBuf>,
+        /// Overwrite the output file if it already exists.
+        #[arg(short, long)]
+        force: bool,
+    },
+    /// Serves the specified schedules as iCal feeds over HTTP.
+    ///
+    /// Each schedule is available at /<file name without .json>.ics,
+    /// optionally filtered with the `type`, `subject` and `teacher` query parameters,
+    /// e.g. /group-1.ics?type=lecture,lab.
+    /// Schedule files are reloaded whenever they change.
+    Serve {
+        /// Paths to the schedule .json files
+        #[arg(value_name = "SCHEDULE_PATH", required = true)]
+        paths: Vec<PathBuf>,
+        /// Address to listen on.
+        #[arg(short, long, default_value = "127.0.0.1:8080")]
+        address: String,
+        /// Generate a single recurring event per class by default, unless the `recurring` query parameter is false.
+        #[arg(short, long)]
+        recurring: bool,
+    },
+    /// Uploads the events of the specified schedule to a CalDAV calendar.
+    ///
+    /// Each event is stored as a separate resource named after its UID.
+    /// Changed events are updated, and events no longer in the schedule are deleted.
+    Sync {
+        /// Path to the schedule .json file
+        #[arg(value_name = "SCHEDULE_PATH", env = "PLANNER_SCHEDULE_PATH")]
+        path: PathBuf,
+        /// URL of the CalDAV calendar collection.
+        #[arg(long, value_name = "URL")]
+        caldav: String,
+        /// User name for basic authentication.
+        #[arg(short, long, env = "PLANNER_CALDAV_USER")]
+        user: Option<String>,
+        /// Password for basic authentication.
+        #[arg(long, env = "PLANNER_CALDAV_PASSWORD", hide_env_values = true)]
+        password: Option<String>,
+        /// Path of the file storing the state of the synchronization.
+        ///
+        /// By default, uses the schedule .json path with .json replaced with `.caldav.json`.
+        #[arg(long, value_name = "STATE_PATH")]
+        state: Option<PathBuf>,
+        /// Generate a single recurring event per class instead of one event per occurrence.
+        #[arg(short, long)]
+        recurring: bool,
+        #[command(flatten)]
+        templates: TemplateArgs,
+    },
+}
+
+/// Prints events other than classes, with their time and location.
+fn print_events(schedule: &Schedule, events: &[&Event]) {
+    for event in events {
+        let mut details = event.span_label();
+        if let Some(location) = &event.location {
+            details = format!("{}, {}", details, location);
+        }
+
+        let title = format!("{} {}\n", event.kind.to_emoji(), schedule.event_title(event));
+        println!("{}", (title.fg(event.kind.to_color()).bold() + format!("    {}", details).dim()).to_ansi());
+    }
 }
 
 fn date_to_triple(date: NaiveDate, schedule: &Schedule)
@@ -60,34 +310,16 @@ fn date_to_triple(date: NaiveDate, schedule: &Schedule)
     Ok((week_no, weekday, date))
 }
 
-fn parse_date(input: &str, schedule: &Schedule)
-    -> eyre::Result<(NonZero<usize>, Weekday, NaiveDate)> {
+/// Parses a date given as `today` (or a similar word), `W<week>D<day>` or `YYYY-MM-DD`.
+///
+/// The date doesn't need to be a day of the schedule.
+fn parse_naive_date(input: &str, schedule: &Schedule) -> eyre::Result<NaiveDate> {
     match input {
-        "today" => {
-            let date = Local::now().date_naive();
-
-            date_to_triple(date, schedule)
-        }
-        "tomorrow" => {
-            let date = Local::now().date_naive() + chrono::Duration::days(1);
-
-            date_to_triple(date, schedule)
-        }
-        "yesterday" => {
-            let date = Local::now().date_naive() - chrono::Duration::days(1);
-
-            date_to_triple(date, schedule)
-        }
-        "ereyesterday" => {
-            let date = Local::now().date_naive() - chrono::Duration::days(2);
-
-            date_to_triple(date, schedule)
-        }
-        "overmorrow" => {
-            let date = Local::now().date_naive() + chrono::Duration::days(2);
-
-            date_to_triple(date, schedule)
-        }
+        "today" => Ok(Local::now().date_naive()),
+        "tomorrow" => Ok(Local::now().date_naive() + chrono::Duration::days(1)),
+        "yesterday" => Ok(Local::now().date_naive() - chrono::Duration::days(1)),
+        "ereyesterday" => Ok(Local::now().date_naive() - chrono::Duration::days(2)),
+        "overmorrow" => Ok(Local::now().date_naive() + chrono::Duration::days(2)),
         _ if input.starts_with('W') && input.contains('D') => {
             let parts: Vec<&str> = input[1..].split('D').collect();
 
@@ -98,9 +330,9 @@ fn parse_date(input: &str, schedule: &Schedule)
             let week_no: usize = parts[0].parse().map_err(|_| eyre!("Invalid week number in date: {}", input))?;
             let day_no: u32 = parts[1].parse().map_err(|_| eyre!("Invalid day number in date: {}", input))?;
 
-            if week_no == 0 || day_no == 0 || day_no > 5 {
-                return Err(eyre!("Week and day numbers must be positive and day must be <= 5: \
-                {}", input));
+            if week_no == 0 || day_no == 0 || day_no as usize > schedule.days.len() {
+                return Err(eyre!("Week and day numbers must be positive and day must be <= {}: \
+                {}", schedule.days.len(), input));
             }
 
             let week_index = week_no - 1;
@@ -110,17 +342,11 @@ fn parse_date(input: &str, schedule: &Schedule)
                 return Err(eyre!("Week number out of range: {}", input));
             }
 
-            let date = schedule.weeks[week_index][day_index as usize];
-
-            date_to_triple(date, schedule)
-        }
-        _ => {
-            if let Ok(date) = NaiveDate::parse_from_str(input, "%Y-%m-%d") {
-                return date_to_triple(date, schedule);
-            }
-
-            Err(eyre!("Failed to parse date: {}", input))
+            schedule.weeks[week_index][day_index as usize]
+                .ok_or_else(|| eyre!("Day {} doesn't occur in week {}", day_no, week_no))
         }
+        _ => NaiveDate::parse_from_str(input, "%Y-%m-%d")
+            .map_err(|_| eyre!("Failed to parse date: {}", input)),
     }
 }
 
@@ -134,16 +360,35 @@ fn main() -> eyre::Result<()> {
     let args = Args::parse();
 
     match args.command {
-        Commands::Show { path, date } => {
-            let schedule: Schedule = serde_json::from_reader(File::open(&path)?)?;
-
-            let (week_no, weekday, date) = match date {
-                Some(d) => parse_date(&d, &schedule),
-                None => {
-                    let date = Local::now().date_naive();
-                    date_to_triple(date, &schedule)
+        Commands::Show { path, date, week, width, templates } => {
+            let mut schedule = Schedule::load(&path)?;
+            templates.apply(&mut schedule)?;
+
+            if let Some(week) = week {
+                let now = Local::now().naive_local();
+                let week = week_view::parse_week(&week, &schedule, now.date())?;
+
+                let width = width.unwrap_or_else(week_view::terminal_width);
+                println!("{}", week_view::render_week(&schedule, week, now, width)?);
+                return Ok(());
+            }
+
+            let date = match date {
+                Some(d) => parse_naive_date(&d, &schedule)?,
+                None => Local::now().date_naive(),
+            };
+            let events = schedule.events_on(date);
+
+            let (week_no, weekday, date) = match date_to_triple(date, &schedule) {
+                Ok(triple) => triple,
+                // Days outside the schedule may still have events, e.g. exams after the semester
+                Err(_) if !events.is_empty() => {
+                    println!("{}", format!(" - {} - ", date).bg(Green).to_ansi());
+                    print_events(&schedule, &events);
+                    return Ok(());
                 }
-            }?;
+                Err(e) => return Err(e),
+            };
 
             let is_today = date == Local::now().date_naive();
 
@@ -154,16 +399,17 @@ fn main() -> eyre::Result<()> {
                     .to_ansi()
             );
 
-            let classes = schedule.get_classes_on(week_no, weekday).collect::<Vec<_>>();
+            print_events(&schedule, &events);
 
-            let time_now = Local::now().time();
+            let mut classes = schedule.get_classes_on(week_no, weekday).collect::<Vec<_>>();
+            classes.sort_by_key(|class| class.time.start);
 
-            if is_today {
-                if classes.is_empty() {
-                    println!("{}", "You have no classes today!".dim().to_ansi());
-                    return Ok(());
-                }
+            let time_now = Local::now().time();
 
+            if is_today && classes.is_empty() {
+                // Cancelled classes are still listed below
+                println!("{}", "You have no classes today!".dim().to_ansi());
+            } else if let (true, Some(first)) = (is_today, classes.first()) {
                 println!(
                     "{}",
                     format!("You have {} classes today:", classes.len())
@@ -171,7 +417,7 @@ fn main() -> eyre::Result<()> {
                         .to_ansi()
                 );
 
-                let classes_start_at = classes.first().unwrap().time.start;
+                let classes_start_at = first.time.start;
 
                 if classes_start_at > time_now {
                     let remaining = classes_start_at - time_now;
@@ -183,31 +429,55 @@ fn main() -> eyre::Result<()> {
             }
 
             for class in &classes {
-                let subject = schedule
-                    .subjects
-                    .get(&class.subject)
-                    .ok_or_eyre("subject name not found")?;
+                let context = schedule.class_context(class)?;
 
-                let mut first_line = format!("{} {}\n", class.class_type.to_emoji(), subject.get_short_or_name())
+                let mut first_line = format!("{}\n", context.render(&schedule.templates.summary))
                     .fg(class.class_type.to_color());
 
                 if is_today {
                     if class.time.end < time_now {
-                        debug!("Class {} has already ended", subject.name);
+                        debug!("Class {} has already ended", context.subject.name);
                         first_line = first_line.strike();
                     } else if class.time.start <= time_now {
                         first_line = first_line.bold();
                     }
                 }
 
+                let mut details = format!("    {}", class.time);
+                if let Some(change) = schedule.change_replaced_by(class) {
+                    if change.is_moved() {
+                        details = format!("{} (moved from {} {})", details, change.date, schedule.changed_class(change).time);
+                    } else {
+                        details = format!("{} (changed)", details);
+                    }
+                }
+
                 let text = first_line
-                    + format!("    {}", class.time).dim();
+                    + details.dim();
 
                 println!("{}", text.to_ansi());
             }
 
-            if is_today {
-                let class_end_at = classes.last().unwrap().time.end;
+            for change in schedule.changes_on(week_no, weekday) {
+                let class = schedule.changed_class(change);
+                let note = match &change.replacement {
+                    None => "cancelled".to_string(),
+                    Some(replacement) if change.is_moved() => {
+                        format!("moved to {} {}", replacement.date, replacement.class.time)
+                    }
+                    // Changed classes taking place on the same day are listed above
+                    Some(_) => continue,
+                };
+
+                let summary = schedule.class_context(class)?.render(&schedule.templates.summary);
+                println!(
+                    "{}",
+                    (summary.strike().dim() + format!(" {}", note).dim()).to_ansi()
+                );
+            }
+
+            if let (true, Some(last)) = (is_today, classes.last()) {
+                let class_end_at = last.time.end;
 
                 if class_end_at > time_now {
                     let remaining = class_end_at - time_now;
@@ -218,7 +488,21 @@ fn main() -> eyre::Result<()> {
                 }
             }
         }
-        Commands::Generate { path, output } => {
+        Commands::Agenda { path, from, to, templates } => {
+            let mut schedule = Schedule::load(&path)?;
+            templates.apply(&mut schedule)?;
+
+            let from = parse_naive_date(&from, &schedule)?;
+            let to = match to {
+                Some(to) => parse_naive_date(&to, &schedule)?,
+                None => from + chrono::Duration::days(6),
+            };
+
+            println!("{}", agenda::render_agenda(&schedule, from, to, Local::now().naive_local())?);
+        }
+        Commands::Now { status } => status.run(status::render_now)?,
+        Commands::Next { status } => status.run(status::render_next)?,
+        Commands::Generate { path, output, recurring, no_state, templates } => {
             let output = match output {
                 Some(path) => path,
                 None => path.with_extension("ics"),
@@ -226,21 +510,153 @@ fn main() -> eyre::Result<()> {
 
             debug!("Will be saving to {}", output.display());
 
-            let schedule: Schedule = serde_json::from_reader(File::open(&path)?)?;
+            let mut schedule = Schedule::load(&path)?;
+            templates.apply(&mut schedule)?;
 
             info!("Schedule: {:?}", schedule);
 
-            let timezone = time::timezones::try_get_local_timezone()?;
-            debug!("Using local timezone: {}", timezone);
+            let timezone = match schedule.calendar.get_timezone()? {
+                Some(timezone) => timezone,
+                None => time::timezones::try_get_local_timezone()?,
+            };
+            debug!("Using timezone: {}", timezone);
 
-            let ical = schedule.to_ical(&timezone)?;
+            let options = ICalOptions { recurring, ..Default::default() };
+            let mut ical = schedule.to_ical(&timezone, &options)?;
 
             info!("Successfully generated events!");
 
+            let state_path = output.with_extension("state.json");
+            let state = if no_state {
+                None
+            } else {
+                let mut state = calendar::revisions::RevisionState::load(&state_path)?;
+                state.apply(&mut ical, Local::now().with_timezone(&timezone))?;
+                Some(state)
+            };
+
             (&ical as &dyn SerializeToICal).serialize_to_ical_file(&output)?;
 
+            // Saved only once the calendar is written, so that no revision is recorded without being published
+            if let Some(state) = state {
+                state.save(&state_path)?;
+            }
+
             info!("Successfully exported calendar to {}!", output.display());
         }
+        Commands::Export { path, format, output } => {
+            let schedule = Schedule::load(&path)?;
+
+            let rows = export::occurrence_rows(&schedule)?;
+
+            match output {
+                Some(output) => {
+                    export::write_rows(&rows, format, &mut File::create(&output)?)?;
+                    info!("Successfully exported {} occurrences to {}!", rows.len(), output.display());
+                }
+                None => export::write_rows(&rows, format, &mut std::io::stdout().lock())?,
+            }
+        }
+        Commands::Timetable { path, format, output } => {
+            let output = match output {
+                Some(path) => path,
+                None => path.with_extension(match format {
+                    timetable::TimetableFormat::Html => "html",
+                    timetable::TimetableFormat::Svg => "svg",
+                }),
+            };
+
+            let schedule = Schedule::load(&path)?;
+
+            std::fs::write(&output, timetable::render(&schedule, format)?)?;
+
+            info!("Successfully rendered timetable to {}!", output.display());
+        }
+        Commands::Weeks { path, json } => {
+            let schedule = Schedule::load(&path)?;
+
+            if json {
+                let weeks: Vec<Vec<Option<String>>> = schedule
+                    .weeks
+                    .iter()
+                    .map(|week| week.iter().map(|date| date.map(|date| date.to_string())).collect())
+                    .collect();
+                println!("{}", serde_json::to_string_pretty(&weeks)?);
+            } else {
+                println!("{}", week_view::render_weeks_table(&schedule));
+            }
+        }
+        Commands::ImportIcs { path, output, force } => {
+            let output = match output {
+                Some(path) => path,
+                None => path.with_extension("json"),
+            };
+
+            if output.exists() && !force {
+                return Err(eyre!(
+                    "{} already exists, use --force to overwrite it",
+                    output.display()
+                ));
+            }
+
+            let timezone = time::timezones::try_get_local_timezone()?;
+            debug!("Using local timezone: {}", timezone);
+
+            // Floating times in the file are local times
+            let calendar = VCalendar::parse_file_in(&path, timezone)?;
+
+            info!("Parsed {} events", calendar.events.len());
+
+            let schedule = calendar::import::import_calendar(&calendar, &timezone)?;
+
+            let mut file = File::create(&output)?;
+            serde_json::to_writer_pretty(&mut file, &schedule)?;
+            writeln!(file)?;
+
+            info!("Successfully imported schedule to {}!", output.display());
+        }
+        Commands::Serve { paths, address, recurring } => {
+            let mut calendar_server = server::CalendarServer::new(&paths, recurring)?;
+
+            let http_server = tiny_http::Server::http(&address)
+                .map_err(|e| eyre!("Failed to listen on {}: {}", address, e))?;
+
+            info!("Serving {} schedules at http://{}/", paths.len(), address);
+
+            calendar_server.run(&http_server);
+        }
+        Commands::Sync { path, caldav, user, password, state, recurring, templates } => {
+            let state_path = match state {
+                Some(path) => path,
+                None => path.with_extension("caldav.json"),
+            };
+
+            let mut schedule = Schedule::load(&path)?;
+            templates.apply(&mut schedule)?;
+
+            let timezone = match schedule.calendar.get_timezone()? {
+                Some(timezone) => timezone,
+                None => time::timezones::try_get_local_timezone()?,
+            };
+            debug!("Using timezone: {}", timezone);
+
+            let options = ICalOptions { recurring, ..Default::default() };
+            let ical = schedule.to_ical(&timezone, &options)?;
+
+            let credentials = user.as_deref().map(|user| (user, password.as_deref().unwrap_or_default()));
+            let client = caldav::CalDavClient::new(&caldav, credentials);
+
+            let mut sync_state = caldav::SyncState::load(&state_path)?;
+            let result = caldav::sync_calendar(&client, &ical, &mut sync_state);
+            sync_state.save(&state_path)?;
+
+            let summary = result?;
+            if summary.failed > 0 {
+                return Err(eyre!("Failed to synchronize {} events", summary.failed));
+            }
+
+            info!("Successfully synchronized calendar with {}!", caldav);
+        }
     }
 
     Ok(())
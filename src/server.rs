@@ -0,0 +1,431 @@
+//! HTTP server publishing schedules as subscribable iCalendar feeds.
+//!
+//! Every schedule file is served at `/<file stem>.ics`.
+//! The query string can select a subset of classes with `type`, `subject` and `teacher`
+//! (comma-separated IDs, see [`ClassFilter`]), and override recurring mode with `recurring`.
+//!
+//! Schedule files are reloaded when their modification time changes.
+//! Generated calendars are cached until then, so that their `ETag` stays the same between refreshes.
+//! Only the [`Feed::MAX_CACHED`] most recently requested variants of each feed are kept.
+
+use crate::calendar::filter::ClassFilter;
+use crate::calendar::schedule::{ICalOptions, Schedule};
+use crate::calendar::uid::stable_hash;
+use crate::http::{percent_decode, to_http_date};
+use crate::ical::SerializeToICal;
+use crate::time::timezones::try_get_local_timezone;
+use chrono::DateTime;
+use eyre::eyre;
+use log::{debug, error, info, warn};
+use std::collections::{BTreeMap, HashMap};
+use std::io::Cursor;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use tiny_http::{Header, Method, Request, Response, Server};
+
+/// Options of a feed request, parsed from its query string.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Hash)]
+struct FeedQuery {
+    filter: ClassFilter,
+    recurring: Option<bool>,
+}
+
+impl FeedQuery {
+    /// Parses a query string, normalizing the filter so that equivalent queries are equal.
+    fn parse(query: &str) -> eyre::Result<Self> {
+        let mut parsed = FeedQuery::default();
+
+        for pair in query.split('&').filter(|pair| !pair.is_empty()) {
+            let (key, value) = pair.split_once('=').unwrap_or((pair, ""));
+            let (key, value) = (percent_decode(key), percent_decode(value));
+
+            match key.as_str() {
+                "recurring" => {
+                    parsed.recurring = Some(match value.as_str() {
+                        "" | "1" | "true" | "yes" => true,
+                        "0" | "false" | "no" => false,
+                        _ => return Err(eyre!("invalid value of recurring: {}", value)),
+                    })
+                }
+                _ => parsed.filter.add(&key, &value)?,
+            }
+        }
+
+        parsed.filter.normalize();
+        Ok(parsed)
+    }
+}
+
+/// A calendar generated for a specific query, with its entity tag.
+struct CachedCalendar {
+    body: Vec<u8>,
+    etag: String,
+    /// Value of [`Feed::requests`] when the calendar was last requested.
+    last_used: u64,
+}
+
+/// A schedule file served as a feed.
+struct Feed {
+    path: PathBuf,
+    schedule: Option<Schedule>,
+    modified: Option<SystemTime>,
+    /// Generated calendars by query, cleared when the schedule is reloaded.
+    cache: HashMap<FeedQuery, CachedCalendar>,
+    /// Number of calendar requests, used to evict the least recently used calendars.
+    requests: u64,
+}
+
+impl Feed {
+    /// Maximum number of calendars kept in the cache.
+    const MAX_CACHED: usize = 32;
+
+    /// Reloads the schedule if the file was modified since it was last loaded.
+    ///
+    /// If the new version can't be loaded, the previous one keeps being served.
+    fn refresh(&mut self) -> eyre::Result<()> {
+        let modified = std::fs::metadata(&self.path)?.modified()?;
+
+        if self.schedule.is_some() && self.modified == Some(modified) {
+            return Ok(());
+        }
+
+        match Schedule::load(&self.path) {
+            Ok(schedule) => {
+                info!("Loaded {}", self.path.display());
+                self.schedule = Some(schedule);
+                self.modified = Some(modified);
+                self.cache.clear();
+                Ok(())
+            }
+            Err(e) if self.schedule.is_some() => {
+                warn!("Failed to reload {}, serving the previous version: {}", self.path.display(), e);
+                Ok(())
+            }
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Returns the calendar for a query string, generating it if it's not cached yet.
+    ///
+    /// Fails if the query is invalid or refers to subjects or teachers not in the schedule.
+    fn calendar(&mut self, query: &str, recurring: bool) -> eyre::Result<&CachedCalendar> {
+        let Some(schedule) = &self.schedule else {
+            return Err(eyre!("schedule not loaded"));
+        };
+
+        let parsed = FeedQuery::parse(query)?;
+        self.requests += 1;
+
+        if !self.cache.contains_key(&parsed) {
+            parsed.filter.check(schedule)?;
+            let tz = match schedule.calendar.get_timezone()? {
+                Some(tz) => tz,
+                None => try_get_local_timezone()?,
+            };
+            let options = ICalOptions {
+                recurring: parsed.recurring.unwrap_or(recurring),
+                filter: parsed.filter.clone(),
+            };
+
+            let mut body = Vec::new();
+            schedule.to_ical(&tz, &options)?.serialize_to_ical(&mut body)?;
+
+            let etag = format!("\"{:016x}\"", stable_hash(&[&String::from_utf8_lossy(&body)]));
+            debug!("Generated {} for {:?}: {}", self.path.display(), query, etag);
+
+            if self.cache.len() >= Self::MAX_CACHED {
+                let oldest = self.cache.iter().min_by_key(|(_, cached)| cached.last_used).map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    self.cache.remove(&oldest);
+                }
+            }
+            self.cache.insert(parsed.clone(), CachedCalendar { body, etag, last_used: 0 });
+        }
+
+        let cached = self.cache.get_mut(&parsed).unwrap();
+        cached.last_used = self.requests;
+        Ok(cached)
+    }
+}
+
+type HttpResponse = Response<Cursor<Vec<u8>>>;
+
+fn header(name: &str, value: &str) -> Header {
+    Header::from_bytes(name.as_bytes(), value.as_bytes()).expect("valid header")
+}
+
+fn text_response(status: u16, text: impl Into<String>) -> HttpResponse {
+    Response::from_string(text)
+        .with_status_code(status)
+        .with_header(header("Content-Type", "text/plain; charset=utf-8"))
+}
+
+/// Server of iCalendar feeds generated from schedule files.
+pub struct CalendarServer {
+    feeds: BTreeMap<String, Feed>,
+    /// Whether to generate recurring events, unless the query says otherwise.
+    recurring: bool,
+}
+
+impl CalendarServer {
+    /// Creates a server of the given schedule files, each served under its file stem.
+    pub fn new(paths: &[PathBuf], recurring: bool) -> eyre::Result<Self> {
+        let mut feeds = BTreeMap::new();
+
+        for path in paths {
+            let name = path
+                .file_stem()
+                .and_then(|stem| stem.to_str())
+                .ok_or_else(|| eyre!("invalid schedule path: {}", path.display()))?
+                .to_string();
+
+            let mut feed = Feed {
+                path: path.clone(),
+                schedule: None,
+                modified: None,
+                cache: HashMap::new(),
+                requests: 0,
+            };
+            feed.refresh()?;
+
+            if feeds.insert(name.clone(), feed).is_some() {
+                return Err(eyre!("multiple schedules named {}", name));
+            }
+        }
+
+        Ok(CalendarServer { feeds, recurring })
+    }
+
+    /// Handles requests until the server is shut down.
+    pub fn run(&mut self, server: &Server) {
+        for request in server.incoming_requests() {
+            let response = self.respond(&request);
+
+            debug!("{} {} -> {}", request.method(), request.url(), response.status_code().0);
+
+            if let Err(e) = request.respond(response) {
+                warn!("Failed to send response: {}", e);
+            }
+        }
+    }
+
+    fn respond(&mut self, request: &Request) -> HttpResponse {
+        if !matches!(request.method(), Method::Get | Method::Head) {
+            return text_response(405, "Method not allowed").with_header(header("Allow", "GET, HEAD"));
+        }
+
+        let (path, query) = request.url().split_once('?').unwrap_or((request.url(), ""));
+
+        if path == "/" {
+            let index: String = self.feeds.keys().map(|name| format!("/{}.ics\n", name)).collect();
+            return text_response(200, index);
+        }
+
+        let Some(feed) = path
+            .strip_prefix('/')
+            .and_then(|name| name.strip_suffix(".ics"))
+            .and_then(|name| self.feeds.get_mut(&percent_decode(name)))
+        else {
+            return text_response(404, "Not found");
+        };
+
+        if let Err(e) = feed.refresh() {
+            error!("Failed to load {}: {}", feed.path.display(), e);
+            return text_response(500, "Failed to load the schedule");
+        }
+
+        let last_modified = feed.modified.map(to_http_date);
+        let calendar = match feed.calendar(query, self.recurring) {
+            Ok(calendar) => calendar,
+            Err(e) => return text_response(400, e.to_string()),
+        };
+
+        let request_header = |name: &'static str| {
+            request
+                .headers()
+                .iter()
+                .find(|h| h.field.equiv(name))
+                .map(|h| h.value.as_str().to_string())
+        };
+
+        let not_modified = match request_header("If-None-Match") {
+            Some(tags) => tags
+                .split(',')
+                .any(|tag| tag.trim() == calendar.etag || tag.trim() == "*"),
+            None => match (request_header("If-Modified-Since"), &last_modified) {
+                (Some(since), Some(modified)) => {
+                    let parse = |date: &str| DateTime::parse_from_rfc2822(date).ok();
+                    matches!((parse(&since), parse(modified)), (Some(since), Some(modified)) if modified <= since)
+                }
+                _ => false,
+            },
+        };
+
+        let response = if not_modified {
+            Response::from_data(Vec::new()).with_status_code(304)
+        } else {
+            Response::from_data(calendar.body.clone())
+                .with_header(header("Content-Type", "text/calendar; charset=utf-8"))
+        };
+
+        let response = response.with_header(header("ETag", &calendar.etag));
+
+        match last_modified {
+            Some(last_modified) => response.with_header(header("Last-Modified", &last_modified)),
+            None => response,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::{Read, Write};
+    use std::net::TcpStream;
+
+    const SCHEDULE: &str = r#"{
+        "calendar": { "id": "test", "timezone": "Europe/Warsaw" },
+        "weeks": [["2025-10-20", "2025-10-21", "2025-10-22", "2025-10-23", "2025-10-24"]],
+        "subjects": { "aisd": { "name": "AiSD" }, "ang": { "name": "Angielski" } },
+        "teachers": {},
+        "schedule": [
+            { "subject": "aisd", "type": "lab", "day": "monday", "time": "8:15-10:00" },
+            { "subject": "ang", "type": "languages", "day": "tuesday", "time": "10:15-12:00" }
+        ]
+    }"#;
+
+    /// Sends a GET request and returns the status code, headers and body of the response.
+    fn get(address: &str, path: &str, headers: &[(&str, &str)]) -> (u16, Vec<(String, String)>, String) {
+        let mut stream = TcpStream::connect(address).unwrap();
+        let mut request = format!("GET {} HTTP/1.1\r\nHost: {}\r\nConnection: close\r\n", path, address);
+        for (name, value) in headers {
+            request.push_str(&format!("{}: {}\r\n", name, value));
+        }
+        request.push_str("\r\n");
+        stream.write_all(request.as_bytes()).unwrap();
+
+        let mut response = String::new();
+        stream.read_to_string(&mut response).unwrap();
+
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let mut lines = head.lines();
+        let status = lines.next().unwrap().split(' ').nth(1).unwrap().parse().unwrap();
+        let headers = lines
+            .filter_map(|line| line.split_once(": "))
+            .map(|(name, value)| (name.to_ascii_lowercase(), value.to_string()))
+            .collect();
+
+        (status, headers, body.to_string())
+    }
+
+    fn header_value<'a>(headers: &'a [(String, String)], name: &str) -> &'a str {
+        &headers.iter().find(|(n, _)| n == name).unwrap().1
+    }
+
+    #[test]
+    fn query_parsing() {
+        let query = FeedQuery::parse("type=lab%2Clecture&subject=aisd&recurring").unwrap();
+        assert_eq!(query.filter.subjects, vec!["aisd"]);
+        assert_eq!(query.filter.types.len(), 2);
+        assert_eq!(query.recurring, Some(true));
+
+        assert!(FeedQuery::parse("color=red").is_err());
+
+        assert_eq!(
+            FeedQuery::parse("subject=ang,aisd&type=lab").unwrap(),
+            FeedQuery::parse("type=lab&subject=aisd&subject=ang&subject=aisd").unwrap()
+        );
+    }
+
+    #[test]
+    fn cache_limit() {
+        let dir = std::env::temp_dir().join(format!("planner-cache-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("group-1.json");
+        std::fs::write(&path, SCHEDULE).unwrap();
+
+        let mut feed = Feed {
+            path,
+            schedule: None,
+            modified: None,
+            cache: HashMap::new(),
+            requests: 0,
+        };
+        feed.refresh().unwrap();
+
+        let types = ["lecture", "lab", "exercise", "seminar", "pe", "languages", "project"];
+        let query = |mask: usize| {
+            let selected: Vec<_> = (0..types.len()).filter(|i| mask & (1 << i) != 0).map(|i| types[i]).collect();
+            format!("type={}", selected.join(","))
+        };
+
+        for mask in 1..=Feed::MAX_CACHED + 8 {
+            feed.calendar(&query(mask), false).unwrap();
+            // Keep the first calendar in use
+            feed.calendar(&query(1), false).unwrap();
+        }
+
+        assert_eq!(feed.cache.len(), Feed::MAX_CACHED);
+        assert!(feed.cache.contains_key(&FeedQuery::parse(&query(1)).unwrap()));
+        assert!(!feed.cache.contains_key(&FeedQuery::parse(&query(2)).unwrap()));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn serve_feeds() {
+        let dir = std::env::temp_dir().join(format!("planner-serve-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("group-1.json");
+        std::fs::write(&path, SCHEDULE).unwrap();
+
+        let server = Server::http("127.0.0.1:0").unwrap();
+        let address = server.server_addr().to_ip().unwrap().to_string();
+        let mut calendar_server = CalendarServer::new(std::slice::from_ref(&path), false).unwrap();
+        std::thread::spawn(move || calendar_server.run(&server));
+
+        let (status, headers, body) = get(&address, "/group-1.ics", &[]);
+        assert_eq!(status, 200);
+        assert!(header_value(&headers, "content-type").starts_with("text/calendar"));
+        assert!(body.contains("SUMMARY:🧪 AiSD"));
+        assert!(body.contains("Angielski"));
+
+        let etag = header_value(&headers, "etag").to_string();
+        let last_modified = header_value(&headers, "last-modified").to_string();
+
+        let (status, _, body) = get(&address, "/group-1.ics", &[("If-None-Match", &etag)]);
+        assert_eq!(status, 304);
+        assert!(body.is_empty());
+
+        let (status, _, _) = get(&address, "/group-1.ics", &[("If-Modified-Since", &last_modified)]);
+        assert_eq!(status, 304);
+
+        let (status, headers, body) = get(&address, "/group-1.ics?type=languages", &[]);
+        assert_eq!(status, 200);
+        assert_ne!(header_value(&headers, "etag"), etag);
+        assert!(!body.contains("AiSD"));
+        assert!(body.contains("Angielski"));
+
+        assert_eq!(get(&address, "/group-1.ics?type=party", &[]).0, 400);
+        assert_eq!(get(&address, "/group-1.ics?subject=pe", &[]).0, 400);
+        assert_eq!(get(&address, "/group-1.ics?teacher=nobody", &[]).0, 400);
+
+        // Equivalent queries share the cached calendar
+        let (_, headers, _) = get(&address, "/group-1.ics?subject=ang", &[]);
+        let (_, same, _) = get(&address, "/group-1.ics?subject=ang,ang", &[]);
+        assert_eq!(header_value(&headers, "etag"), header_value(&same, "etag"));
+        assert_eq!(get(&address, "/group-2.ics", &[]).0, 404);
+
+        // Make sure the modification time changes even on coarse-grained file systems
+        let file = File::options().write(true).open(&path).unwrap();
+        std::fs::write(&path, SCHEDULE.replace("AiSD", "Algorytmy")).unwrap();
+        file.set_modified(SystemTime::now() + std::time::Duration::from_secs(5)).unwrap();
+
+        let (status, _, body) = get(&address, "/group-1.ics", &[("If-None-Match", &etag)]);
+        assert_eq!(status, 200);
+        assert!(body.contains("SUMMARY:🧪 Algorytmy"));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,264 @@
+//! Current and next class, for the `now` and `next` commands meant for status bars.
+
+use crate::calendar::schedule::{Occurrence, Schedule};
+use crate::time::timeext::TimeDeltaExt;
+use chrono::NaiveDateTime;
+use clap::ValueEnum;
+use serde::Serialize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum StatusFormat {
+    /// A sentence, like "Next class in 25m: 🧪 AiSD, 204/C3".
+    Text,
+    /// As short as possible, like "🧪 AiSD in 25m".
+    Terse,
+    /// A JSON object with the state and both classes.
+    Json,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum State {
+    /// A class is ongoing.
+    InClass,
+    /// There are more classes later today.
+    Break,
+    /// There are no more classes today.
+    Free,
+}
+
+impl State {
+    /// Returns the exit code of the `now` and `next` commands in this state.
+    pub fn exit_code(self) -> i32 {
+        match self {
+            State::InClass => 0,
+            State::Break => 10,
+            State::Free => 11,
+        }
+    }
+}
+
+/// An occurrence of a class with its start and end.
+#[derive(Debug, Clone, Copy)]
+pub struct TimedOccurrence<'a> {
+    pub occurrence: Occurrence<'a>,
+    pub start: NaiveDateTime,
+    pub end: NaiveDateTime,
+}
+
+#[derive(Debug)]
+pub struct Status<'a> {
+    pub state: State,
+    pub now: NaiveDateTime,
+    pub current: Option<TimedOccurrence<'a>>,
+    pub next: Option<TimedOccurrence<'a>>,
+}
+
+impl<'a> Status<'a> {
+    /// Finds the ongoing and the next class at `now`, looking through the whole schedule.
+    pub fn at(schedule: &'a Schedule, now: NaiveDateTime) -> Self {
+        let mut occurrences: Vec<_> = schedule
+            .occurrences()
+            .map(|occurrence| TimedOccurrence {
+                occurrence,
+                start: occurrence.date.and_time(occurrence.class.time.start),
+                end: occurrence.date.and_time(occurrence.class.time.end),
+            })
+            .collect();
+        occurrences.sort_by_key(|o| o.start);
+
+        let current = occurrences.iter().find(|o| o.start <= now && now < o.end).copied();
+        let next = occurrences.iter().find(|o| o.start > now).copied();
+
+        let state = match (current, next) {
+            (Some(_), _) => State::InClass,
+            (None, Some(next)) if next.start.date() == now.date() => State::Break,
+            _ => State::Free,
+        };
+
+        Status {
+            state,
+            now,
+            current,
+            next,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ClassJson {
+    summary: String,
+    subject: String,
+    #[serde(rename = "type")]
+    class_type: String,
+    location: String,
+    start: String,
+    end: String,
+    /// Minutes until the start of the class, or until its end if it's ongoing.
+    minutes: i64,
+}
+
+#[derive(Debug, Serialize)]
+struct StatusJson {
+    state: State,
+    current: Option<ClassJson>,
+    next: Option<ClassJson>,
+}
+
+/// Returns the summary and the location of a class, filled from the schedule's templates.
+fn describe(schedule: &Schedule, occurrence: &TimedOccurrence) -> eyre::Result<(String, String)> {
+    let context = schedule.class_context(occurrence.occurrence.class)?;
+    let summary = context.render(&schedule.templates.summary);
+    let location = context.render(&schedule.templates.location);
+
+    Ok((summary, location))
+}
+
+fn with_location(summary: String, location: String) -> String {
+    if location.is_empty() {
+        summary
+    } else {
+        format!("{}, {}", summary, location)
+    }
+}
+
+fn to_json(schedule: &Schedule, status: &Status) -> eyre::Result<String> {
+    let class_json = |occurrence: &TimedOccurrence, until: NaiveDateTime| -> eyre::Result<ClassJson> {
+        let class = occurrence.occurrence.class;
+        let (summary, location) = describe(schedule, occurrence)?;
+        let minutes = (until - status.now).num_seconds();
+
+        Ok(ClassJson {
+            summary,
+            subject: schedule.class_context(class)?.subject.name.clone(),
+            class_type: class.class_type.to_id().to_string(),
+            location,
+            start: occurrence.start.format("%Y-%m-%dT%H:%M").to_string(),
+            end: occurrence.end.format("%Y-%m-%dT%H:%M").to_string(),
+            minutes: (minutes + 59) / 60,
+        })
+    };
+
+    let json = StatusJson {
+        state: status.state,
+        current: status.current.as_ref().map(|o| class_json(o, o.end)).transpose()?,
+        next: status.next.as_ref().map(|o| class_json(o, o.start)).transpose()?,
+    };
+
+    Ok(serde_json::to_string(&json)?)
+}
+
+/// Describes the ongoing class and the time left, or the break or free time.
+pub fn render_now(schedule: &Schedule, status: &Status, format: StatusFormat) -> eyre::Result<String> {
+    if format == StatusFormat::Json {
+        return to_json(schedule, status);
+    }
+
+    let terse = format == StatusFormat::Terse;
+
+    Ok(match (status.current, status.next) {
+        (Some(current), _) => {
+            let (summary, location) = describe(schedule, &current)?;
+            let left = (current.end - status.now).to_short_human_readable();
+
+            if terse {
+                format!("{} {}", summary, left)
+            } else {
+                format!("{}, {} left", with_location(summary, location), left)
+            }
+        }
+        (None, Some(next)) if status.state == State::Break => {
+            let until = (next.start - status.now).to_short_human_readable();
+
+            if terse {
+                format!("Break {}", until)
+            } else {
+                let (summary, location) = describe(schedule, &next)?;
+                format!("Break, next class in {}: {}", until, with_location(summary, location))
+            }
+        }
+        _ if terse => "Free".to_string(),
+        _ => "No more classes today".to_string(),
+    })
+}
+
+/// Describes the next class and the time until it starts.
+pub fn render_next(schedule: &Schedule, status: &Status, format: StatusFormat) -> eyre::Result<String> {
+    if format == StatusFormat::Json {
+        return to_json(schedule, status);
+    }
+
+    let Some(next) = status.next else {
+        return Ok(match format {
+            StatusFormat::Terse => String::new(),
+            _ => "No more classes in the schedule".to_string(),
+        });
+    };
+
+    let (summary, location) = describe(schedule, &next)?;
+    let until = (next.start - status.now).to_short_human_readable();
+
+    Ok(match format {
+        StatusFormat::Terse => format!("{} in {}", summary, until),
+        _ => format!("Next class in {}: {}", until, with_location(summary, location)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use serde_json::json;
+
+    #[test]
+    fn current_and_next_class() {
+        let schedule: Schedule = serde_json::from_value(json!({
+            "weeks": [["2025-10-20", "2025-10-21", "2025-10-22", "2025-10-23", "2025-10-24"],
+                      ["2025-10-27", "2025-10-28", "2025-10-29", "2025-10-30", "2025-10-31"]],
+            "subjects": { "aisd": { "name": "Algorytmy", "short": "AiSD" } },
+            "teachers": {},
+            "schedule": [
+                { "subject": "aisd", "type": "lecture", "day": "monday", "time": "8:15-10:00" },
+                { "subject": "aisd", "type": "lab", "day": "monday", "time": "10:15-12:00",
+                  "location": { "building": "C3", "room": "204" } }
+            ]
+        }))
+        .unwrap();
+        let at = |day, hour, minute| {
+            NaiveDate::from_ymd_opt(2025, 10, day).unwrap().and_hms_opt(hour, minute, 0).unwrap()
+        };
+
+        let status = Status::at(&schedule, at(20, 9, 30));
+        assert_eq!(status.state, State::InClass);
+        assert_eq!(render_now(&schedule, &status, StatusFormat::Text).unwrap(), "📚 AiSD, 30m left");
+        assert_eq!(render_now(&schedule, &status, StatusFormat::Terse).unwrap(), "📚 AiSD 30m");
+        assert_eq!(render_next(&schedule, &status, StatusFormat::Terse).unwrap(), "🧪 AiSD in 45m");
+
+        let status = Status::at(&schedule, at(20, 10, 5));
+        assert_eq!(status.state, State::Break);
+        assert_eq!(
+            render_now(&schedule, &status, StatusFormat::Text).unwrap(),
+            "Break, next class in 10m: 🧪 AiSD, 204/C3"
+        );
+
+        // Looks ahead to the next week
+        let status = Status::at(&schedule, at(21, 8, 0));
+        assert_eq!(status.state, State::Free);
+        assert_eq!(status.state.exit_code(), 11);
+        assert_eq!(
+            render_next(&schedule, &status, StatusFormat::Text).unwrap(),
+            "Next class in 6d 0h: 📚 AiSD"
+        );
+
+        let json: serde_json::Value =
+            serde_json::from_str(&render_next(&schedule, &status, StatusFormat::Json).unwrap()).unwrap();
+        assert_eq!(json["state"], "free");
+        assert_eq!(json["current"], serde_json::Value::Null);
+        assert_eq!(json["next"]["start"], "2025-10-27T08:15");
+        assert_eq!(json["next"]["minutes"], 6 * 24 * 60 + 15);
+
+        let status = Status::at(&schedule, at(27, 12, 0));
+        assert_eq!(render_next(&schedule, &status, StatusFormat::Terse).unwrap(), "");
+    }
+}
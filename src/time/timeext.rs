@@ -1,17 +1,26 @@
-use chrono::{DateTime, TimeZone};
+use chrono::{DateTime, TimeZone, Utc};
 
 pub trait TimeExt {
     fn to_stamp(&self) -> String;
+    fn to_utc_stamp(&self) -> String;
 }
 
 impl<Tz: TimeZone> TimeExt for DateTime<Tz> {
     fn to_stamp(&self) -> String {
         self.naive_local().format("%Y%m%dT%H%M%S").to_string()
     }
+
+    fn to_utc_stamp(&self) -> String {
+        self.with_timezone(&Utc).format("%Y%m%dT%H%M%SZ").to_string()
+    }
 }
 
 pub trait TimeDeltaExt {
     fn to_human_readable(&self) -> String;
+    /// Formats the duration rounded up to minutes, with at most two units, like `2d 3h` or `25m`.
+    fn to_short_human_readable(&self) -> String;
+    /// Formats the duration as an iCalendar `DURATION` value, like `-PT15M`.
+    fn to_ical_duration(&self) -> String;
 }
 
 impl TimeDeltaExt for chrono::TimeDelta {
@@ -29,4 +38,45 @@ impl TimeDeltaExt for chrono::TimeDelta {
             format!("{}s", seconds)
         }
     }
+
+    fn to_short_human_readable(&self) -> String {
+        let total_minutes = (self.num_seconds().abs() + 59) / 60;
+        let (days, hours, minutes) = (total_minutes / 1440, total_minutes % 1440 / 60, total_minutes % 60);
+
+        if days > 0 {
+            format!("{}d {}h", days, hours)
+        } else if hours > 0 {
+            format!("{}h {}m", hours, minutes)
+        } else {
+            format!("{}m", minutes)
+        }
+    }
+
+    fn to_ical_duration(&self) -> String {
+        let sign = if *self < chrono::TimeDelta::zero() { "-" } else { "" };
+        let total_seconds = self.num_seconds().abs();
+        let (days, rest) = (total_seconds / 86400, total_seconds % 86400);
+        let (hours, minutes, seconds) = (rest / 3600, rest % 3600 / 60, rest % 60);
+
+        let mut duration = format!("{}P", sign);
+
+        if days > 0 {
+            duration.push_str(&format!("{}D", days));
+        }
+
+        if rest > 0 || days == 0 {
+            duration.push('T');
+            if hours > 0 {
+                duration.push_str(&format!("{}H", hours));
+            }
+            if minutes > 0 {
+                duration.push_str(&format!("{}M", minutes));
+            }
+            if seconds > 0 || rest == 0 {
+                duration.push_str(&format!("{}S", seconds));
+            }
+        }
+
+        duration
+    }
 }
@@ -11,10 +11,60 @@ pub enum TryGetLocalTimezoneError {
     GetTimezone(GetTimezoneError),
 }
 
+/// Windows time zone names, as used by Outlook and Exchange, with their IANA equivalents.
+///
+/// Based on the default (`001`) mappings of the CLDR `windowsZones` table.
+const WINDOWS_TIMEZONES: &[(&str, &str)] = &[
+    ("Dateline Standard Time", "Etc/GMT+12"),
+    ("UTC-11", "Etc/GMT+11"),
+    ("Hawaiian Standard Time", "Pacific/Honolulu"),
+    ("Alaskan Standard Time", "America/Anchorage"),
+    ("Pacific Standard Time", "America/Los_Angeles"),
+    ("US Mountain Standard Time", "America/Phoenix"),
+    ("Mountain Standard Time", "America/Denver"),
+    ("Central Standard Time", "America/Chicago"),
+    ("Eastern Standard Time", "America/New_York"),
+    ("Atlantic Standard Time", "America/Halifax"),
+    ("Newfoundland Standard Time", "America/St_Johns"),
+    ("E. South America Standard Time", "America/Sao_Paulo"),
+    ("UTC", "Etc/UTC"),
+    ("GMT Standard Time", "Europe/London"),
+    ("Greenwich Standard Time", "Atlantic/Reykjavik"),
+    ("W. Europe Standard Time", "Europe/Berlin"),
+    ("Central Europe Standard Time", "Europe/Budapest"),
+    ("Central European Standard Time", "Europe/Warsaw"),
+    ("Romance Standard Time", "Europe/Paris"),
+    ("GTB Standard Time", "Europe/Bucharest"),
+    ("E. Europe Standard Time", "Europe/Chisinau"),
+    ("FLE Standard Time", "Europe/Kyiv"),
+    ("Kaliningrad Standard Time", "Europe/Kaliningrad"),
+    ("Russian Standard Time", "Europe/Moscow"),
+    ("Turkey Standard Time", "Europe/Istanbul"),
+    ("Israel Standard Time", "Asia/Jerusalem"),
+    ("Egypt Standard Time", "Africa/Cairo"),
+    ("South Africa Standard Time", "Africa/Johannesburg"),
+    ("Arabian Standard Time", "Asia/Dubai"),
+    ("India Standard Time", "Asia/Kolkata"),
+    ("China Standard Time", "Asia/Shanghai"),
+    ("Singapore Standard Time", "Asia/Singapore"),
+    ("Tokyo Standard Time", "Asia/Tokyo"),
+    ("Korea Standard Time", "Asia/Seoul"),
+    ("AUS Eastern Standard Time", "Australia/Sydney"),
+    ("New Zealand Standard Time", "Pacific/Auckland"),
+];
+
 pub fn get_timezone_by_name(name: &str) -> Option<Tz> {
     Tz::from_str(name).ok()
 }
 
+/// Returns the time zone with the given Windows name, e.g. `Central European Standard Time`.
+pub fn get_timezone_by_windows_name(name: &str) -> Option<Tz> {
+    WINDOWS_TIMEZONES
+        .iter()
+        .find(|(windows, _)| *windows == name)
+        .and_then(|(_, iana)| get_timezone_by_name(iana))
+}
+
 pub fn try_get_local_timezone() -> Result<Tz, TryGetLocalTimezoneError> {
     let timezone_str = match iana_time_zone::get_timezone() {
         Ok(timezone) => Ok(timezone),
@@ -25,3 +75,18 @@ pub fn try_get_local_timezone() -> Result<Tz, TryGetLocalTimezoneError> {
 
     timezone.ok_or_else(|| TryGetLocalTimezoneError::ChronoTzNotFound(timezone_str))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows_timezones() {
+        for (windows, iana) in WINDOWS_TIMEZONES {
+            assert!(get_timezone_by_name(iana).is_some(), "{} maps to unknown {}", windows, iana);
+        }
+
+        assert_eq!(get_timezone_by_windows_name("Central European Standard Time"), Some(Tz::Europe__Warsaw));
+        assert_eq!(get_timezone_by_windows_name("Europe/Warsaw"), None);
+    }
+}
@@ -0,0 +1,304 @@
+//! Rendering of the weekly timetable grid, sized to print on a landscape A4 page.
+
+use crate::calendar::class::Class;
+use crate::calendar::schedule::Schedule;
+use crate::calendar::WeekParity;
+use crate::export::escape_html;
+use chrono::{NaiveTime, Timelike};
+use clap::ValueEnum;
+use eyre::eyre;
+use std::fmt::Write;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum TimetableFormat {
+    /// A standalone HTML page with the grid.
+    Html,
+    /// The grid alone, as an SVG image.
+    Svg,
+}
+
+/// Size of the image, in CSS pixels of an A4 page in landscape orientation.
+const WIDTH: f64 = 1123.0;
+const HEIGHT: f64 = 794.0;
+const MARGIN: f64 = 16.0;
+const HOURS_WIDTH: f64 = 40.0;
+const TITLE_HEIGHT: f64 = 28.0;
+const HEADER_HEIGHT: f64 = 24.0;
+const LINE_HEIGHT: f64 = 12.0;
+
+/// Title of timetables of schedules without a calendar name.
+const DEFAULT_TITLE: &str = "Plan zajęć";
+
+/// A class placed in the grid, in one of the lanes its day column is split into
+/// so that classes at overlapping times (e.g. in odd and even weeks) are shown side by side.
+struct PlacedClass<'a> {
+    class: &'a Class,
+    lane: usize,
+}
+
+fn minutes(time: NaiveTime) -> f64 {
+    (time.hour() * 60 + time.minute()) as f64
+}
+
+fn title(schedule: &Schedule) -> &str {
+    schedule.calendar.name.as_deref().unwrap_or(DEFAULT_TITLE)
+}
+
+/// Assigns lanes to the classes of a single day, returning them with the number of lanes.
+fn place_classes<'a>(mut classes: Vec<&'a Class>) -> (Vec<PlacedClass<'a>>, usize) {
+    classes.sort_by_key(|class| (class.time.start, class.time.end));
+
+    let mut lane_ends: Vec<NaiveTime> = Vec::new();
+    let placed = classes
+        .into_iter()
+        .map(|class| {
+            let lane = match lane_ends.iter().position(|end| *end <= class.time.start) {
+                Some(lane) => {
+                    lane_ends[lane] = class.time.end;
+                    lane
+                }
+                None => {
+                    lane_ends.push(class.time.end);
+                    lane_ends.len() - 1
+                }
+            };
+
+            PlacedClass { class, lane }
+        })
+        .collect();
+
+    (placed, lane_ends.len().max(1))
+}
+
+/// Renders the timetable of the schedule's classes, with a column for every weekday of the schedule's days
+/// and a row for every hour.
+pub fn render_svg(schedule: &Schedule) -> eyre::Result<String> {
+    let first_hour = schedule.schedule.iter().map(|class| class.time.start.hour()).min();
+    let last_hour = schedule
+        .schedule
+        .iter()
+        .map(|class| class.time.end.hour() + (class.time.end.minute() > 0) as u32)
+        .max();
+    let (Some(first_hour), Some(last_hour)) = (first_hour, last_hour) else {
+        return Err(eyre!("the schedule has no classes"));
+    };
+
+    let days = &schedule.days;
+
+    let grid_top = MARGIN + TITLE_HEIGHT + HEADER_HEIGHT;
+    let grid_left = MARGIN + HOURS_WIDTH;
+    let column_width = (WIDTH - grid_left - MARGIN) / days.len() as f64;
+    let minute_height = (HEIGHT - grid_top - MARGIN) / ((last_hour - first_hour) * 60) as f64;
+    // In minutes rather than times, as the grid ends at 24:00 for classes ending after 23:00
+    let y_of_minute = |minute: f64| grid_top + (minute - (first_hour * 60) as f64) * minute_height;
+    let y_of = |time: NaiveTime| y_of_minute(minutes(time));
+
+    let mut svg = String::new();
+    writeln!(
+        svg,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="297mm" height="210mm" viewBox="0 0 {} {}" font-family="sans-serif" font-size="10">"#,
+        WIDTH, HEIGHT
+    )?;
+    writeln!(svg, r#"  <rect width="100%" height="100%" fill="white"/>"#)?;
+
+    writeln!(
+        svg,
+        r#"  <text x="{}" y="{}" font-size="16" font-weight="bold">{}</text>"#,
+        MARGIN,
+        MARGIN + 16.0,
+        escape_html(title(schedule))
+    )?;
+
+    for hour in first_hour..=last_hour {
+        let y = y_of_minute((hour * 60) as f64);
+        writeln!(
+            svg,
+            r##"  <line x1="{}" y1="{:.1}" x2="{}" y2="{:.1}" stroke="#ccc"/>"##,
+            MARGIN,
+            y,
+            WIDTH - MARGIN,
+            y
+        )?;
+        if hour < last_hour {
+            writeln!(svg, r#"  <text x="{}" y="{:.1}">{:02}:00</text>"#, MARGIN, y + 11.0, hour)?;
+        }
+    }
+
+    for (index, day) in days.iter().enumerate() {
+        let x = grid_left + index as f64 * column_width;
+        writeln!(
+            svg,
+            r##"  <line x1="{:.1}" y1="{}" x2="{:.1}" y2="{}" stroke="#ccc"/>"##,
+            x,
+            grid_top - HEADER_HEIGHT,
+            x,
+            HEIGHT - MARGIN
+        )?;
+        writeln!(
+            svg,
+            r#"  <text x="{:.1}" y="{}" font-size="12" font-weight="bold" text-anchor="middle">{}</text>"#,
+            x + column_width / 2.0,
+            grid_top - 8.0,
+            day
+        )?;
+
+        let classes = schedule.schedule.iter().filter(|class| class.day == *day).collect();
+        let (placed, lanes) = place_classes(classes);
+        let lane_width = column_width / lanes as f64;
+
+        for PlacedClass { class, lane } in placed {
+            let context = schedule.class_context(class)?;
+            let (x, y) = (x + lane as f64 * lane_width + 2.0, y_of(class.time.start) + 1.0);
+            let (width, height) = (lane_width - 4.0, y_of(class.time.end) - y - 1.0);
+            let color = schedule.class_color(class.class_type);
+            let parity = class.weeks.as_ref().map(|weeks| weeks.parity()).unwrap_or_default();
+            let dash = match parity {
+                WeekParity::All => "",
+                WeekParity::Odd | WeekParity::Even => r#" stroke-dasharray="6 3""#,
+            };
+
+            let mut heading = format!("{} {}", class.time, class.class_type.to_name());
+            if let Some(label) = class.weeks.as_ref().and_then(|weeks| weeks.label()) {
+                heading = format!("{} · {}", heading, label);
+            }
+            let lines = [
+                Some(context.subject.get_short_or_name().to_string()),
+                Some(heading),
+                context.value("location"),
+                context.value("teacherList"),
+            ];
+
+            writeln!(svg, "  <g>")?;
+            writeln!(
+                svg,
+                r#"    <clipPath id="class-{}-{}-{}"><rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}"/></clipPath>"#,
+                index, lane, class.time.start.format("%H%M"), x, y, width, height
+            )?;
+            writeln!(
+                svg,
+                r#"    <rect x="{:.1}" y="{:.1}" width="{:.1}" height="{:.1}" rx="3" fill="{}" fill-opacity="0.3" stroke="{}" stroke-width="1.5"{}/>"#,
+                x, y, width, height, color, color, dash
+            )?;
+            writeln!(
+                svg,
+                r#"    <text clip-path="url(#class-{}-{}-{})">"#,
+                index, lane, class.time.start.format("%H%M")
+            )?;
+            for (line_index, line) in lines.iter().flatten().enumerate() {
+                let weight = if line_index == 0 { r#" font-weight="bold""# } else { "" };
+                writeln!(
+                    svg,
+                    r#"      <tspan x="{:.1}" y="{:.1}"{}>{}</tspan>"#,
+                    x + 4.0,
+                    y + (line_index + 1) as f64 * LINE_HEIGHT,
+                    weight,
+                    escape_html(line)
+                )?;
+            }
+            writeln!(svg, "    </text>")?;
+            writeln!(svg, "  </g>")?;
+        }
+    }
+
+    writeln!(svg, "</svg>")?;
+
+    Ok(svg)
+}
+
+/// Renders the timetable in the given format.
+pub fn render(schedule: &Schedule, format: TimetableFormat) -> eyre::Result<String> {
+    let svg = render_svg(schedule)?;
+
+    match format {
+        TimetableFormat::Svg => Ok(svg),
+        TimetableFormat::Html => {
+            let title = escape_html(title(schedule));
+
+            Ok(format!(
+                r#"<!DOCTYPE html>
+<html>
+<head>
+  <meta charset="utf-8">
+  <title>{}</title>
+  <style>
+    @page {{ size: A4 landscape; margin: 0; }}
+    body {{ margin: 0; print-color-adjust: exact; -webkit-print-color-adjust: exact; }}
+    svg {{ display: block; width: 297mm; height: 210mm; }}
+  </style>
+</head>
+<body>
+{}</body>
+</html>
+"#,
+                title, svg
+            ))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn render_timetable() {
+        let schedule: Schedule = serde_json::from_value(json!({
+            "calendar": { "name": "Plan <grupy 1>" },
+            "weeks": [],
+            "subjects": { "aisd": { "name": "Algorytmy", "short": "AiSD" } },
+            "teachers": { "kowalski": { "name": "dr Kowalski" } },
+            "schedule": [
+                { "subject": "aisd", "type": "lecture", "day": "monday", "time": "8:15-10:00",
+                  "location": { "building": "C3", "room": "204" }, "teachers": ["kowalski"] },
+                { "subject": "aisd", "type": "lab", "day": "wednesday", "time": "10:15-12:00",
+                  "weeks": { "parity": "odd" } },
+                { "subject": "aisd", "type": "lab", "day": "wednesday", "time": "10:15-12:00",
+                  "weeks": { "parity": "even" } }
+            ]
+        }))
+        .unwrap();
+
+        let svg = render(&schedule, TimetableFormat::Svg).unwrap();
+        assert!(svg.starts_with("<svg"));
+        assert!(svg.contains("Plan &lt;grupy 1&gt;"));
+        assert!(svg.contains(">08:00</text>"));
+        assert!(svg.contains(">11:00</text>"));
+        assert!(!svg.contains(">12:00</text>"));
+        assert!(svg.contains(">Fri</text>"));
+        assert!(!svg.contains(">Sat</text>"));
+        assert!(svg.contains(">204/C3</tspan>"));
+        assert!(svg.contains(">dr Kowalski</tspan>"));
+        assert!(svg.contains(">10:15-12:00 Laboratorium · odd weeks</tspan>"));
+        assert!(svg.contains(">10:15-12:00 Laboratorium · even weeks</tspan>"));
+        assert_eq!(svg.matches("stroke-dasharray").count(), 2);
+
+        // Labs in odd and even weeks are placed side by side
+        assert!(svg.contains(r#"<clipPath id="class-2-0-1015">"#));
+        assert!(svg.contains(r#"<clipPath id="class-2-1-1015">"#));
+
+        let html = render(&schedule, TimetableFormat::Html).unwrap();
+        assert!(html.contains("<title>Plan &lt;grupy 1&gt;</title>"));
+        assert!(html.contains("size: A4 landscape"));
+    }
+
+    #[test]
+    fn render_late_classes() {
+        let schedule: Schedule = serde_json::from_value(json!({
+            "weeks": [],
+            "subjects": { "ang": { "name": "Angielski" } },
+            "teachers": {},
+            "schedule": [
+                { "subject": "ang", "type": "languages", "day": "friday", "time": "22:00-23:30" }
+            ]
+        }))
+        .unwrap();
+
+        let svg = render(&schedule, TimetableFormat::Svg).unwrap();
+        assert!(svg.contains(">Plan zajęć</text>"));
+        assert!(svg.contains(">23:00</text>"));
+        assert!(!svg.contains(">24:00</text>"));
+        // The grid ends with a line at midnight
+        assert!(svg.contains(&format!(r#"y1="{:.1}""#, HEIGHT - MARGIN)));
+    }
+}
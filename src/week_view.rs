@@ -0,0 +1,436 @@
+//! Terminal view of a whole week of the schedule, used by `show --week`.
+
+use crate::calendar::class::Class;
+use crate::calendar::schedule::Schedule;
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime};
+use eyre::eyre;
+use qolor::color::BasicColor::{Green, Red};
+use qolor::shorthands::Formattable;
+use std::num::NonZero;
+
+/// Columns narrower than this make the view fall back to a list of days.
+const MIN_COLUMN_WIDTH: usize = 14;
+const SEPARATOR: &str = " │ ";
+
+/// Returns the width of the terminal, in columns.
+///
+/// Asks the terminal of the standard output first, then falls back to the `COLUMNS` environment variable
+/// (which most shells don't export), and to 80 columns.
+pub fn terminal_width() -> usize {
+    query_terminal_width()
+        .or_else(|| std::env::var("COLUMNS").ok()?.parse().ok())
+        .filter(|width| *width > 0)
+        .unwrap_or(80)
+}
+
+#[cfg(unix)]
+fn query_terminal_width() -> Option<usize> {
+    let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+    // SAFETY: TIOCGWINSZ only writes the size into the given struct, and fails if stdout isn't a terminal
+    let result = unsafe { libc::ioctl(libc::STDOUT_FILENO, libc::TIOCGWINSZ, &mut size) };
+
+    (result == 0 && size.ws_col > 0).then_some(size.ws_col as usize)
+}
+
+#[cfg(not(unix))]
+fn query_terminal_width() -> Option<usize> {
+    None
+}
+
+/// Parses the week to show, given as its number, `this` or `next`.
+///
+/// `this` is the week of the schedule falling on the same calendar week as `today`,
+/// and `next` is the first week of the schedule starting after it (e.g. after a break).
+pub fn parse_week(input: &str, schedule: &Schedule, today: NaiveDate) -> eyre::Result<NonZero<usize>> {
+    let week_number = |index: usize| NonZero::new(index + 1).unwrap();
+    let today_week = today.iso_week();
+
+    let index = match input {
+        "this" => schedule
+            .weeks
+            .iter()
+            .position(|week| week.iter().flatten().any(|date| date.iso_week() == today_week))
+            .ok_or_else(|| eyre!("the week of {} isn't in the schedule", today))?,
+        "next" => schedule
+            .weeks
+            .iter()
+            .position(|week| {
+                week.iter()
+                    .flatten()
+                    .min()
+                    .is_some_and(|first| *first > today && first.iso_week() != today_week)
+            })
+            .ok_or_else(|| eyre!("there are no weeks in the schedule after {}", today))?,
+        _ => {
+            let number: usize = input
+                .trim_start_matches('W')
+                .parse()
+                .map_err(|_| eyre!("Invalid week: {}, expected a number, \"this\" or \"next\"", input))?;
+            if number == 0 || number > schedule.weeks.len() {
+                return Err(eyre!("Week number out of range: {}", input));
+            }
+            number - 1
+        }
+    };
+
+    Ok(week_number(index))
+}
+
+/// Returns the number of terminal columns taken by the text, counting emoji as two columns.
+fn display_width(text: &str) -> usize {
+    text.chars()
+        .map(|c| match c as u32 {
+            0xFE00..=0xFE0F | 0x200D => 0,
+            0x1F000.. => 2,
+            _ => 1,
+        })
+        .sum()
+}
+
+/// Cuts the text to the given width, marking cut text with `…`, and pads it with spaces.
+fn fit(text: &str, width: usize) -> String {
+    let mut fitted = String::new();
+
+    if display_width(text) > width {
+        for c in text.chars() {
+            if display_width(&fitted) + display_width(&c.to_string()) + 1 > width {
+                break;
+            }
+            fitted.push(c);
+        }
+        fitted.push('…');
+    } else {
+        fitted.push_str(text);
+    }
+
+    let padding = width.saturating_sub(display_width(&fitted));
+    fitted + &" ".repeat(padding)
+}
+
+/// State of a class relative to the current time, deciding how it's highlighted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ClassState {
+    Ended,
+    Ongoing,
+    Upcoming,
+}
+
+pub fn class_state(class: &Class, date: NaiveDate, now: NaiveDateTime) -> ClassState {
+    if date.and_time(class.time.end) < now {
+        ClassState::Ended
+    } else if date.and_time(class.time.start) <= now {
+        ClassState::Ongoing
+    } else {
+        ClassState::Upcoming
+    }
+}
+
+/// Formats a line of a class, colored by its type, struck through if it has ended today
+/// and bold while it's ongoing.
+pub fn format_class_line(text: String, class: &Class, state: ClassState, today: bool) -> String {
+    let line = text.fg(class.class_type.to_color());
+
+    match state {
+        ClassState::Ended if today => line.strike().to_ansi(),
+        ClassState::Ongoing => line.bold().to_ansi(),
+        _ => line.to_ansi(),
+    }
+}
+
+/// Returns the lines describing a class: the summary from the schedule's templates, time and location.
+pub fn class_lines(schedule: &Schedule, class: &Class) -> eyre::Result<Vec<String>> {
+    let context = schedule.class_context(class)?;
+    let mut lines = vec![context.render(&schedule.templates.summary), class.time.to_string()];
+
+    let location = context.render(&schedule.templates.location);
+    if !location.is_empty() {
+        lines.push(location);
+    }
+
+    Ok(lines)
+}
+
+/// Renders the classes of a week as a grid with a column per weekday and a row per start time,
+/// or as a list of days if `width` is too narrow for the grid.
+pub fn render_week(
+    schedule: &Schedule,
+    week: NonZero<usize>,
+    now: NaiveDateTime,
+    width: usize,
+) -> eyre::Result<String> {
+    let days = schedule.week_days(week)?;
+    // Days which don't occur in the week have no classes
+    let classes: Vec<Vec<&Class>> = days
+        .iter()
+        .map(|(weekday, date)| {
+            let mut classes: Vec<_> = match date {
+                Some(_) => schedule.get_classes_on(week, *weekday).collect(),
+                None => Vec::new(),
+            };
+            classes.sort_by_key(|class| class.time.start);
+            classes
+        })
+        .collect();
+    let is_today = |date: &Option<NaiveDate>| *date == Some(now.date());
+
+    let first = days.iter().filter_map(|(_, date)| *date).min();
+    let last = days.iter().filter_map(|(_, date)| *date).max();
+    let title = match (first, last) {
+        (Some(first), Some(last)) => format!(" - Week {}, {} – {} - ", week, first, last),
+        _ => format!(" - Week {} - ", week),
+    };
+    let mut output = vec![title.bg(Green).to_ansi()];
+
+    let column_width = width.saturating_sub(display_width(SEPARATOR) * (days.len() - 1)) / days.len();
+
+    if column_width < MIN_COLUMN_WIDTH {
+        for ((weekday, date), classes) in days.iter().zip(&classes) {
+            let Some(date) = date else {
+                output.push(format!("{} (doesn't occur this week)", weekday).dim().to_ansi());
+                continue;
+            };
+
+            let today = is_today(&Some(*date));
+            let header = format!("{} {}", weekday, date);
+            output.push(if today { header.bg(Green).to_ansi() } else { header.bold().to_ansi() });
+
+            if classes.is_empty() {
+                output.push("  No classes".dim().to_ansi());
+            }
+            for class in classes {
+                let lines = class_lines(schedule, class)?;
+                let state = class_state(class, *date, now);
+                output.push(format_class_line(format!("  {}", lines.join(", ")), class, state, today));
+            }
+        }
+
+        return Ok(output.join("\n"));
+    }
+
+    let separator = SEPARATOR.dim().to_ansi();
+
+    let header: Vec<String> = days
+        .iter()
+        .map(|(weekday, date)| match date {
+            Some(day) => {
+                let cell = fit(&format!("{} {}", weekday, day.format("%d.%m")), column_width);
+                if is_today(date) { cell.bg(Green).to_ansi() } else { cell.bold().to_ansi() }
+            }
+            None => fit(&format!("{} –", weekday), column_width).dim().to_ansi(),
+        })
+        .collect();
+    output.push(header.join(&separator));
+
+    let mut start_times: Vec<NaiveTime> = classes.iter().flatten().map(|class| class.time.start).collect();
+    start_times.sort();
+    start_times.dedup();
+
+    let rule = vec!["─".repeat(column_width); days.len()].join("─┼─");
+
+    for start in start_times {
+        output.push(rule.clone().dim().to_ansi());
+
+        let cells = days
+            .iter()
+            .zip(&classes)
+            .map(|((_, date), classes)| {
+                let mut lines = Vec::new();
+                for class in classes.iter().filter(|class| class.time.start == start) {
+                    let state = date.map_or(ClassState::Upcoming, |date| class_state(class, date, now));
+                    for line in class_lines(schedule, class)? {
+                        lines.push((fit(&line, column_width), *class, state));
+                    }
+                }
+                Ok((is_today(date), lines))
+            })
+            .collect::<eyre::Result<Vec<_>>>()?;
+
+        let height = cells.iter().map(|(_, lines)| lines.len()).max().unwrap_or_default();
+
+        for row in 0..height {
+            let line: Vec<String> = cells
+                .iter()
+                .map(|(is_today, lines)| match lines.get(row) {
+                    Some((text, class, state)) => format_class_line(text.clone(), class, *state, *is_today),
+                    None => " ".repeat(column_width),
+                })
+                .collect();
+            output.push(line.join(&separator));
+        }
+    }
+
+    if output.len() == 2 {
+        output.push("No classes this week!".dim().to_ansi());
+    }
+
+    Ok(output.join("\n"))
+}
+
+/// Renders the `weeks` table of the schedule, with a row for each week and a column for each of its days.
+///
+/// Dates on which the classes of another weekday take place are marked with an asterisk
+/// and holidays with an exclamation mark, and the breaks of the semester rules, if any, are listed below the table.
+pub fn render_weeks_table(schedule: &Schedule) -> String {
+    let first = schedule.weeks.iter().flatten().flatten().min();
+    let last = schedule.weeks.iter().flatten().flatten().max();
+    let title = match (first, last) {
+        (Some(first), Some(last)) => format!(" - {} weeks, {} – {} - ", schedule.weeks.len(), first, last),
+        _ => " - No weeks - ".to_string(),
+    };
+    let mut output = vec![title.bg(Green).to_ansi()];
+
+    // Dates and the swap marker
+    let column_width = 11;
+    let header: Vec<String> = std::iter::once("Week".to_string())
+        .chain(schedule.days.iter().map(|day| fit(&day.to_string(), column_width)))
+        .collect();
+    output.push(header.join(SEPARATOR).bold().to_ansi());
+    output.push(
+        std::iter::once("────".to_string())
+            .chain(schedule.days.iter().map(|_| "─".repeat(column_width)))
+            .collect::<Vec<_>>()
+            .join("─┼─")
+            .dim()
+            .to_ansi(),
+    );
+
+    let mut swapped = false;
+    let mut holidays = Vec::new();
+    for (index, week) in schedule.weeks.iter().enumerate() {
+        let cells: Vec<String> = std::iter::once(format!("{:>4}", index + 1))
+            .chain(week.iter().zip(&schedule.days).map(|(date, weekday)| match date {
+                Some(date) if schedule.holidays.holiday_on(*date).is_some() => {
+                    holidays.push(*date);
+                    format!("{}!", date)
+                }
+                Some(date) if date.weekday() != *weekday => {
+                    swapped = true;
+                    format!("{}*", date)
+                }
+                Some(date) => fit(&date.to_string(), column_width),
+                None => fit("–", column_width).dim().to_ansi(),
+            }))
+            .collect();
+        output.push(cells.join(SEPARATOR).trim_end().to_string());
+    }
+
+    if swapped {
+        output.push("* on the schedule of another weekday".dim().to_ansi());
+    }
+    for date in holidays {
+        let holiday = schedule.holidays.holiday_on(date).unwrap_or_default();
+        output.push(format!("! {} is a holiday: {}", date, holiday).fg(Red).to_ansi());
+    }
+
+    if let Some(semester) = &schedule.semester {
+        for break_ in &semester.breaks {
+            let dates = if break_.from == break_.to {
+                break_.from.to_string()
+            } else {
+                format!("{} – {}", break_.from, break_.to)
+            };
+            let line = match &break_.name {
+                Some(name) => format!("Break: {} ({})", dates, name),
+                None => format!("Break: {}", dates),
+            };
+            output.push(line.dim().to_ansi());
+        }
+    }
+
+    output.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn schedule() -> Schedule {
+        serde_json::from_value(json!({
+            "weeks": [
+                ["2025-10-20", "2025-10-21", "2025-10-22", "2025-10-23", "2025-10-24"],
+                ["2025-11-03", "2025-11-04", "2025-11-05", "2025-11-06", "2025-11-07"]
+            ],
+            "subjects": { "aisd": { "name": "Algorytmy", "short": "AiSD" } },
+            "teachers": {},
+            "schedule": [
+                { "subject": "aisd", "type": "lecture", "day": "monday", "time": "8:15-10:00",
+                  "location": { "building": "C3", "room": "204" } },
+                { "subject": "aisd", "type": "lab", "day": "wednesday", "time": "8:15-10:00" },
+                { "subject": "aisd", "type": "exercise", "day": "friday", "time": "12:15-14:00", "location": "online" }
+            ]
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn parse_weeks() {
+        let schedule = schedule();
+        let date = |day| NaiveDate::from_ymd_opt(2025, 10, day).unwrap();
+
+        assert_eq!(parse_week("2", &schedule, date(1)).unwrap().get(), 2);
+        assert_eq!(parse_week("this", &schedule, date(26)).unwrap().get(), 1);
+        assert_eq!(parse_week("next", &schedule, date(22)).unwrap().get(), 2);
+        assert_eq!(parse_week("next", &schedule, date(1)).unwrap().get(), 1);
+        assert!(parse_week("this", &schedule, date(29)).is_err());
+        assert!(parse_week("3", &schedule, date(1)).is_err());
+        assert!(parse_week("soon", &schedule, date(1)).is_err());
+    }
+
+    #[test]
+    fn render_grid() {
+        let schedule = schedule();
+        let now = NaiveDate::from_ymd_opt(2025, 10, 22).unwrap().and_hms_opt(9, 0, 0).unwrap();
+        let week = NonZero::new(1).unwrap();
+
+        let grid = render_week(&schedule, week, now, 100).unwrap();
+        let lines: Vec<_> = grid.lines().collect();
+        assert_eq!(lines.len(), 10);
+        assert!(lines[1].starts_with("Mon 20.10         │ Tue 21.10         │"));
+        assert!(lines[3].starts_with("📚 AiSD           │                   │ 🧪 AiSD"));
+        assert!(lines[4].starts_with("08:15-10:00       │                   │ 08:15-10:00"));
+        assert!(lines[5].starts_with("204/C3            │"));
+        assert!(lines[7].ends_with("│ 🏋️ AiSD          "));
+        assert!(lines[9].ends_with("│ Online           "));
+
+        let list = render_week(&schedule, week, now, 40).unwrap();
+        assert!(list.contains("Mon 2025-10-20\n  📚 AiSD, 08:15-10:00, 204/C3\nTue 2025-10-21\n  No classes"));
+    }
+
+    #[test]
+    fn render_weeks() {
+        let schedule: Schedule = serde_json::from_value(json!({
+            "semester": {
+                "start": "2025-11-03",
+                "weeks": 2,
+                "breaks": [{ "from": "2025-11-10", "to": "2025-11-11", "name": "Święto Niepodległości" }],
+                "swaps": [{ "date": "2025-11-12", "day": "monday" }]
+            },
+            "subjects": {},
+            "teachers": {},
+            "schedule": []
+        }))
+        .unwrap();
+
+        let table = render_weeks_table(&schedule);
+        assert_eq!(
+            table.lines().collect::<Vec<_>>(),
+            vec![
+                " - 2 weeks, 2025-11-03 – 2025-11-14 - ",
+                "Week │ Mon         │ Tue         │ Wed         │ Thu         │ Fri        ",
+                "─────┼─────────────┼─────────────┼─────────────┼─────────────┼────────────",
+                "   1 │ 2025-11-03  │ 2025-11-04  │ 2025-11-05  │ 2025-11-06  │ 2025-11-07",
+                "   2 │ 2025-11-12* │ –           │ –           │ 2025-11-13  │ 2025-11-14",
+                "* on the schedule of another weekday",
+                "Break: 2025-11-10 – 2025-11-11 (Święto Niepodległości)",
+            ]
+        );
+    }
+
+    #[test]
+    fn fit_text() {
+        assert_eq!(fit("🧪 AiSD", 9), "🧪 AiSD  ");
+        assert_eq!(fit("Algorytmy", 6), "Algor…");
+        assert_eq!(fit("🏋️ AiSD", 4), "🏋️ …");
+    }
+}